@@ -0,0 +1,69 @@
+use crate::lexer::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Number(f64),
+    Identifier(String),
+    Prefix {
+        op: char,
+        operand: Box<Expr>,
+    },
+    Postfix {
+        op: char,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: char,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// `[a, b, ...]`, a vector, or a matrix whose rows are the items if they are vectors.
+    List(Vec<Expr>),
+    /// `value in target`, converting a quantity to the unit `target` evaluates to.
+    Convert {
+        value: Box<Expr>,
+        target: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Expr {
+        Expr { kind, span }
+    }
+
+    /// Source text of a number literal, without the parentheses or quotes it may be wrapped in.
+    pub fn literal<'s>(&self, src: &'s str) -> &'s str {
+        src[self.span.start..self.span.end]
+            .trim_matches(|c: char| c == '(' || c == ')' || c == '"' || c.is_whitespace())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Assign {
+        name: String,
+        value: Expr,
+    },
+    Define {
+        name: String,
+        params: Vec<String>,
+        body: Expr,
+    },
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
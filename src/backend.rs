@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::ast::*;
+use crate::errors::*;
+use crate::eval::{check_arity, spread_lists, UNSUPPORTED_CONVERSION, UNSUPPORTED_LIST};
+use crate::functions::Arity;
+use crate::lexer::Span;
+use crate::parser::parse_statements;
+
+/// Arithmetic used to evaluate expressions in the modes other than the default `f64` one, such as
+/// exact rationals. Errors returned as `String` are reported at the position of the offending
+/// node.
+pub trait Backend {
+    type Value: Clone + fmt::Debug;
+
+    /// Converts the source text of a number literal, such as `1.5e3`.
+    fn literal(&self, text: &str) -> Result<Self::Value, String>;
+
+    fn constant(&self, name: &str) -> Option<Self::Value>;
+
+    /// Applies a prefix operator (`+` or `-`) or the postfix `!`.
+    fn unary(&self, op: char, operand: Self::Value) -> Result<Self::Value, String>;
+
+    fn binary(&self, op: char, lhs: Self::Value, rhs: Self::Value) -> Result<Self::Value, String>;
+
+    /// Number of arguments taken by the function `name`, or `None` if there is no such function.
+    fn arity(&self, name: &str) -> Option<Arity>;
+
+    /// Calls the function `name`, which is only done after checking its arity.
+    fn call(&self, name: &str, args: &[Self::Value]) -> Result<Self::Value, String>;
+
+    /// Builds the value of `[a, b, ...]` from the values of its items.
+    fn list(&self, _items: Vec<Self::Value>) -> Result<Self::Value, String> {
+        Err(UNSUPPORTED_LIST.to_owned())
+    }
+
+    /// Converts `value` to the unit `target`, whose source text is `unit`, for `value in unit`.
+    fn convert(
+        &self,
+        _value: Self::Value,
+        _target: Self::Value,
+        _unit: &str,
+    ) -> Result<Self::Value, String> {
+        Err(UNSUPPORTED_CONVERSION.to_owned())
+    }
+}
+
+/// Evaluates statements with a [`Backend`], keeping the variables they assign.
+#[derive(Debug, Clone)]
+pub struct Session<B: Backend> {
+    backend: B,
+    variables: HashMap<String, B::Value>,
+}
+
+impl<B: Backend> Session<B> {
+    pub fn new(backend: B) -> Session<B> {
+        Session {
+            backend,
+            variables: HashMap::new(),
+        }
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: B::Value) {
+        self.variables.insert(name.to_owned(), value);
+    }
+
+    /// Every variable assigned so far, sorted by name.
+    pub fn variables(&self) -> Vec<(String, B::Value)> {
+        let variables = self
+            .variables
+            .clone()
+            .into_iter()
+            .collect::<BTreeMap<String, B::Value>>();
+
+        variables.into_iter().collect()
+    }
+
+    /// Parses and runs `src`, returning the value of the last statement. Function definitions
+    /// are only supported by [`crate::Context`].
+    pub fn execute(&mut self, src: &str) -> Result<Option<B::Value>, SyntaxError> {
+        let mut res = None;
+
+        for stmt in parse_statements(src)? {
+            res = match stmt.kind {
+                StmtKind::Expr(expr) => Some(self.eval(&expr, src)?),
+                StmtKind::Assign { name, value } => {
+                    if self.backend.constant(&name).is_some() {
+                        return Err(SyntaxError::at(
+                            src,
+                            stmt.span,
+                            format!("cannot assign to constant \"{}\"", name),
+                        ));
+                    }
+
+                    let value = self.eval(&value, src)?;
+                    self.variables.insert(name, value.clone());
+                    Some(value)
+                }
+                StmtKind::Define { .. } => {
+                    return Err(SyntaxError::at(
+                        src,
+                        stmt.span,
+                        "function definitions are not supported in this mode".to_owned(),
+                    ))
+                }
+            };
+        }
+
+        Ok(res)
+    }
+
+    /// Like [`Session::execute`], but requires the last statement to produce a value.
+    pub fn evaluate(&mut self, src: &str) -> Result<B::Value, SyntaxError> {
+        self.execute(src)?.ok_or_else(|| {
+            SyntaxError::at(
+                src,
+                Span::new(0, src.len()),
+                "expected an expression to evaluate".to_owned(),
+            )
+        })
+    }
+
+    fn eval(&self, expr: &Expr, src: &str) -> Result<B::Value, SyntaxError> {
+        let at = |msg: String| SyntaxError::at(src, expr.span, msg);
+
+        match &expr.kind {
+            ExprKind::Number(_) => self.backend.literal(expr.literal(src)).map_err(at),
+            ExprKind::Identifier(name) => self
+                .variables
+                .get(name)
+                .cloned()
+                .or_else(|| self.backend.constant(name))
+                .ok_or_else(|| at(format!("unknown variable \"{}\"", name))),
+            ExprKind::Prefix { op, operand } | ExprKind::Postfix { op, operand } => {
+                let operand = self.eval(operand, src)?;
+                self.backend.unary(*op, operand).map_err(at)
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.eval(lhs, src)?;
+                let rhs = self.eval(rhs, src)?;
+                self.backend.binary(*op, lhs, rhs).map_err(at)
+            }
+            ExprKind::Call { name, args } => {
+                let arity = match self.backend.arity(name) {
+                    Some(arity) => arity,
+                    None => return Err(at(format!("unkown function name \"{}\"", name))),
+                };
+
+                let args = spread_lists(name, arity, args);
+                check_arity(src, name, arity, &args, expr)?;
+
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg, src))
+                    .collect::<Result<Vec<B::Value>, SyntaxError>>()?;
+
+                self.backend.call(name, &args).map_err(at)
+            }
+            ExprKind::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| self.eval(item, src))
+                    .collect::<Result<Vec<B::Value>, SyntaxError>>()?;
+
+                self.backend.list(items).map_err(at)
+            }
+            ExprKind::Convert { value, target } => {
+                let unit = target.literal(src);
+                let value = self.eval(value, src)?;
+                let target = self.eval(target, src)?;
+                self.backend.convert(value, target, unit).map_err(at)
+            }
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use num_complex::Complex64;
+use phf::phf_map;
+use std::fmt;
+
+use crate::backend::Backend;
+use crate::eval::CONSTANTS;
+use crate::functions::Arity;
+use crate::math;
+use crate::parser::INTEGER_DIVISION;
+
+static UNARY_FUNCTIONS: phf::Map<&str, fn(Complex64) -> Complex64> = phf_map! {
+    "cos" => Complex64::cos,
+    "sin" => Complex64::sin,
+    "tan" => Complex64::tan,
+    "acos" => Complex64::acos,
+    "asin" => Complex64::asin,
+    "atan" => Complex64::atan,
+    "ln" => Complex64::ln,
+    "sqrt" => Complex64::sqrt,
+    "re" => |z| Complex64::new(z.re, 0.),
+    "im" => |z| Complex64::new(z.im, 0.),
+    "arg" => |z| Complex64::new(z.arg(), 0.),
+    "abs" => |z| Complex64::new(z.norm(), 0.),
+    "conj" => |z| z.conj(),
+};
+
+/// Parts smaller than this fraction of the modulus are printed as zero, so that rounding errors
+/// don't show up as tiny imaginary parts, as in `e^(i*pi)`.
+const NEGLIGIBLE_PART: f64 = 1e-15;
+
+/// Largest integer exponent whose powers are computed by repeated multiplication. Larger ones are
+/// computed from the logarithm, as the rounding errors of the multiplications grow with the
+/// exponent.
+const MAX_MULTIPLIED_POWER: f64 = 64.;
+
+/// Powers of `i`, indexed by the exponent modulo 4.
+const QUARTER_TURNS: [Complex64; 4] = [
+    Complex64::new(1., 0.),
+    Complex64::new(0., 1.),
+    Complex64::new(-1., 0.),
+    Complex64::new(0., -1.),
+];
+
+fn is_real(z: Complex64) -> bool {
+    z.im == 0.
+}
+
+/// Wraps the result of an operation, dropping the sign of zero parts, which would otherwise put
+/// real numbers such as `-1` on the wrong side of the branch cuts of `sqrt` and `ln`.
+fn value(z: Complex64) -> ComplexValue {
+    ComplexValue(Complex64::new(z.re + 0., z.im + 0.))
+}
+
+/// Divides real numbers as `f64`, keeping the infinities of dividing by zero and exact quotients.
+fn div(a: Complex64, b: Complex64) -> Complex64 {
+    if is_real(a) && is_real(b) {
+        Complex64::new(a.re / b.re, 0.)
+    } else {
+        a / b
+    }
+}
+
+fn pow(z: Complex64, w: Complex64) -> Result<Complex64, String> {
+    let is_integer = is_real(w) && w.re.fract() == 0.;
+
+    if z == Complex64::new(0., 0.) && w == z {
+        Err("0^0 is undefined".to_owned())
+    } else if is_real(z) && is_real(w) && (z.re >= 0. || is_integer) {
+        // Real powers, whose sign is exact even for large exponents, as in `(-1)^(3e9)`
+        Ok(Complex64::new(z.re.powf(w.re), 0.))
+    } else if is_integer && z.re == 0. && z.im.abs() == 1. {
+        // Powers of i and -i cycle every 4 exponents
+        let quarter = w.re.rem_euclid(4.) * z.im;
+        Ok(QUARTER_TURNS[quarter.rem_euclid(4.) as usize])
+    } else if is_integer && w.re.abs() <= MAX_MULTIPLIED_POWER {
+        // Repeated multiplication keeps powers of Gaussian integers such as `(1 + i)^8` exact
+        Ok(z.powi(w.re as i32))
+    } else if w == Complex64::new(0.5, 0.) {
+        Ok(z.sqrt())
+    } else if z == Complex64::new(0., 0.) {
+        if w.re > 0. {
+            Ok(z)
+        } else {
+            Ok(Complex64::new(f64::NAN, 0.))
+        }
+    } else {
+        Ok(z.powc(w))
+    }
+}
+
+/// Result of evaluating an expression in complex mode. Displays as `a + bi`, see
+/// [`ComplexValue::polar`] for the polar form.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComplexValue(Complex64);
+
+impl ComplexValue {
+    pub fn new(re: f64, im: f64) -> ComplexValue {
+        ComplexValue(Complex64::new(re, im))
+    }
+
+    pub fn re(&self) -> f64 {
+        self.0.re
+    }
+
+    pub fn im(&self) -> f64 {
+        self.0.im
+    }
+
+    /// The value written as `r * e^(θi)`, or just `r` for non-negative real numbers.
+    pub fn polar(&self) -> String {
+        let (r, theta) = self.cleaned().to_polar();
+
+        if theta == 0. || r == 0. {
+            format!("{}", r)
+        } else {
+            format!("{} * e^({}i)", r, theta)
+        }
+    }
+
+    fn cleaned(&self) -> Complex64 {
+        let z = self.0;
+        let threshold = z.norm() * NEGLIGIBLE_PART;
+
+        if !threshold.is_finite() {
+            return z;
+        }
+
+        let clean = |x: f64| if x.abs() < threshold { 0. } else { x };
+        Complex64::new(clean(z.re), clean(z.im))
+    }
+}
+
+impl fmt::Display for ComplexValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let z = self.cleaned();
+
+        let imaginary = |im: f64| match im {
+            1. => "i".to_owned(),
+            -1. => "-i".to_owned(),
+            _ if !im.is_finite() => format!("{}*i", im),
+            _ => format!("{}i", im),
+        };
+
+        if z.im == 0. {
+            write!(f, "{}", z.re)
+        } else if z.re == 0. {
+            write!(f, "{}", imaginary(z.im))
+        } else if z.im < 0. {
+            write!(f, "{} - {}", z.re, imaginary(-z.im))
+        } else {
+            write!(f, "{} + {}", z.re, imaginary(z.im))
+        }
+    }
+}
+
+/// Evaluates expressions over complex numbers, with `i` as the imaginary unit.
+#[derive(Debug, Clone, Default)]
+pub struct ComplexBackend;
+
+impl ComplexBackend {
+    pub fn new() -> ComplexBackend {
+        ComplexBackend
+    }
+}
+
+impl Backend for ComplexBackend {
+    type Value = ComplexValue;
+
+    fn literal(&self, text: &str) -> Result<ComplexValue, String> {
+        text.parse::<f64>()
+            .map(|x| ComplexValue::new(x, 0.))
+            .map_err(|_| "unkown token".to_owned())
+    }
+
+    fn constant(&self, name: &str) -> Option<ComplexValue> {
+        match name {
+            "i" => Some(ComplexValue::new(0., 1.)),
+            _ => CONSTANTS.get(name).map(|x| ComplexValue::new(*x, 0.)),
+        }
+    }
+
+    fn unary(&self, op: char, operand: ComplexValue) -> Result<ComplexValue, String> {
+        let z = operand.0;
+
+        match op {
+            '+' => Ok(operand),
+            '-' => Ok(value(-z)),
+            '!' if is_real(z) => Ok(ComplexValue::new(math::fact(z.re), 0.)),
+            '!' => Err("the factorial is only defined for real numbers".to_owned()),
+            _ => unreachable!(),
+        }
+    }
+
+    fn binary(
+        &self,
+        op: char,
+        lhs: ComplexValue,
+        rhs: ComplexValue,
+    ) -> Result<ComplexValue, String> {
+        let (a, b) = (lhs.0, rhs.0);
+
+        let res = match op {
+            '+' => a + b,
+            '-' => a - b,
+            '*' => a * b,
+            '/' => div(a, b),
+            '^' => pow(a, b)?,
+            '%' if is_real(a) && is_real(b) => Complex64::new(a.re % b.re, 0.),
+            '%' => return Err("the remainder is only defined for real numbers".to_owned()),
+            INTEGER_DIVISION if is_real(a) && is_real(b) => {
+                Complex64::new((a.re / b.re).trunc(), 0.)
+            }
+            INTEGER_DIVISION => {
+                return Err("integer division is only defined for real numbers".to_owned())
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(value(res))
+    }
+
+    fn arity(&self, name: &str) -> Option<Arity> {
+        match name {
+            "logab" | "nroot" => Some(Arity::Exact(2)),
+            _ if UNARY_FUNCTIONS.contains_key(name) => Some(Arity::Exact(1)),
+            _ => None,
+        }
+    }
+
+    fn call(&self, name: &str, args: &[ComplexValue]) -> Result<ComplexValue, String> {
+        let res = match (name, args) {
+            // log_a(b) = ln b / ln a
+            ("logab", [a, b]) => div(b.0.ln(), a.0.ln()),
+            ("nroot", [n, x]) => pow(x.0, n.0.inv())?,
+            (_, [z]) => UNARY_FUNCTIONS[name](z.0),
+            _ => unreachable!(),
+        };
+
+        Ok(value(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Session;
+
+    fn complex(src: &str) -> String {
+        Session::new(ComplexBackend::new())
+            .evaluate(src)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn complex_constants() {
+        assert_eq!(complex("i"), "i");
+        assert_eq!(complex("-i"), "-i");
+        assert_eq!(complex("i^2"), "-1");
+        assert_eq!(complex("e^(i*pi)"), "-1");
+        assert_eq!(complex("1 + 2*i"), "1 + 2i");
+        assert_eq!(complex("1 - i"), "1 - i");
+    }
+
+    #[test]
+    fn complex_operators() {
+        assert_eq!(complex("(1 + 2*i) * (3 - i)"), "5 + 5i");
+        assert_eq!(complex("(1 + i) / (1 - i)"), "i");
+        assert_eq!(complex("1/0"), "inf");
+        assert_eq!(complex("7 % 4"), "3");
+        assert_eq!(complex("3!"), "6");
+        assert_eq!(complex("(-1)^(3e9)"), "1");
+        assert_eq!(complex("i^(1e10)"), "1");
+        assert_eq!(complex("i^(1e10 + 1)"), "i");
+        assert_eq!(complex("(-i)^3"), "i");
+        assert_eq!(complex("i^-1"), "-i");
+        assert_eq!(complex("(1 + i)^8"), "16");
+        assert_eq!(
+            complex("i^i"),
+            format!("{}", (-std::f64::consts::FRAC_PI_2).exp())
+        );
+    }
+
+    #[test]
+    fn complex_functions() {
+        assert_eq!(complex("sqrt(-1)"), "i");
+        assert_eq!(complex("sqrt(-4)"), "2i");
+        assert_eq!(complex("ln(-1)"), format!("{}i", std::f64::consts::PI));
+        assert_eq!(complex("re(3 + 4*i)"), "3");
+        assert_eq!(complex("im(3 + 4*i)"), "4");
+        assert_eq!(complex("abs(3 + 4*i)"), "5");
+        assert_eq!(
+            complex("arg(i)"),
+            format!("{}", std::f64::consts::FRAC_PI_2)
+        );
+        assert_eq!(complex("conj(3 + 4*i)"), "3 - 4i");
+        assert_eq!(complex("logab(2, 8)"), "3");
+        assert_eq!(complex("nroot(2, -9)"), "3i");
+        assert_eq!(complex("cos(i)"), format!("{}", 1f64.cosh()));
+        assert_eq!(complex("atan(i)"), "inf*i");
+        assert_eq!(
+            complex("re(asin(2))"),
+            format!("{}", std::f64::consts::FRAC_PI_2)
+        );
+    }
+
+    #[test]
+    fn complex_polar() {
+        let polar = |src| {
+            Session::new(ComplexBackend::new())
+                .evaluate(src)
+                .unwrap()
+                .polar()
+        };
+
+        assert_eq!(polar("2"), "2");
+        assert_eq!(
+            polar("2*i"),
+            format!("2 * e^({}i)", std::f64::consts::FRAC_PI_2)
+        );
+        assert_eq!(polar("-1"), format!("1 * e^({}i)", std::f64::consts::PI));
+        assert_eq!(polar("0"), "0");
+    }
+
+    #[test]
+    fn complex_errors() {
+        let mut session = Session::new(ComplexBackend::new());
+
+        assert_eq!(
+            format!("{}", session.evaluate("i!").expect_err("")),
+            "error while parsing token \"i!\" in expression \"i!\": the factorial is only defined for real numbers, i! <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", session.evaluate("i % 2").expect_err("")),
+            "error while parsing token \"i % 2\" in expression \"i % 2\": the remainder is only defined for real numbers, i % 2 <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", session.evaluate("0^0").expect_err("")),
+            "error while parsing token \"0^0\" in expression \"0^0\": 0^0 is undefined, 0^0 <-- HERE"
+        );
+    }
+}
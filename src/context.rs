@@ -0,0 +1,385 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::ast::*;
+use crate::errors::*;
+use crate::eval::{Evaluator, CONSTANTS};
+use crate::functions::*;
+use crate::lexer::Span;
+use crate::parser::parse_statements;
+
+/// A function defined from an expression, such as `f(x) = x^2`.
+#[derive(Debug, Clone)]
+pub(crate) struct Definition {
+    pub params: Vec<String>,
+    pub body: Expr,
+    /// Text the definition was parsed from, used to report errors raised while evaluating `body`.
+    pub src: String,
+}
+
+/// Constants and functions available while evaluating an expression, on top of the built-in ones,
+/// along with the variables and functions defined by previous evaluations.
+#[derive(Debug, Clone)]
+pub struct Context {
+    variables: HashMap<String, f64>,
+    constants: HashMap<String, f64>,
+    functions: FunctionRegistry,
+    definitions: HashMap<String, Definition>,
+    /// Behind a `RefCell` so that evaluation, which only borrows the context, can raise warnings.
+    warnings: RefCell<Vec<String>>,
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context::with_functions(FunctionRegistry::builtin())
+    }
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context::default()
+    }
+
+    /// A context whose only functions are the ones in `functions`.
+    pub fn with_functions(functions: FunctionRegistry) -> Context {
+        Context {
+            variables: HashMap::new(),
+            constants: HashMap::new(),
+            functions,
+            definitions: HashMap::new(),
+            warnings: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Assigns a variable, as `name = value` would. Variables take precedence over constants.
+    pub fn set_variable(&mut self, name: &str, value: f64) {
+        self.variables.insert(name.to_owned(), value);
+    }
+
+    pub fn variable(&self, name: &str) -> Option<f64> {
+        self.variables.get(name).copied()
+    }
+
+    /// Every variable assigned so far, sorted by name.
+    pub fn variables(&self) -> Vec<(String, f64)> {
+        let variables = self
+            .variables
+            .clone()
+            .into_iter()
+            .collect::<BTreeMap<String, f64>>();
+
+        variables.into_iter().collect()
+    }
+
+    fn is_constant(&self, name: &str) -> bool {
+        self.constants.contains_key(name) || CONSTANTS.contains_key(name)
+    }
+
+    /// Whether `name` is a variable or constant, built-in or not.
+    pub(crate) fn is_defined(&self, name: &str) -> bool {
+        self.variables.contains_key(name) || self.is_constant(name)
+    }
+
+    /// Defines a constant, shadowing any built-in constant with the same name.
+    pub fn set_constant(&mut self, name: &str, value: f64) {
+        self.constants.insert(name.to_owned(), value);
+    }
+
+    pub fn constant(&self, name: &str) -> Option<f64> {
+        self.constants.get(name).copied()
+    }
+
+    /// Defines a function taking exactly `arity` arguments, shadowing any built-in function with
+    /// the same name. See [`Context::functions_mut`] to register functions with metadata or a
+    /// variable number of arguments.
+    pub fn set_function(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[f64]) -> f64 + Send + Sync + 'static,
+    ) {
+        let params = (1..=arity)
+            .map(|i| format!("x{}", i))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        self.functions.register(
+            name,
+            Function::new(
+                &format!("{}({})", name, params),
+                "",
+                Arity::Exact(arity),
+                move |args| Ok(function(args)),
+            ),
+        );
+    }
+
+    pub fn functions(&self) -> &FunctionRegistry {
+        &self.functions
+    }
+
+    pub fn functions_mut(&mut self) -> &mut FunctionRegistry {
+        &mut self.functions
+    }
+
+    pub(crate) fn definition(&self, name: &str) -> Option<&Definition> {
+        self.definitions.get(name)
+    }
+
+    /// Returns the warnings raised since the last call, such as a definition shadowing a built-in
+    /// function.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    pub(crate) fn warn(&self, warning: String) {
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    /// Every constant visible from this context, built-in or not, sorted by name.
+    pub fn constants(&self) -> Vec<(String, f64)> {
+        let mut constants = CONSTANTS
+            .entries()
+            .map(|(name, value)| (name.to_string(), *value))
+            .collect::<BTreeMap<String, f64>>();
+        constants.extend(self.constants.clone());
+
+        constants.into_iter().collect()
+    }
+
+    /// Every function defined from an expression, as pairs of signature and body, sorted by name.
+    pub fn definitions(&self) -> Vec<(String, String)> {
+        let definitions = self
+            .definitions
+            .iter()
+            .map(|(name, definition)| {
+                (
+                    name.clone(),
+                    (
+                        format!("{}({})", name, definition.params.join(", ")),
+                        definition.src[definition.body.span.start..definition.body.span.end]
+                            .to_owned(),
+                    ),
+                )
+            })
+            .collect::<BTreeMap<String, (String, String)>>();
+
+        definitions.into_values().collect()
+    }
+
+    /// Parses and runs `src`, which may hold several statements separated by `;` or newlines.
+    /// Variables and functions defined along the way are kept in this context. Returns the value
+    /// of the last statement, or `None` if it was a function definition.
+    pub fn execute(&mut self, src: &str) -> Result<Option<f64>, SyntaxError> {
+        let mut res = None;
+
+        for stmt in parse_statements(src)? {
+            res = match stmt.kind {
+                StmtKind::Expr(expr) => Some(Evaluator::new(src, self).eval(&expr)?),
+                StmtKind::Assign { name, value } => {
+                    if self.is_constant(&name) {
+                        return Err(SyntaxError::at(
+                            src,
+                            stmt.span,
+                            format!("cannot assign to constant \"{}\"", name),
+                        ));
+                    }
+
+                    let value = Evaluator::new(src, self).eval(&value)?;
+                    self.variables.insert(name, value);
+                    Some(value)
+                }
+                StmtKind::Define { name, params, body } => {
+                    if self.functions.contains(&name) {
+                        self.warn(format!(
+                            "function {} shadows a built-in function with the same name",
+                            name
+                        ));
+                    }
+
+                    self.definitions.insert(
+                        name,
+                        Definition {
+                            params,
+                            body,
+                            src: src.to_owned(),
+                        },
+                    );
+                    None
+                }
+            };
+        }
+
+        Ok(res)
+    }
+
+    /// Like [`Context::execute`], but requires the last statement to produce a value.
+    pub fn evaluate(&mut self, src: &str) -> Result<f64, SyntaxError> {
+        self.execute(src)?.ok_or_else(|| {
+            SyntaxError::at(
+                src,
+                Span::new(0, src.len()),
+                "expected an expression to evaluate".to_owned(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_constant() {
+        let mut ctx = Context::new();
+        ctx.set_constant("g", 9.81);
+
+        assert_eq!(ctx.evaluate("2*g").unwrap(), 19.62);
+        assert_eq!(ctx.constant("g"), Some(9.81));
+    }
+
+    #[test]
+    fn context_constant_shadows_builtin() {
+        let mut ctx = Context::new();
+        ctx.set_constant("pi", 3.);
+
+        assert_eq!(ctx.evaluate("pi").unwrap(), 3.);
+    }
+
+    #[test]
+    fn context_variables() {
+        let mut ctx = Context::new();
+
+        assert_eq!(ctx.evaluate("x = 3; x^2").unwrap(), 9.);
+        assert_eq!(ctx.evaluate("y = x + 1").unwrap(), 4.);
+        assert_eq!(ctx.evaluate("x * y").unwrap(), 12.);
+        assert_eq!(
+            ctx.variables(),
+            vec![("x".to_owned(), 3.), ("y".to_owned(), 4.)]
+        );
+    }
+
+    #[test]
+    fn context_variable_shadows_constant() {
+        let mut ctx = Context::new();
+        ctx.set_constant("g", 9.81);
+        ctx.set_variable("g", 10.);
+
+        assert_eq!(ctx.evaluate("g").unwrap(), 10.);
+    }
+
+    #[test]
+    fn context_assign_constant() {
+        let mut ctx = Context::new();
+
+        assert_eq!(
+            format!("{}", ctx.evaluate("pi = 3").expect_err("")),
+            "error while parsing token \"pi = 3\" in expression \"pi = 3\": cannot assign to constant \"pi\", pi = 3 <-- HERE"
+        );
+    }
+
+    #[test]
+    fn context_definitions() {
+        let mut ctx = Context::new();
+
+        assert_eq!(ctx.execute("f(x, y) = x^2 + y").unwrap(), None);
+        assert_eq!(ctx.evaluate("f(3, 1)").unwrap(), 10.);
+        assert_eq!(
+            ctx.definitions(),
+            vec![("f(x, y)".to_owned(), "x^2 + y".to_owned())]
+        );
+        assert!(ctx.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn context_definition_shadows_builtin() {
+        let mut ctx = Context::new();
+
+        ctx.execute("sqrt(x) = x / 2").unwrap();
+        assert_eq!(ctx.evaluate("sqrt(16)").unwrap(), 8.);
+        assert_eq!(
+            ctx.take_warnings(),
+            vec!["function sqrt shadows a built-in function with the same name".to_owned()]
+        );
+        assert!(ctx.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn context_definition_without_value() {
+        let mut ctx = Context::new();
+
+        assert_eq!(
+            format!("{}", ctx.evaluate("f(x) = x").expect_err("")),
+            "error while parsing token \"f(x) = x\" in expression \"f(x) = x\": expected an expression to evaluate, f(x) = x <-- HERE"
+        );
+    }
+
+    #[test]
+    fn context_registered_function() {
+        let mut ctx = Context::new();
+        ctx.functions_mut().register(
+            "max",
+            Function::new(
+                "max(x, ...)",
+                "largest argument",
+                Arity::AtLeast(1),
+                |args| Ok(args.iter().cloned().fold(f64::NEG_INFINITY, f64::max)),
+            ),
+        );
+        ctx.functions_mut().register(
+            "checked_sqrt",
+            Function::new("checked_sqrt(x)", "", Arity::Exact(1), |args| {
+                if args[0] < 0. {
+                    Err("cannot take the square root of a negative number".to_owned())
+                } else {
+                    Ok(args[0].sqrt())
+                }
+            }),
+        );
+
+        assert_eq!(ctx.evaluate("max(1, 5, 3)").unwrap(), 5.);
+        assert_eq!(ctx.evaluate("max(2)").unwrap(), 2.);
+        assert_eq!(
+            format!("{}", ctx.evaluate("max()").expect_err("")),
+            "error while parsing token \"max()\" in expression \"max()\": incorrect number of arguments passed, function max takes at least 1 parameters but 0 were passed, max( <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", ctx.evaluate("1 + checked_sqrt(-1)").expect_err("")),
+            "error while parsing token \"checked_sqrt(-1)\" in expression \"1 + checked_sqrt(-1)\": cannot take the square root of a negative number, 1 + checked_sqrt(-1) <-- HERE"
+        );
+    }
+
+    #[test]
+    fn context_without_builtins() {
+        let mut ctx = Context::with_functions(FunctionRegistry::new());
+
+        assert_eq!(
+            format!("{}", ctx.evaluate("sqrt(4)").expect_err("")),
+            "error while parsing token \"sqrt(4)\" in expression \"sqrt(4)\": unkown function name \"sqrt\", sqrt(4) <-- HERE"
+        );
+    }
+
+    #[test]
+    fn context_listing() {
+        let mut ctx = Context::new();
+        ctx.set_constant("g", 9.81);
+        ctx.set_function("hypot", 2, |args| args[0].hypot(args[1]));
+
+        assert!(ctx.constants().contains(&("g".to_owned(), 9.81)));
+        assert!(ctx.constants().iter().any(|(name, _)| name == "pi"));
+        assert!(ctx.functions().contains("hypot"));
+        assert!(ctx.functions().contains("logab"));
+    }
+
+    #[test]
+    fn context_function() {
+        let mut ctx = Context::new();
+        ctx.set_function("hypot", 2, |args| args[0].hypot(args[1]));
+
+        assert_eq!(ctx.evaluate("hypot(3,4)").unwrap(), 5.);
+        assert_eq!(
+            format!("{}", ctx.evaluate("hypot(3)").expect_err("")),
+            "error while parsing token \"hypot(3)\" in expression \"hypot(3)\": incorrect number of arguments passed, function hypot takes 2 parameters but 1 was passed, hypot(3 <-- HERE"
+        );
+    }
+}
@@ -0,0 +1,553 @@
+use std::fmt;
+
+use crate::ast::*;
+use crate::context::Context;
+use crate::errors::*;
+use crate::eval::{check_arity, spread_lists, Binding, Evaluator};
+use crate::functions::Arity;
+use crate::lexer::Span;
+use crate::parser::{parse_expression, INTEGER_DIVISION};
+use crate::solver::contains;
+use crate::symbolic::*;
+
+/// How deep calls to user defined functions may nest while they are expanded, so that recursive
+/// definitions are reported instead of expanded forever.
+const MAX_EXPANSION_DEPTH: usize = 16;
+
+/// Symbolic derivative of an expression, see [`differentiate`].
+#[derive(Debug, Clone)]
+pub struct Derivative {
+    src: String,
+    variable: String,
+    expr: Expr,
+}
+
+impl Derivative {
+    /// Value of the derivative where the variable it was taken with respect to is `value`. Other
+    /// names are looked up in `ctx`.
+    pub fn at(&self, ctx: &Context, value: f64) -> Result<f64, SyntaxError> {
+        let mut ctx = ctx.clone();
+        ctx.set_variable(&self.variable, value);
+
+        Evaluator::new(&self.src, &ctx).eval(&self.expr)
+    }
+}
+
+impl fmt::Display for Derivative {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format(&self.expr))
+    }
+}
+
+/// Replaces every name in `params` inside `body` with the matching argument, and moves the rest
+/// of `body` to `span`, so that errors point at the call it was expanded from. Variables bound by
+/// functions such as `integrate` are left alone, names being looked up in `ctx`.
+fn substitute(body: &Expr, params: &[String], args: &[Expr], span: Span, ctx: &Context) -> Expr {
+    let map = |expr: &Expr| Box::new(substitute(expr, params, args, span, ctx));
+
+    let kind = match &body.kind {
+        ExprKind::Identifier(name) => match params.iter().position(|param| param == name) {
+            Some(i) => return args[i].clone(),
+            None => ExprKind::Identifier(name.clone()),
+        },
+        ExprKind::Number(value) => ExprKind::Number(*value),
+        ExprKind::Prefix { op, operand } => ExprKind::Prefix {
+            op: *op,
+            operand: map(operand),
+        },
+        ExprKind::Postfix { op, operand } => ExprKind::Postfix {
+            op: *op,
+            operand: map(operand),
+        },
+        ExprKind::Binary { op, lhs, rhs } => ExprKind::Binary {
+            op: *op,
+            lhs: map(lhs),
+            rhs: map(rhs),
+        },
+        ExprKind::Call { name, args: inner } => {
+            let binding = Binding::of(name, inner, ctx);
+            let free = |i: usize| {
+                params
+                    .iter()
+                    .map(|param| match &binding {
+                        Some(binding) if !binding.is_free(inner, i, param) => String::new(),
+                        _ => param.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            ExprKind::Call {
+                name: name.clone(),
+                args: inner
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| substitute(arg, &free(i), args, span, ctx))
+                    .collect(),
+            }
+        }
+        ExprKind::List(items) => ExprKind::List(
+            items
+                .iter()
+                .map(|item| substitute(item, params, args, span, ctx))
+                .collect(),
+        ),
+        ExprKind::Convert { value, target } => ExprKind::Convert {
+            value: map(value),
+            target: map(target),
+        },
+    };
+
+    Expr::new(kind, span)
+}
+
+struct Differentiator<'a> {
+    src: &'a str,
+    ctx: &'a Context,
+    variable: &'a str,
+}
+
+impl<'a> Differentiator<'a> {
+    fn error(&self, span: Span, msg: String) -> SyntaxError {
+        SyntaxError::at(self.src, span, msg)
+    }
+
+    fn is_constant(&self, expr: &Expr) -> bool {
+        !contains(expr, self.variable, self.ctx)
+    }
+
+    /// Whether `expr` is the built-in constant `e`, which `ln` cancels out.
+    fn is_euler(&self, expr: &Expr) -> bool {
+        matches!(&expr.kind, ExprKind::Identifier(name) if name == "e")
+            && self.ctx.variable("e").is_none()
+            && self.ctx.constant("e").is_none()
+    }
+
+    /// Derivative of `expr` with respect to the variable, simplified along the way. `depth` is
+    /// the number of user defined functions `expr` was expanded from.
+    fn derivative(&self, expr: &Expr, depth: usize) -> Result<Expr, SyntaxError> {
+        let span = expr.span;
+        let zero = || number(0., span);
+
+        if self.is_constant(expr) {
+            return Ok(zero());
+        }
+
+        match &expr.kind {
+            ExprKind::Number(_) => Ok(zero()),
+            ExprKind::Identifier(_) => Ok(number(1., span)),
+            ExprKind::Prefix { op, operand } => {
+                let d = self.derivative(operand, depth)?;
+
+                match op {
+                    '-' => Ok(neg(d)),
+                    _ => Ok(d),
+                }
+            }
+            // (u!)' = u! ψ(u + 1) u'
+            ExprKind::Postfix { operand, .. } => {
+                let d = self.derivative(operand, depth)?;
+                let digamma = call(
+                    "polygamma",
+                    vec![zero(), add(*operand.clone(), number(1., span))],
+                    span,
+                );
+
+                Ok(mul(mul(expr.clone(), digamma), d))
+            }
+            ExprKind::Binary { op, lhs, rhs } => self.binary(*op, lhs, rhs, depth),
+            ExprKind::Call { name, args } => self.call(name, args, expr, depth),
+            ExprKind::List(_) => Err(self.error(
+                span,
+                "vectors and matrices can't be differentiated".to_owned(),
+            )),
+            ExprKind::Convert { .. } => {
+                Err(self.error(span, "unit conversions can't be differentiated".to_owned()))
+            }
+        }
+    }
+
+    fn binary(&self, op: char, u: &Expr, v: &Expr, depth: usize) -> Result<Expr, SyntaxError> {
+        let (du, dv) = (self.derivative(u, depth)?, self.derivative(v, depth)?);
+        let (u, v) = (u.clone(), v.clone());
+        let span = u.span.to(v.span);
+
+        match op {
+            '+' => Ok(add(du, dv)),
+            '-' => Ok(sub(du, dv)),
+            // (u v)' = u' v + u v'
+            '*' => Ok(add(mul(du, v.clone()), mul(u, dv))),
+            // (u / v)' = u' / v when v is constant
+            '/' if self.is_constant(&v) => Ok(div(du, v)),
+            // (u / v)' = (u' v - u v') / v^2
+            '/' => Ok(div(
+                sub(mul(du, v.clone()), mul(u, dv)),
+                pow(v, number(2., span)),
+            )),
+            // (u^n)' = n u^(n - 1) u'
+            '^' if self.is_constant(&v) => {
+                Ok(mul(mul(v.clone(), pow(u, sub(v, number(1., span)))), du))
+            }
+            // (e^v)' = e^v v'
+            '^' if self.is_euler(&u) => Ok(mul(pow(u, v), dv)),
+            // (a^v)' = a^v ln(a) v'
+            '^' if self.is_constant(&u) => {
+                let ln = call("ln", vec![u.clone()], u.span);
+                Ok(mul(mul(pow(u, v), ln), dv))
+            }
+            // (u^v)' = u^v (v' ln(u) + v u' / u)
+            '^' => {
+                let ln = call("ln", vec![u.clone()], u.span);
+                let factor = add(mul(dv, ln), div(mul(v.clone(), du), u.clone()));
+                Ok(mul(pow(u, v), factor))
+            }
+            // u % v = u - v trunc(u / v), where the truncated quotient is constant almost everywhere
+            '%' if self.is_constant(&v) => Ok(du),
+            '%' => {
+                let quotient = div(sub(u.clone(), binary('%', u, v.clone())), v);
+                Ok(sub(du, mul(dv, quotient)))
+            }
+            // The truncated quotient is constant almost everywhere
+            INTEGER_DIVISION => Ok(number(0., u.span.to(v.span))),
+            _ => unreachable!(),
+        }
+    }
+
+    fn call(
+        &self,
+        name: &str,
+        args: &[Expr],
+        expr: &Expr,
+        depth: usize,
+    ) -> Result<Expr, SyntaxError> {
+        let span = expr.span;
+
+        // Definitions are expanded, as they take precedence over built-in functions
+        if let Some(definition) = self.ctx.definition(name) {
+            check_arity(
+                self.src,
+                name,
+                Arity::Exact(definition.params.len()),
+                args,
+                expr,
+            )?;
+
+            if depth >= MAX_EXPANSION_DEPTH {
+                return Err(self.error(
+                    span,
+                    format!(
+                        "maximum expansion depth of {} exceeded, recursive functions can't be differentiated",
+                        MAX_EXPANSION_DEPTH
+                    ),
+                ));
+            }
+
+            let body = substitute(&definition.body, &definition.params, args, span, self.ctx);
+            return self.derivative(&body, depth + 1);
+        }
+
+        let cannot = || self.error(span, format!("cannot differentiate function \"{}\"", name));
+
+        let function = self.ctx.functions().get(name).ok_or_else(cannot)?;
+        let args = &spread_lists(name, function.arity(), args)[..];
+        check_arity(self.src, name, function.arity(), args, expr)?;
+        let is_series = Binding::of(name, args, self.ctx).is_some();
+
+        let u = args.last().unwrap();
+        let du = || self.derivative(u, depth);
+        let u = u.clone();
+        let one = || number(1., span);
+        let square = |u: Expr| pow(u, number(2., span));
+
+        match (name, args) {
+            ("cos", [_]) => Ok(neg(mul(call("sin", vec![u], span), du()?))),
+            ("sin", [_]) => Ok(mul(call("cos", vec![u], span), du()?)),
+            ("tan", [_]) => Ok(div(du()?, square(call("cos", vec![u], span)))),
+            ("acos", [_]) => Ok(neg(div(
+                du()?,
+                call("sqrt", vec![sub(one(), square(u))], span),
+            ))),
+            ("asin", [_]) => Ok(div(du()?, call("sqrt", vec![sub(one(), square(u))], span))),
+            ("atan", [_]) => Ok(div(du()?, add(one(), square(u)))),
+            ("ln", [_]) => Ok(div(du()?, u)),
+            ("sqrt", [_]) => Ok(div(du()?, mul(number(2., span), expr.clone()))),
+            // logab(a, b) = ln(b) / ln(a)
+            ("logab", [a, _]) if self.is_constant(a) => {
+                Ok(div(du()?, mul(u, call("ln", vec![a.clone()], a.span))))
+            }
+            ("logab", [a, b]) => {
+                let ln = |x: &Expr| call("ln", vec![x.clone()], x.span);
+                self.derivative(&div(ln(b), ln(a)), depth)
+            }
+            // nroot(n, x) = x^(1 / n), so its derivative is nroot(n, x) / (n x) x'
+            ("nroot", [n, _]) if self.is_constant(n) => {
+                Ok(mul(div(expr.clone(), mul(n.clone(), u)), du()?))
+            }
+            ("nroot", [n, x]) => self.derivative(&pow(x.clone(), div(one(), n.clone())), depth),
+            ("polygamma", [n, _]) if self.is_constant(n) => {
+                let order = add(n.clone(), one());
+                Ok(mul(call("polygamma", vec![order, u], span), du()?))
+            }
+            ("polygamma", [n, _]) => Err(self.error(
+                n.span,
+                "polygamma can only be differentiated with respect to its second argument"
+                    .to_owned(),
+            )),
+            // The condition only changes at isolated points, where the derivative is undefined
+            ("if", [condition, then, otherwise]) => Ok(call(
+                "if",
+                vec![
+                    condition.clone(),
+                    self.derivative(then, depth)?,
+                    self.derivative(otherwise, depth)?,
+                ],
+                span,
+            )),
+            // Leibniz rule, d/dx ∫_a^b f dt = f(b) b' - f(a) a' + ∫_a^b ∂f/∂x dt
+            ("integrate", [f, t, a, b]) => {
+                let name = match &t.kind {
+                    ExprKind::Identifier(name) => name,
+                    _ => {
+                        return Err(self.error(t.span, "expected the name of a variable".to_owned()))
+                    }
+                };
+                let at = |x: &Expr| {
+                    substitute(
+                        f,
+                        std::slice::from_ref(name),
+                        std::slice::from_ref(x),
+                        span,
+                        self.ctx,
+                    )
+                };
+
+                let mut res = sub(
+                    mul(at(b), self.derivative(b, depth)?),
+                    mul(at(a), self.derivative(a, depth)?),
+                );
+
+                if name != self.variable && !self.is_constant(f) {
+                    let df = self.derivative(f, depth)?;
+                    res = add(
+                        res,
+                        call("integrate", vec![df, t.clone(), a.clone(), b.clone()], span),
+                    );
+                }
+
+                Ok(res)
+            }
+            // d/dx f^(n)(a) = f^(n+1)(a) a', as long as f itself doesn't depend on x
+            ("deriv", [f, t, a, n])
+                if self.is_constant(n)
+                    && (self.is_constant(f)
+                        || matches!(&t.kind, ExprKind::Identifier(t) if t == self.variable)) =>
+            {
+                let order = add(n.clone(), one());
+                let deriv = call("deriv", vec![f.clone(), t.clone(), a.clone(), order], span);
+                Ok(mul(deriv, self.derivative(a, depth)?))
+            }
+            ("sum" | "prod", [_, from, to, _])
+                if is_series && (!self.is_constant(from) || !self.is_constant(to)) =>
+            {
+                Err(self.error(
+                    span,
+                    format!("the bounds of {} can't depend on {}", name, self.variable),
+                ))
+            }
+            ("sum", [k, from, to, f]) if is_series => Ok(call(
+                "sum",
+                vec![
+                    k.clone(),
+                    from.clone(),
+                    to.clone(),
+                    self.derivative(f, depth)?,
+                ],
+                span,
+            )),
+            // (∏ f)' = ∏ f ∑ f' / f
+            ("prod", [k, from, to, f]) => {
+                let df = div(self.derivative(f, depth)?, f.clone());
+                let sum = call("sum", vec![k.clone(), from.clone(), to.clone(), df], span);
+                Ok(mul(expr.clone(), sum))
+            }
+            ("sum", _) => {
+                let derivatives = args
+                    .iter()
+                    .map(|arg| self.derivative(arg, depth))
+                    .collect::<Result<Vec<Expr>, SyntaxError>>()?;
+                Ok(call("sum", derivatives, span))
+            }
+            _ => Err(cannot()),
+        }
+    }
+}
+
+/// Differentiates the expression `src` with respect to `variable`, returning a simplified
+/// symbolic derivative. Other names are taken as constants, and functions defined in `ctx` are
+/// expanded.
+pub fn differentiate(ctx: &Context, src: &str, variable: &str) -> Result<Derivative, SyntaxError> {
+    let expr = parse_expression(src)?;
+    let differentiator = Differentiator { src, ctx, variable };
+
+    Ok(Derivative {
+        src: src.to_owned(),
+        variable: variable.to_owned(),
+        expr: differentiator.derivative(&expr, 0)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(src: &str) -> String {
+        differentiate(&Context::new(), src, "x")
+            .unwrap()
+            .to_string()
+    }
+
+    /// Compares the derivative of `src` at `x` with a central difference.
+    fn assert_matches_numeric(src: &str, x: f64) {
+        let ctx = Context::new();
+        let derivative = differentiate(&ctx, src, "x").unwrap();
+        let f = |x: f64| {
+            let mut ctx = ctx.clone();
+            ctx.set_variable("x", x);
+            ctx.evaluate(src).unwrap()
+        };
+
+        let h = 1e-5;
+        let expected = (f(x + h) - f(x - h)) / (2. * h);
+        let res = derivative.at(&ctx, x).unwrap();
+
+        assert!(
+            (res - expected).abs() <= 1e-6 * expected.abs().max(1.),
+            "d/dx {} = {} is {} at {}, expected {}",
+            src,
+            derivative,
+            res,
+            x,
+            expected
+        );
+    }
+
+    #[test]
+    fn diff_operators() {
+        assert_eq!(diff("x^2*sin(x)"), "2x * sin(x) + x^2 * cos(x)");
+        assert_eq!(diff("3x + 2"), "3");
+        assert_eq!(diff("x^3 - x"), "3x^2 - 1");
+        assert_eq!(diff("-x^2"), "-2x");
+        assert_eq!(diff("1/x"), "-1 / x^2");
+        assert_eq!(diff("x/2"), "0.5");
+        assert_eq!(diff("2^x"), "2^x * ln(2)");
+        assert_eq!(diff("e^x"), "e^x");
+        assert_eq!(diff("e^(2x)"), "2 * e^(2x)");
+        assert_eq!(diff("x^x"), "x^x * (ln(x) + 1)");
+        assert_eq!(diff("x % 3"), "1");
+        assert_eq!(diff("x // 3"), "0");
+        assert_eq!(diff("x!"), "x! * polygamma(0, x + 1)");
+        assert_eq!(diff("a x + b"), "a");
+        assert_eq!(diff("y"), "0");
+    }
+
+    #[test]
+    fn diff_functions() {
+        assert_eq!(diff("sin(2x)"), "2cos(2x)");
+        assert_eq!(diff("cos(x)"), "-sin(x)");
+        assert_eq!(diff("tan(x)"), "1 / cos(x)^2");
+        assert_eq!(diff("ln(x^2)"), "2x / x^2");
+        assert_eq!(diff("sqrt(x)"), "1 / (2sqrt(x))");
+        assert_eq!(diff("atan(x)"), "1 / (1 + x^2)");
+        assert_eq!(diff("asin(x)"), "1 / sqrt(1 - x^2)");
+        assert_eq!(diff("acos(x)"), "-1 / sqrt(1 - x^2)");
+        assert_eq!(diff("logab(2, x)"), "1 / (x * ln(2))");
+        assert_eq!(diff("nroot(3, x)"), "nroot(3, x) / (3x)");
+        assert_eq!(diff("polygamma(1, x)"), "polygamma(2, x)");
+        assert_eq!(diff("if(x, x^2, 0)"), "if(x, 2x, 0)");
+        assert_eq!(diff("integrate(t^2, t, 0, x)"), "x^2");
+        assert_eq!(diff("integrate(x^2, x, 0, 1)"), "0");
+        assert_eq!(diff("integrate(x t, t, 0, 1)"), "integrate(t, t, 0, 1)");
+        assert_eq!(diff("sum(k, 1, 3, x^k)"), "sum(k, 1, 3, k * x^(k - 1))");
+        assert_eq!(diff("sum(x, 1, 3, x^2)"), "0");
+        assert_eq!(diff("sum(x^2, 3x, [2, x])"), "sum(2x, 3, 0, 1)");
+        assert_eq!(diff("deriv(x^3, x, 2x, 1)"), "2deriv(x^3, x, 2x, 2)");
+        assert_eq!(
+            diff("prod(k, 1, 3, x + k)"),
+            "prod(k, 1, 3, x + k) * sum(k, 1, 3, 1 / (x + k))"
+        );
+    }
+
+    #[test]
+    fn diff_matches_numeric() {
+        let cases = [
+            "x^2*sin(x)",
+            "e^(x^2) / (1 + x)",
+            "x^x",
+            "x^sin(x)",
+            "(x^2 + 1)^0.5",
+            "x % (x / 3 + 1)",
+            "(x + 1)!",
+            "tan(x) * acos(x / 2)",
+            "asin(x / 2) - atan(1 / x)",
+            "logab(x + 2, x^2 + 1)",
+            "logab(10, x)",
+            "nroot(x + 2, x^2 + 1)",
+            "nroot(3, x^2 + 1)",
+            "sqrt(ln(x + 3))",
+            "polygamma(0, x^2 + 1)",
+            "-x^-2 + 3/x",
+            "integrate(sin(x t), t, x, x^2)",
+            "prod(k, 1, 4, x + k) + sum(k, 0, inf, x^k / k!)",
+        ];
+
+        for src in cases {
+            for x in [0.7, 1.3] {
+                assert_matches_numeric(src, x);
+            }
+        }
+    }
+
+    #[test]
+    fn diff_definitions() {
+        let mut ctx = Context::new();
+        ctx.execute("f(t) = t^2 + a t; g(x, y) = f(x) y; h(t) = integrate(t, t, 0, 1) t")
+            .unwrap();
+        ctx.set_variable("a", 3.);
+
+        let derivative = differentiate(&ctx, "g(x, 2)", "x").unwrap();
+        assert_eq!(derivative.to_string(), "2 * (2x + a)");
+        assert_eq!(derivative.at(&ctx, 1.).unwrap(), 10.);
+        assert_eq!(
+            differentiate(&ctx, "h(x^2)", "x").unwrap().to_string(),
+            "2 * integrate(t, t, 0, 1) * x"
+        );
+
+        // A variable named e is no longer Euler's number
+        ctx.set_variable("e", 2.);
+        assert_eq!(
+            differentiate(&ctx, "e^x", "x").unwrap().to_string(),
+            "e^x * ln(e)"
+        );
+    }
+
+    #[test]
+    fn diff_errors() {
+        let mut ctx = Context::new();
+        ctx.set_function("double", 1, |args| args[0] * 2.);
+        ctx.execute("f(n) = if(n, n f(n - 1), 1)").unwrap();
+
+        assert_eq!(
+            format!("{}", differentiate(&ctx, "1 + double(x)", "x").expect_err("")),
+            "error while parsing token \"double(x)\" in expression \"1 + double(x)\": cannot differentiate function \"double\", 1 + double(x) <-- HERE"
+        );
+        assert!(differentiate(&ctx, "f(x)", "x").is_err());
+        assert!(differentiate(&ctx, "polygamma(x, 1)", "x").is_err());
+        assert!(differentiate(&ctx, "sum(k, 1, x, k)", "x").is_err());
+        assert!(differentiate(&ctx, "deriv(x t, t, 1, 1)", "x").is_err());
+        assert!(differentiate(&ctx, "limit(x t, t, 1)", "x").is_err());
+        assert!(differentiate(&ctx, "x = 1", "x").is_err());
+        assert_eq!(
+            format!("{}", differentiate(&ctx, "y x", "x").unwrap().at(&ctx, 1.).expect_err("")),
+            "error while parsing token \"y\" in expression \"y x\": unknown variable \"y\", y <-- HERE"
+        );
+    }
+}
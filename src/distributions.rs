@@ -0,0 +1,750 @@
+use std::f64::consts::{PI, SQRT_2};
+
+use crate::functions::{Arity, Function, FunctionRegistry};
+use crate::math;
+
+/// Most factors of the products giving binomial and Poisson probabilities directly, which is exact
+/// for small ones, past which rounding errors add up and the saddle point expansion is used.
+const MAX_PRODUCT_TERMS: f64 = 30.;
+
+/// A probability distribution, whose functions are registered as `{NAME}pdf`, `{NAME}cdf` and
+/// `{NAME}inv`.
+trait Distribution: Sized {
+    const NAME: &'static str;
+    /// Names of the parameters in signatures.
+    const PARAMETERS: &'static str;
+    const DESCRIPTION: &'static str;
+    /// Least and most number of parameters, the missing ones taking default values.
+    const PARAMETER_COUNT: (usize, usize);
+    /// Discrete distributions take values among integers, and take their parameters before the
+    /// point or probability, as on calculators.
+    const DISCRETE: bool = false;
+
+    fn new(params: &[f64]) -> Result<Self, String>;
+
+    /// Probability density at `x`, or probability of `x` for discrete distributions.
+    fn pdf(&self, x: f64) -> f64;
+
+    /// Probability of a value at most `x`.
+    fn cdf(&self, x: f64) -> f64;
+
+    /// Smallest value whose cumulative probability is at least `p`, which is between 0 and 1.
+    fn quantile(&self, p: f64) -> f64;
+}
+
+/// Finds the smallest `x` such that `cdf(x) >= p` by bisection, `cdf` being continuous and
+/// increasing over `support`.
+fn invert(p: f64, support: (f64, f64), cdf: impl Fn(f64) -> f64) -> f64 {
+    if p == 0. {
+        return support.0;
+    } else if p == 1. {
+        return support.1;
+    }
+
+    let mut lower = if support.0.is_finite() {
+        support.0
+    } else {
+        -1.
+    };
+    let mut upper = if support.1.is_finite() { support.1 } else { 1. };
+
+    while cdf(lower) > p && lower.is_finite() {
+        lower *= 2.;
+    }
+    while cdf(upper) < p && upper.is_finite() {
+        upper = 2. * upper.max(1.);
+    }
+
+    loop {
+        let middle = lower + (upper - lower) / 2.;
+        if middle <= lower || middle >= upper {
+            return upper;
+        }
+
+        if cdf(middle) < p {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+}
+
+/// Finds the smallest integer `k` from 0 to `max` such that `cdf(k) >= p` by bisection. Past 2^53,
+/// where not every integer is an `f64`, it is only found up to the spacing between them.
+fn invert_discrete(p: f64, max: f64, cdf: impl Fn(f64) -> f64) -> f64 {
+    if p == 1. {
+        return max;
+    }
+
+    let mut upper = 1f64.min(max);
+    while upper < max && cdf(upper) < p {
+        upper = (2. * upper).min(max);
+    }
+
+    // cdf(lower) < p <= cdf(upper)
+    let mut lower = -1.;
+    while upper - lower > 1. {
+        let middle = ((lower + upper) / 2.).floor();
+        if middle <= lower || middle >= upper {
+            break;
+        }
+
+        if cdf(middle) < p {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+
+    upper
+}
+
+fn is_count(k: f64) -> bool {
+    k >= 0. && k == k.trunc()
+}
+
+struct Normal {
+    mean: f64,
+    deviation: f64,
+}
+
+impl Normal {
+    fn standard_cdf(z: f64) -> f64 {
+        0.5 * math::erfc(-z / SQRT_2)
+    }
+}
+
+impl Distribution for Normal {
+    const NAME: &'static str = "norm";
+    const PARAMETERS: &'static str = "mu, sigma";
+    const DESCRIPTION: &'static str =
+        "normal distribution of mean mu and standard deviation sigma, 0 and 1 by default";
+    const PARAMETER_COUNT: (usize, usize) = (0, 2);
+
+    fn new(params: &[f64]) -> Result<Normal, String> {
+        let mean = params.first().copied().unwrap_or(0.);
+        let deviation = params.get(1).copied().unwrap_or(1.);
+
+        if deviation.is_nan() || deviation <= 0. {
+            return Err(
+                "the standard deviation of a normal distribution must be positive".to_owned(),
+            );
+        }
+
+        Ok(Normal { mean, deviation })
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        let z = (x - self.mean) / self.deviation;
+        (-z * z / 2.).exp() / (self.deviation * (2. * PI).sqrt())
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        Normal::standard_cdf((x - self.mean) / self.deviation)
+    }
+
+    fn quantile(&self, p: f64) -> f64 {
+        let z = invert(p, (f64::NEG_INFINITY, f64::INFINITY), Normal::standard_cdf);
+        self.mean + self.deviation * z
+    }
+}
+
+fn degrees_of_freedom(nu: f64) -> Result<f64, String> {
+    if nu.is_nan() || nu <= 0. {
+        return Err("the degrees of freedom must be positive".to_owned());
+    }
+
+    Ok(nu)
+}
+
+/// Degrees of freedom above which the t distribution is taken as the standard normal one, which
+/// it is within rounding.
+const NORMAL_DEGREES_OF_FREEDOM: f64 = 1e17;
+
+const STANDARD_NORMAL: Normal = Normal {
+    mean: 0.,
+    deviation: 1.,
+};
+
+struct StudentT {
+    nu: f64,
+}
+
+impl Distribution for StudentT {
+    const NAME: &'static str = "t";
+    const PARAMETERS: &'static str = "nu";
+    const DESCRIPTION: &'static str = "Student's t distribution with nu degrees of freedom";
+    const PARAMETER_COUNT: (usize, usize) = (1, 1);
+
+    fn new(params: &[f64]) -> Result<StudentT, String> {
+        Ok(StudentT {
+            nu: degrees_of_freedom(params[0])?,
+        })
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        let nu = self.nu;
+        if nu > NORMAL_DEGREES_OF_FREEDOM {
+            return STANDARD_NORMAL.pdf(x);
+        }
+
+        let ln =
+            -0.5 * nu.ln() - math::ln_beta(0.5, nu / 2.) - (nu + 1.) / 2. * (x * x / nu).ln_1p();
+        ln.exp()
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        if self.nu > NORMAL_DEGREES_OF_FREEDOM {
+            return STANDARD_NORMAL.cdf(x);
+        }
+
+        let x2 = x * x;
+
+        // Near the median, the tail is close to 1/2 and would cancel out, so the probability is
+        // taken from the complementary function
+        if x2 < self.nu {
+            let center = 0.5 * math::beta_reg(0.5, self.nu / 2., x2 / (self.nu + x2));
+            return 0.5 + center.copysign(x);
+        }
+
+        let tail = 0.5 * math::beta_reg(self.nu / 2., 0.5, self.nu / (self.nu + x2));
+
+        if x > 0. {
+            1. - tail
+        } else {
+            tail
+        }
+    }
+
+    fn quantile(&self, p: f64) -> f64 {
+        // Bisection would stop at the first point rounding to the median
+        if p == 0.5 {
+            return 0.;
+        } else if self.nu > NORMAL_DEGREES_OF_FREEDOM {
+            return STANDARD_NORMAL.quantile(p);
+        }
+
+        invert(p, (f64::NEG_INFINITY, f64::INFINITY), |x| self.cdf(x))
+    }
+}
+
+struct ChiSquared {
+    k: f64,
+}
+
+impl Distribution for ChiSquared {
+    const NAME: &'static str = "chi2";
+    const PARAMETERS: &'static str = "k";
+    const DESCRIPTION: &'static str = "chi-squared distribution with k degrees of freedom";
+    const PARAMETER_COUNT: (usize, usize) = (1, 1);
+
+    fn new(params: &[f64]) -> Result<ChiSquared, String> {
+        Ok(ChiSquared {
+            k: degrees_of_freedom(params[0])?,
+        })
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        let half = self.k / 2.;
+
+        match x {
+            _ if x < 0. => 0.,
+            0. if half < 1. => f64::INFINITY,
+            0. if half == 1. => 0.5,
+            0. => 0.,
+            _ => ((half - 1.) * x.ln() - x / 2. - half * 2f64.ln() - math::ln_gamma(half)).exp(),
+        }
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        if x <= 0. {
+            0.
+        } else {
+            math::gamma_p(self.k / 2., x / 2.)
+        }
+    }
+
+    fn quantile(&self, p: f64) -> f64 {
+        invert(p, (0., f64::INFINITY), |x| self.cdf(x))
+    }
+}
+
+struct FisherF {
+    d1: f64,
+    d2: f64,
+}
+
+impl Distribution for FisherF {
+    const NAME: &'static str = "f";
+    const PARAMETERS: &'static str = "d1, d2";
+    const DESCRIPTION: &'static str = "F distribution with d1 and d2 degrees of freedom";
+    const PARAMETER_COUNT: (usize, usize) = (2, 2);
+
+    fn new(params: &[f64]) -> Result<FisherF, String> {
+        Ok(FisherF {
+            d1: degrees_of_freedom(params[0])?,
+            d2: degrees_of_freedom(params[1])?,
+        })
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        let (d1, d2) = (self.d1, self.d2);
+
+        match x {
+            _ if x < 0. => 0.,
+            0. if d1 < 2. => f64::INFINITY,
+            0. if d1 == 2. => 1.,
+            0. => 0.,
+            _ => (0.5 * (d1 * d1.ln() + d2 * d2.ln()) + (d1 / 2. - 1.) * x.ln()
+                - (d1 + d2) / 2. * (d1 * x + d2).ln()
+                - math::ln_beta(d1 / 2., d2 / 2.))
+            .exp(),
+        }
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        match x {
+            _ if x <= 0. => 0.,
+            f64::INFINITY => 1.,
+            _ => math::beta_reg(
+                self.d1 / 2.,
+                self.d2 / 2.,
+                self.d1 * x / (self.d1 * x + self.d2),
+            ),
+        }
+    }
+
+    fn quantile(&self, p: f64) -> f64 {
+        invert(p, (0., f64::INFINITY), |x| self.cdf(x))
+    }
+}
+
+struct Binomial {
+    n: f64,
+    p: f64,
+}
+
+impl Distribution for Binomial {
+    const NAME: &'static str = "binom";
+    const PARAMETERS: &'static str = "n, p";
+    const DESCRIPTION: &'static str =
+        "binomial distribution of the number of successes in n trials of probability p";
+    const PARAMETER_COUNT: (usize, usize) = (2, 2);
+    const DISCRETE: bool = true;
+
+    fn new(params: &[f64]) -> Result<Binomial, String> {
+        let (n, p) = (params[0], params[1]);
+
+        if !is_count(n) || n.is_infinite() {
+            return Err("the number of trials must be a non-negative integer".to_owned());
+        } else if !(0. ..=1.).contains(&p) {
+            return Err("the probability of success must be between 0 and 1".to_owned());
+        }
+
+        Ok(Binomial { n, p })
+    }
+
+    fn pdf(&self, k: f64) -> f64 {
+        let (n, p) = (self.n, self.p);
+
+        if !is_count(k) || k > n {
+            return 0.;
+        }
+
+        let q = 1. - p;
+
+        if k == 0. {
+            return q.powf(n);
+        } else if k == n {
+            return p.powf(n);
+        } else if p == 0. || q == 0. {
+            return 0.;
+        }
+
+        // Exact for small coefficients, such as 120 for binompdf(10, 0.5, 3)
+        let terms = k.min(n - k);
+        if terms <= MAX_PRODUCT_TERMS {
+            let coefficient =
+                (1..=terms as u64).fold(1., |c, i| c * (n - terms + i as f64) / i as f64);
+            let res = coefficient * p.powf(k) * q.powf(n - k);
+
+            if res.is_finite() && res != 0. {
+                return res;
+            }
+        }
+
+        // Saddle point expansion, as in Loader's "Fast and Accurate Computation of Binomial
+        // Probabilities"
+        let exponent = math::stirling_error(n)
+            - math::stirling_error(k)
+            - math::stirling_error(n - k)
+            - math::deviance(k, n * p)
+            - math::deviance(n - k, n * q);
+
+        exponent.exp() * (n / (2. * PI * k * (n - k))).sqrt()
+    }
+
+    fn cdf(&self, k: f64) -> f64 {
+        let k = k.floor();
+
+        if k < 0. {
+            0.
+        } else if k >= self.n {
+            1.
+        } else {
+            math::beta_reg(self.n - k, k + 1., 1. - self.p)
+        }
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        invert_discrete(q, self.n, |k| self.cdf(k))
+    }
+}
+
+struct Poisson {
+    lambda: f64,
+}
+
+impl Distribution for Poisson {
+    const NAME: &'static str = "poisson";
+    const PARAMETERS: &'static str = "lambda";
+    const DESCRIPTION: &'static str = "Poisson distribution of mean lambda";
+    const PARAMETER_COUNT: (usize, usize) = (1, 1);
+    const DISCRETE: bool = true;
+
+    fn new(params: &[f64]) -> Result<Poisson, String> {
+        let lambda = params[0];
+
+        if lambda.is_nan() || lambda <= 0. || lambda.is_infinite() {
+            return Err("the mean of a Poisson distribution must be positive".to_owned());
+        }
+
+        Ok(Poisson { lambda })
+    }
+
+    fn pdf(&self, k: f64) -> f64 {
+        if !is_count(k) {
+            return 0.;
+        }
+
+        if k == 0. {
+            return (-self.lambda).exp();
+        }
+
+        if k <= MAX_PRODUCT_TERMS {
+            let res = (-self.lambda).exp() * self.lambda.powf(k) / math::fact(k);
+            if res.is_finite() && res != 0. {
+                return res;
+            }
+        }
+
+        (-math::stirling_error(k) - math::deviance(k, self.lambda)).exp() / (2. * PI * k).sqrt()
+    }
+
+    fn cdf(&self, k: f64) -> f64 {
+        if k < 0. {
+            0.
+        } else {
+            math::gamma_q(k.floor() + 1., self.lambda)
+        }
+    }
+
+    fn quantile(&self, q: f64) -> f64 {
+        invert_discrete(q, f64::INFINITY, |k| self.cdf(k))
+    }
+}
+
+struct Exponential {
+    rate: f64,
+}
+
+impl Distribution for Exponential {
+    const NAME: &'static str = "exp";
+    const PARAMETERS: &'static str = "lambda";
+    const DESCRIPTION: &'static str = "exponential distribution of rate lambda, 1 by default";
+    const PARAMETER_COUNT: (usize, usize) = (0, 1);
+
+    fn new(params: &[f64]) -> Result<Exponential, String> {
+        let rate = params.first().copied().unwrap_or(1.);
+
+        if rate.is_nan() || rate <= 0. {
+            return Err("the rate of an exponential distribution must be positive".to_owned());
+        }
+
+        Ok(Exponential { rate })
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        if x < 0. {
+            0.
+        } else {
+            self.rate * (-self.rate * x).exp()
+        }
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        if x < 0. {
+            0.
+        } else {
+            -(-self.rate * x).exp_m1()
+        }
+    }
+
+    fn quantile(&self, p: f64) -> f64 {
+        -(-p).ln_1p() / self.rate
+    }
+}
+
+struct Uniform {
+    a: f64,
+    b: f64,
+}
+
+impl Distribution for Uniform {
+    const NAME: &'static str = "unif";
+    const PARAMETERS: &'static str = "a, b";
+    const DESCRIPTION: &'static str = "uniform distribution from a to b, 0 and 1 by default";
+    const PARAMETER_COUNT: (usize, usize) = (0, 2);
+
+    fn new(params: &[f64]) -> Result<Uniform, String> {
+        let a = params.first().copied().unwrap_or(0.);
+        let b = params.get(1).copied().unwrap_or(1.);
+
+        if !(a.is_finite() && b.is_finite()) || a >= b {
+            return Err(
+                "the bounds of a uniform distribution must be finite and increasing".to_owned(),
+            );
+        }
+
+        Ok(Uniform { a, b })
+    }
+
+    fn pdf(&self, x: f64) -> f64 {
+        if (self.a..=self.b).contains(&x) {
+            1. / (self.b - self.a)
+        } else {
+            0.
+        }
+    }
+
+    fn cdf(&self, x: f64) -> f64 {
+        ((x - self.a) / (self.b - self.a)).clamp(0., 1.)
+    }
+
+    fn quantile(&self, p: f64) -> f64 {
+        self.a + p * (self.b - self.a)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Pdf,
+    Cdf,
+    Inv,
+}
+
+/// Evaluates a function of `D`, whose arguments are the point or probability, and the parameters
+/// before or after it.
+fn call<D: Distribution>(kind: Kind, args: &[f64]) -> Result<f64, String> {
+    let (x, params) = if D::DISCRETE {
+        let (x, params) = args.split_last().unwrap();
+        (*x, params)
+    } else {
+        let (x, params) = args.split_first().unwrap();
+        (*x, params)
+    };
+
+    let distribution = D::new(params)?;
+
+    match kind {
+        Kind::Pdf => Ok(distribution.pdf(x)),
+        Kind::Cdf => Ok(distribution.cdf(x)),
+        Kind::Inv if (0. ..=1.).contains(&x) => Ok(distribution.quantile(x)),
+        Kind::Inv => Err(format!(
+            "the probability passed to {}inv must be between 0 and 1",
+            D::NAME
+        )),
+    }
+}
+
+fn register<D: Distribution>(registry: &mut FunctionRegistry) {
+    let (min, max) = D::PARAMETER_COUNT;
+    let arity = if min == max {
+        Arity::Exact(min + 1)
+    } else {
+        Arity::Between(min + 1, max + 1)
+    };
+
+    let point = if D::DISCRETE { "k" } else { "x" };
+    let density = if D::DISCRETE {
+        "probability of k for the".to_owned()
+    } else {
+        "probability density at x of the".to_owned()
+    };
+
+    let functions = [
+        ("pdf", Kind::Pdf, point, density),
+        (
+            "cdf",
+            Kind::Cdf,
+            point,
+            format!("probability of a value at most {} for the", point),
+        ),
+        (
+            "inv",
+            Kind::Inv,
+            "p",
+            "smallest value whose cumulative probability is at least p for the".to_owned(),
+        ),
+    ];
+
+    for (suffix, kind, point, description) in functions {
+        let name = format!("{}{}", D::NAME, suffix);
+        let signature = if D::DISCRETE {
+            format!("{}({}, {})", name, D::PARAMETERS, point)
+        } else {
+            format!("{}({}, {})", name, point, D::PARAMETERS)
+        };
+
+        registry.register(
+            &name,
+            Function::new(
+                &signature,
+                &format!("{} {}", description, D::DESCRIPTION),
+                arity,
+                move |args| call::<D>(kind, args),
+            ),
+        );
+    }
+}
+
+/// Adds the probability density, cumulative distribution and quantile functions of every
+/// distribution to `registry`.
+pub(crate) fn register_all(registry: &mut FunctionRegistry) {
+    register::<Normal>(registry);
+    register::<StudentT>(registry);
+    register::<ChiSquared>(registry);
+    register::<FisherF>(registry);
+    register::<Binomial>(registry);
+    register::<Poisson>(registry);
+    register::<Exponential>(registry);
+    register::<Uniform>(registry);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(name: &str, args: &[f64]) -> Result<f64, String> {
+        FunctionRegistry::builtin()
+            .get(name)
+            .and_then(|f| f.native())
+            .unwrap()(args)
+    }
+
+    /// Reference values computed with mpmath.
+    fn assert_values(cases: &[(&str, &[f64], f64)]) {
+        for (name, args, expected) in cases {
+            let res = evaluate(name, args).unwrap();
+            assert!(
+                (res - expected).abs() <= 1e-12 * expected.abs(),
+                "{}{:?} = {}, expected {}",
+                name,
+                args,
+                res,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn distributions_continuous() {
+        assert_values(&[
+            ("normcdf", &[1.96], 0.9750021048517795),
+            ("normcdf", &[-3.], 0.0013498980316300946),
+            ("normcdf", &[-10.], 7.619853024160526e-24),
+            ("normcdf", &[2., 1., 2.], 0.6914624612740131),
+            ("normpdf", &[0.5], 0.3520653267642995),
+            ("norminv", &[0.975], 1.959963984540054),
+            ("norminv", &[1e-10], -6.361340902404056),
+            ("tcdf", &[2.1, 5.], 0.9551233750577006),
+            ("tcdf", &[-1.5, 3.], 0.11529193262241153),
+            ("tcdf", &[1e-8, 3.], 0.500000003675526),
+            ("tcdf", &[-1e-8, 3.], 0.499999996324474),
+            ("tcdf", &[0.3, 0.5], 0.5775704239347546),
+            ("tpdf", &[0.7, 4.], 0.2809088317119511),
+            ("tinv", &[0.975, 12.], 2.178812829667229),
+            ("tinv", &[0.05, 2.5], -2.5582186141359366),
+            ("tcdf", &[1.96, 1e16], 0.9750021048517795),
+            ("tpdf", &[1., 1e16], 0.24197072451914337),
+            ("tinv", &[0.975, 1e16], 1.959963984540054),
+            ("tcdf", &[1.96, 1e300], 0.9750021048517795),
+            ("chi2cdf", &[3.84, 1.], 0.9499564787512949),
+            ("chi2cdf", &[10., 7.], 0.81142653248655),
+            ("chi2pdf", &[2.5, 3.], 0.18072239266818127),
+            ("chi2inv", &[0.95, 10.], 18.307038053275147),
+            ("chi2inv", &[0.5, 1e6], 999999.3333334123),
+            ("chi2cdf", &[1e300, 1e300], 0.5),
+            ("fcdf", &[2.5, 3., 10.], 0.8809604373417218),
+            ("fpdf", &[1.2, 5., 8.], 0.38561738204373023),
+            ("finv", &[0.95, 4., 20.], 2.8660814020156586),
+            ("fcdf", &[1., 1e300, 1e300], 0.5),
+            ("expcdf", &[2., 1.5], 0.950212931632136),
+            ("exppdf", &[2., 1.5], 0.07468060255179591),
+            ("expinv", &[0.950212931632136, 1.5], 2.),
+            ("unifcdf", &[0.25], 0.25),
+            ("unifpdf", &[3., 2., 6.], 0.25),
+            ("unifinv", &[0.5, 2., 6.], 4.),
+        ]);
+
+        assert_eq!(evaluate("tcdf", &[0., 3.]), Ok(0.5));
+        assert_eq!(evaluate("tinv", &[0.5, 3.]), Ok(0.));
+        assert!((evaluate("tinv", &[0.500000003675526, 3.]).unwrap() - 1e-8).abs() < 1e-15);
+        assert_eq!(evaluate("norminv", &[1.]), Ok(f64::INFINITY));
+        assert_eq!(evaluate("chi2inv", &[0., 3.]), Ok(0.));
+        assert_eq!(evaluate("chi2pdf", &[0., 2.]), Ok(0.5));
+        assert_eq!(evaluate("unifpdf", &[7., 2., 6.]), Ok(0.));
+    }
+
+    #[test]
+    fn distributions_discrete() {
+        assert_values(&[
+            ("binompdf", &[10., 0.5, 3.], 0.1171875),
+            ("binompdf", &[50., 0.3, 12.], 0.08382972003732648),
+            ("binompdf", &[5000., 0.5, 2500.], 0.011283227495479844),
+            ("binomcdf", &[20., 0.4, 7.], 0.41589293755753562),
+            ("poissonpdf", &[3., 5.], 0.10081881344492448),
+            ("poissonpdf", &[1000., 1000.], 0.0126146113487215),
+            ("poissoncdf", &[4.5, 6.], 0.8310505787254114),
+            ("poissoncdf", &[1e6, 1e6], 0.5002659614862837),
+            ("binomcdf", &[1e300, 0.5, 5e299], 0.5),
+        ]);
+
+        assert_eq!(evaluate("binompdf", &[10., 0.5, 3.]), Ok(0.1171875));
+        assert_eq!(evaluate("binompdf", &[10., 0.5, 2.5]), Ok(0.));
+        assert_eq!(evaluate("binompdf", &[3., 0., 0.]), Ok(1.));
+        assert_eq!(evaluate("binomcdf", &[10., 0.5, 10.]), Ok(1.));
+        assert_eq!(evaluate("binominv", &[10., 0.5, 0.5]), Ok(5.));
+        assert_eq!(evaluate("binominv", &[10., 0.5, 0.]), Ok(0.));
+        assert_eq!(evaluate("poissoninv", &[4.5, 0.8310505787254114]), Ok(6.));
+        assert_eq!(evaluate("poissoninv", &[4.5, 0.83]), Ok(6.));
+        assert_eq!(evaluate("poissoninv", &[4.5, 1.]), Ok(f64::INFINITY));
+
+        // Past 2^53, the bisection stops once no integer is left between its bounds
+        assert_eq!(evaluate("binominv", &[1e18, 0.5, 0.5]), Ok(5e17));
+        let large = evaluate("poissoninv", &[1e300, 0.5]).unwrap();
+        assert!((large - 1e300).abs() <= 1e-15 * 1e300, "{}", large);
+    }
+
+    #[test]
+    fn distributions_errors() {
+        assert!(evaluate("normcdf", &[0., 0., -1.]).is_err());
+        assert!(evaluate("tcdf", &[0., 0.]).is_err());
+        assert!(evaluate("finv", &[1.5, 2., 3.]).is_err());
+        assert!(evaluate("binompdf", &[2.5, 0.5, 1.]).is_err());
+        assert!(evaluate("binompdf", &[10., 1.5, 1.]).is_err());
+        assert!(evaluate("poissoncdf", &[-1., 2.]).is_err());
+        assert!(evaluate("unifcdf", &[0.5, 1., 1.]).is_err());
+        assert!(evaluate("expcdf", &[1., 0.]).is_err());
+    }
+}
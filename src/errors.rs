@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::lexer::Span;
+
 #[derive(Debug)]
 pub struct SyntaxError {
     expression: String,
@@ -17,6 +19,35 @@ impl SyntaxError {
             position: pos,
         }
     }
+
+    /// Builds an error pointing at the end of `span` inside `src`.
+    pub(crate) fn at(src: &str, span: Span, msg: String) -> SyntaxError {
+        SyntaxError::new(
+            src[span.start..span.end].to_owned(),
+            src.to_owned(),
+            msg,
+            span.end,
+        )
+    }
+
+    /// The part of the expression the error was raised at.
+    pub fn token(&self) -> &str {
+        &self.expression
+    }
+
+    /// The whole expression being parsed or evaluated.
+    pub fn full_expression(&self) -> &str {
+        &self.full_expression
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Byte offset inside the full expression the error points at.
+    pub fn position(&self) -> usize {
+        self.position
+    }
 }
 
 impl fmt::Display for SyntaxError {
@@ -31,3 +62,20 @@ impl fmt::Display for SyntaxError {
         )
     }
 }
+
+impl std::error::Error for SyntaxError {}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::evaluate;
+
+    #[test]
+    fn error_accessors() {
+        let err = evaluate("1 + foo(2)").unwrap_err();
+
+        assert_eq!(err.token(), "foo(2)");
+        assert_eq!(err.full_expression(), "1 + foo(2)");
+        assert_eq!(err.message(), "unkown function name \"foo\"");
+        assert_eq!(err.position(), 10);
+    }
+}
@@ -0,0 +1,818 @@
+use phf::phf_map;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::f64;
+
+use crate::ast::*;
+use crate::context::Context;
+use crate::errors::*;
+use crate::functions::Arity;
+use crate::limit::{Extrapolation, Side, MAX_DERIVATIVE_ORDER};
+use crate::math;
+use crate::parser::INTEGER_DIVISION;
+use crate::quadrature::Quadrature;
+use crate::series::Series;
+
+pub(crate) static CONSTANTS: phf::Map<&str, f64> = phf_map! {
+    "pi" => f64::consts::PI,
+    "e" => f64::consts::E,
+    "inf" => f64::INFINITY,
+    "nan" => f64::NAN,
+};
+
+/// Number of leading parameters of variadic functions which aren't among their values, such as the
+/// `p` of `percentile(p, x, ...)`. List literals aren't spread into them.
+pub(crate) static LEADING_PARAMETERS: phf::Map<&str, usize> = phf_map! {
+    "percentile" => 1,
+};
+
+pub(crate) const UNSUPPORTED_CONVERSION: &str = "unit conversions are only supported in units mode";
+pub(crate) const UNSUPPORTED_LIST: &str = "vectors and matrices are only supported in matrix mode";
+
+/// How deep calls to user defined functions may nest before evaluation is aborted.
+const MAX_RECURSION_DEPTH: usize = 256;
+
+pub struct Evaluator<'a> {
+    src: &'a str,
+    ctx: &'a Context,
+    /// Arguments of the user defined function being evaluated, if any.
+    locals: HashMap<&'a str, f64>,
+    depth: usize,
+}
+
+impl<'a> Evaluator<'a> {
+    /// `src` is the text the evaluated expressions were parsed from, and is only used to report
+    /// errors.
+    pub fn new(src: &'a str, ctx: &'a Context) -> Evaluator<'a> {
+        Evaluator {
+            src,
+            ctx,
+            locals: HashMap::new(),
+            depth: 0,
+        }
+    }
+
+    /// Gives `name` a value taking precedence over variables and constants, as the parameters of
+    /// functions do.
+    pub(crate) fn bind(&mut self, name: &'a str, value: f64) {
+        self.locals.insert(name, value);
+    }
+
+    pub fn eval(&self, expr: &Expr) -> Result<f64, SyntaxError> {
+        match &expr.kind {
+            ExprKind::Number(value) => Ok(*value),
+            ExprKind::Identifier(name) => {
+                let value = self
+                    .locals
+                    .get(name.as_str())
+                    .copied()
+                    .or_else(|| self.ctx.variable(name))
+                    .or_else(|| self.ctx.constant(name))
+                    .or_else(|| CONSTANTS.get(name.as_str()).copied());
+
+                match value {
+                    Some(value) => Ok(value),
+                    None => Err(SyntaxError::at(
+                        self.src,
+                        expr.span,
+                        format!("unknown variable \"{}\"", name),
+                    )),
+                }
+            }
+            ExprKind::Prefix { op, operand } => {
+                let operand = self.eval(operand)?;
+
+                match op {
+                    '+' => Ok(operand),
+                    '-' => Ok(-operand),
+                    _ => unreachable!(),
+                }
+            }
+            ExprKind::Postfix { op, operand } => {
+                let operand = self.eval(operand)?;
+
+                match op {
+                    '!' => Ok(math::fact(operand)),
+                    _ => unreachable!(),
+                }
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let left = self.eval(lhs)?;
+                let right = self.eval(rhs)?;
+
+                Ok(binary(*op, left, right))
+            }
+            ExprKind::Call { name, args } => self.call(name, args, expr),
+            ExprKind::List(_) => Err(SyntaxError::at(
+                self.src,
+                expr.span,
+                UNSUPPORTED_LIST.to_owned(),
+            )),
+            ExprKind::Convert { .. } => Err(SyntaxError::at(
+                self.src,
+                expr.span,
+                UNSUPPORTED_CONVERSION.to_owned(),
+            )),
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expr], expr: &Expr) -> Result<f64, SyntaxError> {
+        if let Some(definition) = self.ctx.definition(name) {
+            check_arity(
+                self.src,
+                name,
+                Arity::Exact(definition.params.len()),
+                args,
+                expr,
+            )?;
+
+            if self.depth >= MAX_RECURSION_DEPTH {
+                return Err(SyntaxError::at(
+                    self.src,
+                    expr.span,
+                    format!(
+                        "maximum recursion depth of {} exceeded",
+                        MAX_RECURSION_DEPTH
+                    ),
+                ));
+            }
+
+            let locals = definition
+                .params
+                .iter()
+                .map(String::as_str)
+                .zip(self.eval_args(args)?)
+                .collect();
+
+            let evaluator = Evaluator {
+                src: &definition.src,
+                ctx: self.ctx,
+                locals,
+                depth: self.depth + 1,
+            };
+
+            return evaluator.eval(&definition.body);
+        }
+
+        let function = match self.ctx.functions().get(name) {
+            Some(function) => function,
+            None => {
+                return Err(SyntaxError::at(
+                    self.src,
+                    expr.span,
+                    format!("unkown function name \"{}\"", name),
+                ))
+            }
+        };
+
+        let args = spread_lists(name, function.arity(), args);
+        check_arity(self.src, name, function.arity(), &args, expr)?;
+
+        match function.native() {
+            Some(native) if Binding::of(name, &args, self.ctx).is_none() => {
+                native(&self.eval_args(&args)?)
+                    .map_err(|msg| SyntaxError::at(self.src, expr.span, msg))
+            }
+            _ => self.special(name, &args, expr),
+        }
+    }
+
+    /// Evaluates functions whose arguments can't be evaluated up front.
+    fn special(&self, name: &str, args: &[Expr], expr: &Expr) -> Result<f64, SyntaxError> {
+        match (name, args) {
+            // Only the chosen branch is evaluated, so that recursive definitions can terminate
+            ("if", [condition, then, otherwise]) => {
+                let condition = self.eval(condition)?;
+
+                if condition != 0. && !condition.is_nan() {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+            ("integrate", [integrand, variable, a, b]) => {
+                let variable = self.bound_variable(variable)?;
+                let (a, b) = (self.eval(a)?, self.eval(b)?);
+                let mut evaluator = self.nested();
+
+                let integral = Quadrature::new(self.src, expr.span, |x| {
+                    evaluator.bind(variable, x);
+                    evaluator.eval(integrand)
+                })
+                .integrate(a, b)?;
+
+                if !integral.is_accurate() {
+                    self.ctx.warn(format!(
+                        "{} may be inaccurate, its estimated error is {:e}",
+                        &self.src[expr.span.start..expr.span.end],
+                        integral.error
+                    ));
+                }
+
+                Ok(integral.value)
+            }
+            ("limit" | "limitleft" | "limitright", [f, variable, a]) => {
+                let variable = self.bound_variable(variable)?;
+                let a = self.eval(a)?;
+                let mut evaluator = self.nested();
+
+                let side = match name {
+                    "limitleft" => Side::Left,
+                    "limitright" => Side::Right,
+                    _ => Side::Both,
+                };
+
+                let limit = Extrapolation::new(self.src, expr.span, |x| {
+                    evaluator.bind(variable, x);
+                    evaluator.eval(f)
+                })
+                .limit(a, side)?;
+
+                if !limit.is_accurate() {
+                    self.ctx.warn(format!(
+                        "{} may be inaccurate, its estimated error is {:e}",
+                        &self.src[expr.span.start..expr.span.end],
+                        limit.error
+                    ));
+                }
+
+                Ok(limit.value)
+            }
+            ("deriv", [f, variable, a, order]) => {
+                let variable = self.bound_variable(variable)?;
+                let (a, order) = (self.eval(a)?, self.eval(order)?);
+                let mut evaluator = self.nested();
+
+                if !(0. ..=MAX_DERIVATIVE_ORDER as f64).contains(&order) || order != order.trunc() {
+                    return Err(SyntaxError::at(
+                        self.src,
+                        expr.span,
+                        format!(
+                            "the order of deriv must be an integer from 0 to {}",
+                            MAX_DERIVATIVE_ORDER
+                        ),
+                    ));
+                }
+
+                let derivative = Extrapolation::new(self.src, expr.span, |x| {
+                    evaluator.bind(variable, x);
+                    evaluator.eval(f)
+                })
+                .derivative(a, order as u32)?;
+
+                if !derivative.is_accurate() {
+                    self.ctx.warn(format!(
+                        "{} may be inaccurate, its estimated error is {:e}",
+                        &self.src[expr.span.start..expr.span.end],
+                        derivative.error
+                    ));
+                }
+
+                Ok(derivative.value)
+            }
+            ("sum" | "prod", [index, from, to, term]) => {
+                let index = self.bound_variable(index)?;
+                let (from, to) = (self.eval(from)?, self.eval(to)?);
+                let mut evaluator = self.nested();
+
+                let mut series = Series::new(self.src, expr.span, |k| {
+                    evaluator.bind(index, k);
+                    evaluator.eval(term)
+                });
+
+                match name {
+                    "sum" => series.sum(from, to),
+                    _ => series.product(from, to),
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// The name of the variable bound by a function such as `integrate`, which must be an
+    /// identifier.
+    fn bound_variable<'e>(&self, arg: &'e Expr) -> Result<&'e str, SyntaxError> {
+        match &arg.kind {
+            ExprKind::Identifier(name) => Ok(name),
+            _ => Err(SyntaxError::at(
+                self.src,
+                arg.span,
+                "expected the name of a variable".to_owned(),
+            )),
+        }
+    }
+
+    /// An evaluator seeing the same values as this one, to which more can be bound.
+    fn nested<'b>(&self) -> Evaluator<'b>
+    where
+        'a: 'b,
+    {
+        Evaluator {
+            src: self.src,
+            ctx: self.ctx,
+            locals: self.locals.clone(),
+            depth: self.depth,
+        }
+    }
+
+    fn eval_args(&self, args: &[Expr]) -> Result<Vec<f64>, SyntaxError> {
+        args.iter().map(|arg| self.eval(arg)).collect()
+    }
+}
+
+/// Arguments of a built-in function binding a variable, such as `x` in `integrate(x^2, x, 0, 1)`.
+pub(crate) struct Binding {
+    /// Position of the variable.
+    pub variable: usize,
+    /// Positions of the arguments in which the variable is bound.
+    pub scope: &'static [usize],
+}
+
+impl Binding {
+    /// The variable bound by a call to `name` with `args`, if any. `sum` only binds one when
+    /// called as `sum(k, a, b, f)` with a name `ctx` doesn't define as first argument, and adds
+    /// its arguments otherwise, so that `sum(pi, 1, 2, 3)` is `pi + 6`.
+    pub fn of(name: &str, args: &[Expr], ctx: &Context) -> Option<Binding> {
+        match (name, args) {
+            ("integrate" | "limit" | "limitleft" | "limitright" | "deriv", _) => Some(Binding {
+                variable: 1,
+                scope: &[0],
+            }),
+            (
+                "sum",
+                [Expr {
+                    kind: ExprKind::Identifier(index),
+                    ..
+                }, _, _, _],
+            ) if !ctx.is_defined(index) => Some(Binding {
+                variable: 0,
+                scope: &[3],
+            }),
+            ("prod", _) => Some(Binding {
+                variable: 0,
+                scope: &[3],
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether `name` appearing in the argument at `index` of `args` doesn't refer to the bound
+    /// variable.
+    pub fn is_free(&self, args: &[Expr], index: usize, name: &str) -> bool {
+        let bound = match &args.get(self.variable).map(|arg| &arg.kind) {
+            Some(ExprKind::Identifier(variable)) => variable == name,
+            _ => false,
+        };
+
+        index != self.variable && !(bound && self.scope.contains(&index))
+    }
+}
+
+/// Applies a binary operator to floating point operands.
+pub(crate) fn binary(op: char, left: f64, right: f64) -> f64 {
+    match op {
+        '+' => left + right,
+        '-' => left - right,
+        '*' => left * right,
+        '/' => left / right,
+        '^' => {
+            if left == 0. && right == 0. {
+                f64::NAN
+            } else {
+                f64::powf(left, right)
+            }
+        }
+        '%' => left % right,
+        INTEGER_DIVISION => (left / right).trunc(),
+        _ => unreachable!(),
+    }
+}
+
+/// Arguments of a call to `name`, a function of `arity`, where list literals passed to a variadic
+/// function are replaced by their items, so that `mean([1, 2, 3])` is `mean(1, 2, 3)`. Lists
+/// passed as leading parameters are left for [`check_arity`] to reject.
+pub(crate) fn spread_lists<'a>(name: &str, arity: Arity, args: &'a [Expr]) -> Cow<'a, [Expr]> {
+    fn spread(args: &[Expr], spread_args: &mut Vec<Expr>) {
+        for arg in args {
+            match &arg.kind {
+                ExprKind::List(items) => spread(items, spread_args),
+                _ => spread_args.push(arg.clone()),
+            }
+        }
+    }
+
+    let leading = LEADING_PARAMETERS
+        .get(name)
+        .map_or(0, |n| (*n).min(args.len()));
+    let (parameters, values) = args.split_at(leading);
+    let has_lists = values
+        .iter()
+        .any(|arg| matches!(arg.kind, ExprKind::List(_)));
+
+    if !has_lists || !matches!(arity, Arity::AtLeast(_)) {
+        return Cow::Borrowed(args);
+    }
+
+    let mut spread_args = parameters.to_vec();
+    spread(values, &mut spread_args);
+    Cow::Owned(spread_args)
+}
+
+/// Fails with the position of the call `expr` if `arity` doesn't allow `args`.
+pub(crate) fn check_arity(
+    src: &str,
+    name: &str,
+    arity: Arity,
+    args: &[Expr],
+    expr: &Expr,
+) -> Result<(), SyntaxError> {
+    let arg_num = args.len();
+
+    if !arity.accepts(arg_num) {
+        return Err(SyntaxError::new(
+            src[expr.span.start..expr.span.end].to_owned(),
+            src.to_owned(),
+            format!("incorrect number of arguments passed, function {} takes {} parameters but {} {} passed", name, arity, arg_num, if arg_num == 1 {"was"} else {"were"}),
+            expr.span.end - 1,
+        ));
+    }
+
+    let leading = LEADING_PARAMETERS.get(name).map_or(0, |n| *n);
+
+    if let Some(list) = args
+        .iter()
+        .take(leading)
+        .find(|arg| matches!(arg.kind, ExprKind::List(_)))
+    {
+        return Err(SyntaxError::at(src, list.span, list_parameter(name)));
+    }
+
+    Ok(())
+}
+
+/// Error for a list passed as one of the [`LEADING_PARAMETERS`] of `name`.
+pub(crate) fn list_parameter(name: &str) -> String {
+    format!(
+        "the values passed to {} go after its leading parameters, which can't be lists",
+        name
+    )
+}
+
+/// Parses and evaluates `src` using only the built-in constants and functions, see
+/// [`Context::evaluate`].
+pub fn evaluate(src: &str) -> Result<f64, SyntaxError> {
+    Context::new().evaluate(src)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unkown_token() {
+        assert_parse_error!(
+            "1$",
+            "error while parsing token \"$\" in expression \"1$\": unkown token, 1$ <-- HERE"
+        );
+    }
+
+    #[test]
+    fn unknown_variable() {
+        assert_parse_error!("error", "error while parsing token \"error\" in expression \"error\": unknown variable \"error\", error <-- HERE");
+        assert_parse_error!("x = 1; x + y", "error while parsing token \"y\" in expression \"x = 1; x + y\": unknown variable \"y\", x = 1; x + y <-- HERE");
+    }
+
+    #[test]
+    fn variables() {
+        assert_parse_result_float!("x = 3; x^2", 9.);
+        assert_parse_result_float!("x = 3\ny = x * 2\ny - x", 3.);
+        assert_parse_result_float!("x = 2; x = x + 1; x", 3.);
+    }
+
+    #[test]
+    fn float_simple() {
+        assert_parse_result_float!("1", 1.0);
+        assert_parse_result_float!("-5", -5.0);
+    }
+
+    #[test]
+    fn float_decimals() {
+        assert_parse_result_float!("1.", 1.0);
+        assert_parse_result_float!(".1", 0.1);
+        assert_parse_result_float!("1.1", 1.1);
+    }
+
+    #[test]
+    fn float_nan() {
+        assert_parse_result_is!("nan", is_nan);
+    }
+
+    #[test]
+    fn float_inf() {
+        assert_parse_result_is!("inf", is_infinite);
+    }
+
+    #[test]
+    fn operator_sum() {
+        assert_parse_result_float!("5+3", 8.);
+    }
+
+    #[test]
+    fn operator_difference() {
+        assert_parse_result_float!("1-1", 0.);
+    }
+
+    #[test]
+    fn operator_product() {
+        assert_parse_result_float!("2*4", 8.);
+    }
+
+    #[test]
+    fn operator_quotient() {
+        assert_parse_result_float!("10/2", 5.);
+    }
+
+    #[test]
+    fn operator_exponent() {
+        assert_parse_result_float!("2^2", 4.);
+        assert_parse_result_float!("-2^2", -4.);
+        assert_parse_result_float!("(-1)^1", -1.);
+        assert_parse_result_float!("2^-1", 0.5);
+        assert!(evaluate("0^0").unwrap().is_nan());
+    }
+
+    #[test]
+    fn operator_modulo() {
+        assert_parse_result_float!("5%5", 0.);
+        assert_parse_result_float!("10%5", 0.);
+        assert_parse_result_float!("11%5", 1.);
+    }
+
+    #[test]
+    fn operator_integer_division() {
+        assert_parse_result_float!("11//5", 2.);
+        assert_parse_result_float!("-11 // 5", -2.);
+        assert_parse_result_float!("7.5 // 2 * 2 + 7.5 % 2", 7.5);
+    }
+
+    #[test]
+    fn operator_factorial() {
+        assert_parse_result_is!("(-1)!", is_nan);
+        assert_parse_result_float!("0!", 1.);
+        assert_parse_result_float!("1!", 1.);
+        assert_parse_result_float!("2!", 2.);
+        assert_parse_result_float!("3!", 6.);
+        assert_parse_result_float!("4!", 24.);
+    }
+
+    #[test]
+    fn operator_precedence() {
+        assert_parse_result_float!("1+2*3", 7.);
+        assert_parse_result_float!("2*3-1", 5.);
+        assert_parse_result_float!("1+4/2", 3.);
+        assert_parse_result_float!("1^2/4", 0.25);
+        assert_parse_result_float!("10-2-3", 5.);
+    }
+
+    #[test]
+    fn implicit_operators() {
+        assert_parse_result_float!("2*-2", -4.);
+        assert_parse_result_float!("1--1", 2.);
+        assert_parse_result_float!("-1*-1", 1.);
+    }
+
+    #[test]
+    fn implicit_product() {
+        assert_parse_result_float!("2pi", 2. * f64::consts::PI);
+        assert_parse_result_float!("2(3+1)", 8.);
+        assert_parse_result_float!("x = 3; 2x^2", 18.);
+        assert_parse_result_float!("6/2pi", 3. / f64::consts::PI);
+    }
+
+    #[test]
+    fn unsupported_conversion() {
+        assert_parse_error!(
+            "1 in 2",
+            "error while parsing token \"1 in 2\" in expression \"1 in 2\": unit conversions are only supported in units mode, 1 in 2 <-- HERE"
+        );
+    }
+
+    #[test]
+    fn unwrap_quotes() {
+        assert_parse_result_float!("\"10-10\"", 0.);
+    }
+
+    #[test]
+    fn unwrap_parenthesis_simple() {
+        assert_parse_result_float!("(1+1)", 2.);
+    }
+
+    #[test]
+    fn unwrap_parenthesis_nested() {
+        assert_parse_result_float!("(((1)+1)+(1+(1)))", 4.);
+        assert_parse_result_float!("(1+(1+(1+(1+(1+(1+(1)))))))", 7.);
+    }
+
+    #[test]
+    fn function_single_cos() {
+        assert_parse_result_float!("cos(0)", 1.0);
+        assert_parse_result_float!("cos(pi)", -1.0);
+    }
+
+    #[test]
+    fn function_single_sin() {
+        assert_parse_result_float!("sin(0)", 0.0);
+        assert_parse_result_float!("sin(pi)", 0.0);
+    }
+
+    #[test]
+    fn function_single_tan() {
+        assert_parse_result_float!("tan(0)", 0.0);
+    }
+
+    #[test]
+    fn function_single_acos() {
+        assert_parse_result_float!("acos(0)", f64::consts::FRAC_PI_2);
+    }
+
+    #[test]
+    fn function_single_asin() {
+        assert_parse_result_float!("asin(1)", f64::consts::FRAC_PI_2);
+    }
+
+    #[test]
+    fn function_single_atan() {
+        assert_parse_result_float!("atan(1)", f64::consts::FRAC_PI_4);
+    }
+
+    #[test]
+    fn function_single_ln() {
+        assert_parse_result_is!("ln(0)", is_infinite);
+        assert_parse_result_float!("ln(1)", 0.0);
+        assert_parse_result_float!("ln(e)", 1.0);
+    }
+
+    #[test]
+    fn function_single_sqrt() {
+        assert_parse_result_float!("sqrt(9)", 3.);
+        assert_parse_result_float!("sqrt(4)", 2.);
+        assert_parse_result_float!("sqrt(0)", 0.);
+        assert_parse_result_is!("sqrt(-1)", is_nan);
+    }
+
+    #[test]
+    fn function_two_logab() {
+        assert_parse_result_float!("logab(2,16)", 4.);
+        assert_parse_result_float!("logab(3,9)", 2.);
+        assert_parse_result_is!("logab(1,1)", is_nan);
+    }
+
+    #[test]
+    fn function_two_nroot() {
+        assert_parse_result_float!("nroot(3,8)", 2.);
+        assert_parse_result_float!("nroot(4,16)", 2.);
+    }
+
+    #[test]
+    fn function_nested() {
+        assert_parse_result_float!("sqrt(sqrt(16))", 2.);
+        assert_parse_result_float!("nroot(2,logab(2,256))", f64::sqrt(8.));
+    }
+
+    #[test]
+    fn function_if() {
+        assert_parse_result_float!("if(1, 2, 3)", 2.);
+        assert_parse_result_float!("if(0, 2, 3)", 3.);
+        assert_parse_result_float!("if(0, unknown, 3)", 3.);
+    }
+
+    #[test]
+    fn function_integrate() {
+        let close = |src: &str, expected: f64| {
+            let res = evaluate(src).unwrap();
+            assert!((res - expected).abs() < 1e-12, "{} gave {}", src, res);
+        };
+
+        close("integrate(x^2, x, 0, 3)", 9.);
+        close("integrate(integrate(x y, y, 0, x), x, 0, 1)", 0.125);
+        close("integrate(e^-t, t, 0, inf)", 1.);
+        assert_parse_error!("integrate(x, 2, 0, 1)", "error while parsing token \"2\" in expression \"integrate(x, 2, 0, 1)\": expected the name of a variable, integrate(x, 2 <-- HERE");
+        assert_parse_error!("integrate(1/x, x, -1, 1)", "error while parsing token \"integrate(1/x, x, -1, 1)\" in expression \"integrate(1/x, x, -1, 1)\": the integrand is not finite at 0, integrate(1/x, x, -1, 1) <-- HERE");
+
+        let mut ctx = Context::new();
+        ctx.execute("x = 5; f(x) = integrate(x, x, 0, 1) + x")
+            .unwrap();
+        assert_eq!(ctx.evaluate("integrate(x, x, 0, 2) + f(1)").unwrap(), 3.5);
+        assert!(ctx.take_warnings().is_empty());
+
+        ctx.evaluate("integrate(sin(x), x, 0, inf)").unwrap();
+        assert_eq!(ctx.take_warnings().len(), 1);
+    }
+
+    #[test]
+    fn function_limit_deriv() {
+        let close = |src: &str, expected: f64| {
+            let res = evaluate(src).unwrap();
+            assert!((res - expected).abs() < 1e-9, "{} gave {}", src, res);
+        };
+
+        close("limit(sin(x)/x, x, 0)", 1.);
+        close("limit((1 + 1/n)^n, n, inf)", f64::consts::E);
+        close("limitleft(x/sqrt(x^2), x, 0)", -1.);
+        close("limitright(x/sqrt(x^2), x, 0)", 1.);
+        assert_eq!(
+            evaluate("limitright(ln(x), x, 0)").unwrap(),
+            f64::NEG_INFINITY
+        );
+        assert_parse_error!("limit(x/sqrt(x^2), x, 0)", "error while parsing token \"limit(x/sqrt(x^2), x, 0)\" in expression \"limit(x/sqrt(x^2), x, 0)\": the limit from the left, -1, differs from the limit from the right, 1, limit(x/sqrt(x^2), x, 0) <-- HERE");
+
+        close("deriv(x^3, x, 2, 1)", 12.);
+        close("deriv(sin(t), t, 0, 0)", 0.);
+        close("deriv(e^x, x, 1, 2)", f64::consts::E);
+        let mut ctx = Context::new();
+        assert!((ctx.evaluate("deriv(e^x, x, 0, 6)").unwrap() - 1.).abs() < 1e-6);
+        assert!(ctx.evaluate("limit(x ln(x), x, 0)").unwrap().abs() < 1e-6);
+        assert_eq!(ctx.take_warnings().len(), 1);
+        assert!((ctx.evaluate("deriv(e^x, x, 0, 8)").unwrap() - 1.).abs() < 1e-5);
+        assert_eq!(ctx.take_warnings().len(), 1);
+        ctx.evaluate("limit(sin(x)/x, x, 0) + deriv(e^x, x, 0, 7)")
+            .unwrap();
+        assert!(ctx.take_warnings().is_empty());
+        assert_parse_error!("deriv(x, x, 0, 1.5)", "error while parsing token \"deriv(x, x, 0, 1.5)\" in expression \"deriv(x, x, 0, 1.5)\": the order of deriv must be an integer from 0 to 8, deriv(x, x, 0, 1.5) <-- HERE");
+    }
+
+    #[test]
+    fn function_sum_prod() {
+        assert_parse_result_float!("sum(k, 1, 100, k^2)", 338350.);
+        assert_parse_result_float!("prod(k, 1, 5, k)", 120.);
+        assert_parse_result_float!("sum(i, 1, 3, sum(j, 1, i, j))", 10.);
+        assert_parse_result_float!("sum(k, 3, 1, k)", 0.);
+        assert!(
+            (evaluate("sum(k, 1, inf, 1/k^2)").unwrap() - f64::consts::PI.powi(2) / 6.).abs()
+                < 1e-12
+        );
+        assert_parse_error!("sum(k, 1, inf, 1/k)", "error while parsing token \"sum(k, 1, inf, 1/k)\" in expression \"sum(k, 1, inf, 1/k)\": the sum did not converge after 1000000 terms, sum(k, 1, inf, 1/k) <-- HERE");
+        assert_parse_result_float!("sum(k, 1, 5, (-1)^k)", -1.);
+        assert!((evaluate("sum(k, 1, inf, (-1)^k/k)").unwrap() + f64::consts::LN_2).abs() < 1e-12);
+        assert_parse_error!("sum(k, 1, inf, (-1)^(k/2))", "error while parsing token \"sum(k, 1, inf, (-1)^(k/2))\" in expression \"sum(k, 1, inf, (-1)^(k/2))\": the sum is undefined, its term of index 1 is not a number, sum(k, 1, inf, (-1)^(k/2)) <-- HERE");
+        assert_parse_error!("prod(k, 0.5, 2, k)", "error while parsing token \"prod(k, 0.5, 2, k)\" in expression \"prod(k, 0.5, 2, k)\": the bounds of a product must be integers, the upper one may be inf, prod(k, 0.5, 2, k) <-- HERE");
+    }
+
+    #[test]
+    fn function_statistics() {
+        assert_parse_result_float!("mean(1, 2, 3)", 2.);
+        assert_parse_result_float!("mean([1, 2, 3])", 2.);
+        assert_parse_result_float!("max([1, 5], 3, [[2], [4]])", 5.);
+        assert_parse_result_float!("median(3, 1, 4, 1, 5)", 3.);
+        assert_parse_result_float!("percentile(25, [1, 2, 3, 4, 5])", 2.);
+        assert_parse_result_float!("percentile(50, [1, 2, 3, 4])", 2.5);
+        assert_parse_error!("percentile([1, 2, 3, 4], 50)", "error while parsing token \"[1, 2, 3, 4]\" in expression \"percentile([1, 2, 3, 4], 50)\": the values passed to percentile go after its leading parameters, which can't be lists, percentile([1, 2, 3, 4] <-- HERE");
+        assert_parse_result_float!("stdevp([2, 4, 4, 4, 5, 5, 7, 9])", 2.);
+        assert_parse_result_float!("sum(1, 2, 3, 4)", 10.);
+        assert_parse_result_float!("sum([1, 2, 3])", 6.);
+        assert_parse_result_float!("sum(k, 1, 3, 4)", 12.);
+        assert_parse_result_float!("sum(pi, 1, 2, 3)", f64::consts::PI + 6.);
+
+        let mut ctx = Context::new();
+        ctx.execute("a = 1; b = 2").unwrap();
+        assert_eq!(ctx.evaluate("sum(a, b, 3, 4)").unwrap(), 10.);
+        assert_eq!(ctx.evaluate("sum(k, a, b, k b)").unwrap(), 6.);
+        assert_parse_error!("var([1])", "error while parsing token \"var([1])\" in expression \"var([1])\": incorrect number of arguments passed, function var takes at least 2 parameters but 1 was passed, var([1] <-- HERE");
+        assert_parse_error!("percentile(150, 1, 2)", "error while parsing token \"percentile(150, 1, 2)\" in expression \"percentile(150, 1, 2)\": the percentile must be between 0 and 100, percentile(150, 1, 2) <-- HERE");
+        assert_parse_error!("[1, 2]", "error while parsing token \"[1, 2]\" in expression \"[1, 2]\": vectors and matrices are only supported in matrix mode, [1, 2] <-- HERE");
+    }
+
+    #[test]
+    fn function_user_defined() {
+        assert_parse_result_float!("f(x, y) = x^2 + y; f(3, 1)", 10.);
+        assert_parse_result_float!("c = 2; f(x) = c*x; f(4)", 8.);
+        assert_parse_result_float!("x = 10; f(x) = x; f(1) + x", 11.);
+        assert_parse_result_float!("f(x) = x + 1; g(x) = f(x)*2; g(1)", 4.);
+    }
+
+    #[test]
+    fn function_user_defined_recursive() {
+        assert_parse_result_float!("fact(n) = if(n, n*fact(n-1), 1); fact(5)", 120.);
+    }
+
+    #[test]
+    fn function_user_defined_errors() {
+        assert_parse_error!("f(x) = x; f(1, 2)", "error while parsing token \"f(1, 2)\" in expression \"f(x) = x; f(1, 2)\": incorrect number of arguments passed, function f takes 1 parameters but 2 were passed, f(x) = x; f(1, 2 <-- HERE");
+        assert_parse_error!("f(x) = f(x); f(1)", "error while parsing token \"f(x)\" in expression \"f(x) = f(x); f(1)\": maximum recursion depth of 256 exceeded, f(x) = f(x) <-- HERE");
+        assert_parse_error!("f(x) = x + y; f(1)", "error while parsing token \"y\" in expression \"f(x) = x + y; f(1)\": unknown variable \"y\", f(x) = x + y <-- HERE");
+    }
+
+    #[test]
+    fn function_unkown_name() {
+        assert_parse_error!("test()", "error while parsing token \"test()\" in expression \"test()\": unkown function name \"test\", test() <-- HERE");
+    }
+
+    #[test]
+    fn function_argument_missmatch() {
+        assert_parse_error!("sqrt()", "error while parsing token \"sqrt()\" in expression \"sqrt()\": incorrect number of arguments passed, function sqrt takes 1 parameters but 0 were passed, sqrt( <-- HERE");
+        assert_parse_error!("sqrt(1,1)", "error while parsing token \"sqrt(1,1)\" in expression \"sqrt(1,1)\": incorrect number of arguments passed, function sqrt takes 1 parameters but 2 were passed, sqrt(1,1 <-- HERE");
+        assert_parse_error!("logab()", "error while parsing token \"logab()\" in expression \"logab()\": incorrect number of arguments passed, function logab takes 2 parameters but 0 were passed, logab( <-- HERE");
+        assert_parse_error!("logab(1)", "error while parsing token \"logab(1)\" in expression \"logab(1)\": incorrect number of arguments passed, function logab takes 2 parameters but 1 was passed, logab(1 <-- HERE");
+        assert_parse_error!("logab(1,1,1)", "error while parsing token \"logab(1,1,1)\" in expression \"logab(1,1,1)\": incorrect number of arguments passed, function logab takes 2 parameters but 3 were passed, logab(1,1,1 <-- HERE");
+    }
+}
@@ -0,0 +1,391 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::backend::Backend;
+use crate::eval::{self, CONSTANTS};
+use crate::functions::{Arity, FunctionRegistry};
+use crate::integers;
+use crate::math;
+use crate::parser::INTEGER_DIVISION;
+
+/// Largest power of ten a literal is scaled by, and largest exponent `^` raises rationals to,
+/// before falling back to floating point.
+pub(crate) const MAX_EXACT_EXPONENT: u32 = 100_000;
+
+/// Largest number whose factorial is computed exactly.
+pub(crate) const MAX_EXACT_FACTORIAL: u64 = 100_000;
+
+/// Result of evaluating an expression in exact mode.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExactValue {
+    Rational(BigRational),
+    /// Result of an operation that can't be carried out exactly, such as `sin(1)`, and everything
+    /// computed from it.
+    Inexact(f64),
+}
+
+impl ExactValue {
+    pub fn is_exact(&self) -> bool {
+        matches!(self, ExactValue::Rational(_))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            ExactValue::Rational(x) => x.to_f64().unwrap_or(f64::NAN),
+            ExactValue::Inexact(x) => *x,
+        }
+    }
+
+    /// Decimal expansion of the value, with the repeating digits in parentheses (`1/6` expands to
+    /// `0.1(6)`). Expansions longer than `max_digits` fractional digits are cut short with `...`.
+    pub fn decimal(&self, max_digits: usize) -> String {
+        let x = match self {
+            ExactValue::Rational(x) => x,
+            ExactValue::Inexact(x) => return x.to_string(),
+        };
+
+        let sign = if x.is_negative() { "-" } else { "" };
+        let denom = x.denom();
+        let (int, mut rem) = x.numer().abs().div_rem(denom);
+
+        let mut digits = String::new();
+        let mut seen: HashMap<BigInt, usize> = HashMap::new();
+
+        while !rem.is_zero() && digits.len() < max_digits {
+            if let Some(&start) = seen.get(&rem) {
+                return format!("{}{}.{}({})", sign, int, &digits[..start], &digits[start..]);
+            }
+
+            seen.insert(rem.clone(), digits.len());
+
+            let (digit, next) = (rem * BigInt::from(10)).div_rem(denom);
+            digits.push_str(&digit.to_string());
+            rem = next;
+        }
+
+        if digits.is_empty() {
+            format!("{}{}", sign, int)
+        } else if rem.is_zero() {
+            format!("{}{}.{}", sign, int, digits)
+        } else {
+            format!("{}{}.{}...", sign, int, digits)
+        }
+    }
+}
+
+impl fmt::Display for ExactValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExactValue::Rational(x) => write!(f, "{}", x),
+            ExactValue::Inexact(x) => write!(f, "≈ {}", x),
+        }
+    }
+}
+
+/// Parses a decimal literal such as `1.25e-3` into the rational it denotes.
+pub(crate) fn parse_decimal(text: &str) -> Option<BigRational> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac).parse::<BigInt>().ok()?;
+    let scale = exponent.checked_sub(frac.len() as i64)?;
+
+    if scale.unsigned_abs() > MAX_EXACT_EXPONENT as u64 {
+        return None;
+    }
+
+    let power = num_traits::pow(BigInt::from(10), scale.unsigned_abs() as usize);
+
+    Some(if scale >= 0 {
+        BigRational::from_integer(digits * power)
+    } else {
+        BigRational::new(digits, power)
+    })
+}
+
+/// The `n`-th root of `x`, if it is rational.
+fn exact_root(x: &BigRational, n: u32) -> Option<BigRational> {
+    if n == 0 || (x.is_negative() && n.is_multiple_of(2)) {
+        return None;
+    }
+
+    let root =
+        |i: &BigInt| Some(i.nth_root(n)).filter(|r| &num_traits::pow(r.clone(), n as usize) == i);
+
+    Some(BigRational::new(root(x.numer())?, root(x.denom())?))
+}
+
+pub(crate) fn pow(
+    base: &BigRational,
+    exponent: &BigRational,
+) -> Result<Option<BigRational>, String> {
+    let (p, q) = match (exponent.numer().to_i32(), exponent.denom().to_u32()) {
+        (Some(p), Some(q)) if p.unsigned_abs() <= MAX_EXACT_EXPONENT => (p, q),
+        _ => return Ok(None),
+    };
+
+    if base.is_zero() && p <= 0 {
+        return Err(if p == 0 {
+            "0^0 is undefined".to_owned()
+        } else {
+            "division by zero".to_owned()
+        });
+    }
+
+    // x^(p/q) = (x^(1/q))^p
+    Ok(exact_root(base, q).map(|root| root.pow(p)))
+}
+
+pub(crate) fn factorial(n: u64) -> BigInt {
+    (2..=n).fold(BigInt::one(), |acc, i| acc * i)
+}
+
+/// Evaluates expressions over arbitrary precision rationals, falling back to floating point for
+/// anything without an exact result (irrational constants, most functions, ...).
+#[derive(Debug, Clone)]
+pub struct ExactBackend {
+    functions: FunctionRegistry,
+}
+
+impl Default for ExactBackend {
+    fn default() -> ExactBackend {
+        ExactBackend {
+            functions: FunctionRegistry::builtin(),
+        }
+    }
+}
+
+impl ExactBackend {
+    pub fn new() -> ExactBackend {
+        ExactBackend::default()
+    }
+
+    fn inexact_call(&self, name: &str, args: &[ExactValue]) -> Result<ExactValue, String> {
+        let args = args.iter().map(ExactValue::to_f64).collect::<Vec<f64>>();
+        let native = self.functions.get(name).and_then(|f| f.native()).unwrap();
+
+        native(&args).map(ExactValue::Inexact)
+    }
+}
+
+impl Backend for ExactBackend {
+    type Value = ExactValue;
+
+    fn literal(&self, text: &str) -> Result<ExactValue, String> {
+        match parse_decimal(text) {
+            Some(x) => Ok(ExactValue::Rational(x)),
+            None => text
+                .parse::<f64>()
+                .map(ExactValue::Inexact)
+                .map_err(|_| "unkown token".to_owned()),
+        }
+    }
+
+    fn constant(&self, name: &str) -> Option<ExactValue> {
+        CONSTANTS.get(name).map(|x| ExactValue::Inexact(*x))
+    }
+
+    fn unary(&self, op: char, operand: ExactValue) -> Result<ExactValue, String> {
+        match (op, operand) {
+            ('+', x) => Ok(x),
+            ('-', ExactValue::Rational(x)) => Ok(ExactValue::Rational(-x)),
+            ('!', ExactValue::Rational(x))
+                if x.is_integer()
+                    && !x.is_negative()
+                    && x.to_integer() <= MAX_EXACT_FACTORIAL.into() =>
+            {
+                Ok(ExactValue::Rational(BigRational::from_integer(factorial(
+                    x.to_integer().to_u64().unwrap(),
+                ))))
+            }
+            ('-', x) => Ok(ExactValue::Inexact(-x.to_f64())),
+            ('!', x) => Ok(ExactValue::Inexact(math::fact(x.to_f64()))),
+            _ => unreachable!(),
+        }
+    }
+
+    fn binary(&self, op: char, lhs: ExactValue, rhs: ExactValue) -> Result<ExactValue, String> {
+        let (a, b) = match (&lhs, &rhs) {
+            (ExactValue::Rational(a), ExactValue::Rational(b)) => (a, b),
+            _ => {
+                return Ok(ExactValue::Inexact(eval::binary(
+                    op,
+                    lhs.to_f64(),
+                    rhs.to_f64(),
+                )))
+            }
+        };
+
+        if matches!(op, '/' | '%' | INTEGER_DIVISION) && b.is_zero() {
+            return Err("division by zero".to_owned());
+        }
+
+        let res = match op {
+            '+' => a + b,
+            '-' => a - b,
+            '*' => a * b,
+            '/' => a / b,
+            '%' => a - b * (a / b).trunc(),
+            INTEGER_DIVISION => (a / b).trunc(),
+            '^' => match pow(a, b)? {
+                Some(res) => res,
+                None => {
+                    return Ok(ExactValue::Inexact(eval::binary(
+                        op,
+                        lhs.to_f64(),
+                        rhs.to_f64(),
+                    )))
+                }
+            },
+            _ => unreachable!(),
+        };
+
+        Ok(ExactValue::Rational(res))
+    }
+
+    fn arity(&self, name: &str) -> Option<Arity> {
+        self.functions
+            .get(name)
+            .filter(|f| f.native().is_some())
+            .map(|f| f.arity())
+    }
+
+    fn call(&self, name: &str, args: &[ExactValue]) -> Result<ExactValue, String> {
+        let integers = args
+            .iter()
+            .map(|x| match x {
+                ExactValue::Rational(x) if x.is_integer() => Some(x.to_integer()),
+                _ => None,
+            })
+            .collect::<Option<Vec<BigInt>>>();
+
+        if let (Some(f), Some(integers)) = (integers::function(name), integers) {
+            return f(&integers).map(|x| ExactValue::Rational(BigRational::from_integer(x)));
+        }
+
+        let exact = match (name, args) {
+            ("sqrt", [ExactValue::Rational(x)]) => exact_root(x, 2),
+            ("nroot", [ExactValue::Rational(n), ExactValue::Rational(x)]) => n
+                .to_integer()
+                .to_u32()
+                .filter(|_| n.is_integer())
+                .and_then(|n| exact_root(x, n)),
+            _ => None,
+        };
+
+        match exact {
+            Some(x) => Ok(ExactValue::Rational(x)),
+            None => self.inexact_call(name, args),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Session;
+
+    fn exact(src: &str) -> String {
+        Session::new(ExactBackend::new())
+            .evaluate(src)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn exact_literals() {
+        assert_eq!(exact("0.1"), "1/10");
+        assert_eq!(exact("1.25e-3"), "1/800");
+        assert_eq!(exact("2e3"), "2000");
+        assert_eq!(exact("(0.5)"), "1/2");
+    }
+
+    #[test]
+    fn exact_operators() {
+        assert_eq!(exact("1/3*3"), "1");
+        assert_eq!(exact("0.1+0.2"), "3/10");
+        assert_eq!(exact("1/3 - 1/2"), "-1/6");
+        assert_eq!(exact("(7/2) % 1"), "1/2");
+        assert_eq!(exact("-7 % 3"), "-1");
+        assert_eq!(exact("(15/2) // (-2)"), "-3");
+        assert_eq!(exact("20!"), "2432902008176640000");
+    }
+
+    #[test]
+    fn exact_powers() {
+        assert_eq!(exact("2^-2"), "1/4");
+        assert_eq!(exact("(2/3)^3"), "8/27");
+        assert_eq!(exact("(4/9)^(1/2)"), "2/3");
+        assert_eq!(exact("(-8)^(1/3)"), "-2");
+        assert_eq!(exact("2^100"), "1267650600228229401496703205376");
+    }
+
+    #[test]
+    fn exact_functions() {
+        assert_eq!(exact("sqrt(16/25)"), "4/5");
+        assert_eq!(exact("nroot(3, 27)"), "3");
+        assert_eq!(exact("nCr(100, 50)"), "100891344545564193334812497256");
+        assert_eq!(
+            exact("fib(90) / fib(89)"),
+            "2880067194370816120/1779979416004714189"
+        );
+    }
+
+    #[test]
+    fn exact_fallback() {
+        assert_eq!(exact("sqrt(2)"), format!("≈ {}", f64::sqrt(2.)));
+        assert_eq!(exact("sin(0)"), "≈ 0");
+        assert_eq!(exact("pi/pi"), "≈ 1");
+        assert_eq!(exact("2^0.5"), format!("≈ {}", f64::sqrt(2.)));
+        assert!(!Session::new(ExactBackend::new())
+            .evaluate("1/3 + ln(2)")
+            .unwrap()
+            .is_exact());
+    }
+
+    #[test]
+    fn exact_errors() {
+        let mut session = Session::new(ExactBackend::new());
+
+        assert_eq!(
+            format!("{}", session.evaluate("1/(1-1)").expect_err("")),
+            "error while parsing token \"1/(1-1)\" in expression \"1/(1-1)\": division by zero, 1/(1-1) <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", session.evaluate("0^0").expect_err("")),
+            "error while parsing token \"0^0\" in expression \"0^0\": 0^0 is undefined, 0^0 <-- HERE"
+        );
+    }
+
+    #[test]
+    fn exact_variables() {
+        let mut session = Session::new(ExactBackend::new());
+
+        assert_eq!(session.evaluate("x = 1/3; x * 6").unwrap().to_string(), "2");
+        assert_eq!(session.evaluate("x + 1").unwrap().to_string(), "4/3");
+    }
+
+    #[test]
+    fn exact_decimal() {
+        let decimal = |src| {
+            Session::new(ExactBackend::new())
+                .evaluate(src)
+                .unwrap()
+                .decimal(20)
+        };
+
+        assert_eq!(decimal("1/3"), "0.(3)");
+        assert_eq!(decimal("-1/6"), "-0.1(6)");
+        assert_eq!(decimal("1/7"), "0.(142857)");
+        assert_eq!(decimal("5/4"), "1.25");
+        assert_eq!(decimal("3"), "3");
+        assert_eq!(decimal("1/97"), "0.01030927835051546391...");
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::distributions;
+use crate::integers;
+use crate::math;
+use crate::statistics;
+
+type NativeImplementation = dyn Fn(&[f64]) -> Result<f64, String> + Send + Sync;
+
+/// Number of arguments a function accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    /// Variadic functions, taking at least the given number of arguments.
+    AtLeast(usize),
+    /// Functions with optional arguments, taking from the first to the second number of them.
+    Between(usize, usize),
+}
+
+impl Arity {
+    pub fn accepts(&self, arg_num: usize) -> bool {
+        match *self {
+            Arity::Exact(n) => arg_num == n,
+            Arity::AtLeast(n) => arg_num >= n,
+            Arity::Between(min, max) => (min..=max).contains(&arg_num),
+        }
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arity::Exact(n) => write!(f, "{}", n),
+            Arity::AtLeast(n) => write!(f, "at least {}", n),
+            Arity::Between(min, max) => write!(f, "{} to {}", min, max),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Implementation {
+    Native(Arc<NativeImplementation>),
+    /// Handled by the evaluator itself, as the arguments must not be evaluated up front.
+    Special,
+}
+
+/// A function callable from expressions, along with the metadata used to list it.
+#[derive(Clone)]
+pub struct Function {
+    arity: Arity,
+    signature: String,
+    description: String,
+    implementation: Implementation,
+}
+
+impl Function {
+    /// Wraps `implementation`, which receives the already evaluated arguments. Errors it returns
+    /// are reported at the position of the call.
+    pub fn new(
+        signature: &str,
+        description: &str,
+        arity: Arity,
+        implementation: impl Fn(&[f64]) -> Result<f64, String> + Send + Sync + 'static,
+    ) -> Function {
+        Function {
+            arity,
+            signature: signature.to_owned(),
+            description: description.to_owned(),
+            implementation: Implementation::Native(Arc::new(implementation)),
+        }
+    }
+
+    pub(crate) fn special(signature: &str, description: &str, arity: Arity) -> Function {
+        Function {
+            arity,
+            signature: signature.to_owned(),
+            description: description.to_owned(),
+            implementation: Implementation::Special,
+        }
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    /// How the function is called, such as `logab(a, b)`.
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The closure implementing the function, or `None` if it has to be handled by the evaluator.
+    pub(crate) fn native(&self) -> Option<&NativeImplementation> {
+        match &self.implementation {
+            Implementation::Native(implementation) => Some(implementation.as_ref()),
+            Implementation::Special => None,
+        }
+    }
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("arity", &self.arity)
+            .field("signature", &self.signature)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+/// Set of functions available to expressions, keyed by name.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionRegistry {
+    functions: BTreeMap<String, Function>,
+}
+
+fn unary(f: fn(f64) -> f64) -> impl Fn(&[f64]) -> Result<f64, String> {
+    move |args| Ok(f(args[0]))
+}
+
+/// The order of a Bessel function, which must be an integer no larger than
+/// [`math::MAX_BESSEL_ORDER`] in absolute value.
+fn bessel_order(name: &str, n: f64) -> Result<i32, String> {
+    let max = math::MAX_BESSEL_ORDER as f64;
+
+    if n == n.trunc() && n.abs() <= max {
+        Ok(n as i32)
+    } else {
+        Err(format!(
+            "the order of {} must be an integer between -{} and {}",
+            name, max, max
+        ))
+    }
+}
+
+impl FunctionRegistry {
+    /// An empty registry, see [`FunctionRegistry::builtin`] for one with the default functions.
+    pub fn new() -> FunctionRegistry {
+        FunctionRegistry::default()
+    }
+
+    /// A registry holding every built-in function.
+    pub fn builtin() -> FunctionRegistry {
+        let mut registry = FunctionRegistry::new();
+
+        let unary_functions = [
+            (
+                "cos",
+                "cos(x)",
+                "cosine of x radians",
+                f64::cos as fn(f64) -> f64,
+            ),
+            ("sin", "sin(x)", "sine of x radians", f64::sin),
+            ("tan", "tan(x)", "tangent of x radians", f64::tan),
+            ("acos", "acos(x)", "arccosine of x, in radians", f64::acos),
+            ("asin", "asin(x)", "arcsine of x, in radians", f64::asin),
+            ("atan", "atan(x)", "arctangent of x, in radians", f64::atan),
+            ("ln", "ln(x)", "natural logarithm of x", f64::ln),
+            ("sqrt", "sqrt(x)", "square root of x", f64::sqrt),
+        ];
+
+        for (name, signature, description, f) in unary_functions {
+            registry.register(
+                name,
+                Function::new(signature, description, Arity::Exact(1), unary(f)),
+            );
+        }
+
+        registry.register(
+            "logab",
+            Function::new(
+                "logab(a, b)",
+                "logarithm of b in base a",
+                Arity::Exact(2),
+                |args| Ok(args[1].ln() / args[0].ln()), // log_a(b) = ln b / ln a
+            ),
+        );
+        registry.register(
+            "nroot",
+            Function::new("nroot(n, x)", "n-th root of x", Arity::Exact(2), |args| {
+                Ok(f64::powf(args[1], 1. / args[0]))
+            }),
+        );
+        registry.register(
+            "polygamma",
+            Function::new(
+                "polygamma(n, x)",
+                "n-th derivative of the digamma function, d/dx ln(x!) = polygamma(0, x + 1)",
+                Arity::Exact(2),
+                |args| match args[0] {
+                    n if n >= 0. && n == n.trunc() && n <= math::MAX_POLYGAMMA_ORDER as f64 => {
+                        Ok(math::polygamma(n as u32, args[1]))
+                    }
+                    _ => Err(format!(
+                        "the order of polygamma must be an integer between 0 and {}",
+                        math::MAX_POLYGAMMA_ORDER
+                    )),
+                },
+            ),
+        );
+        let special_functions = [
+            (
+                "gamma",
+                "gamma(x)",
+                "gamma function, gamma(n) = (n - 1)!",
+                math::gamma as fn(f64) -> f64,
+            ),
+            (
+                "lgamma",
+                "lgamma(x)",
+                "natural logarithm of the absolute value of the gamma function",
+                math::ln_gamma,
+            ),
+            ("erf", "erf(x)", "error function", math::erf),
+            (
+                "erfc",
+                "erfc(x)",
+                "complementary error function, 1 - erf(x)",
+                math::erfc,
+            ),
+            ("zeta", "zeta(s)", "Riemann zeta function", math::zeta),
+        ];
+
+        for (name, signature, description, f) in special_functions {
+            registry.register(
+                name,
+                Function::new(signature, description, Arity::Exact(1), unary(f)),
+            );
+        }
+
+        registry.register(
+            "beta",
+            Function::new(
+                "beta(a, b)",
+                "beta function, gamma(a) gamma(b) / gamma(a + b)",
+                Arity::Exact(2),
+                |args| Ok(math::beta(args[0], args[1])),
+            ),
+        );
+        registry.register(
+            "gammainc",
+            Function::new(
+                "gammainc(a, x)",
+                "regularized lower incomplete gamma function",
+                Arity::Exact(2),
+                |args| match args[0] {
+                    a if a > 0. && args[1] >= 0. => Ok(math::gamma_p(a, args[1])),
+                    _ => Err("gammainc is only defined for a > 0 and x >= 0".to_owned()),
+                },
+            ),
+        );
+        registry.register(
+            "lambertw",
+            Function::new(
+                "lambertw(x, k)",
+                "Lambert W function, the inverse of x e^x, on the branch k = 0 (default) or -1",
+                Arity::Between(1, 2),
+                |args| match args.get(1).copied().unwrap_or(0.) {
+                    k if k == 0. || k == -1. => Ok(math::lambert_w(args[0], k as i32)),
+                    _ => Err("the branch of lambertw must be 0 or -1".to_owned()),
+                },
+            ),
+        );
+        registry.register(
+            "besselj",
+            Function::new(
+                "besselj(n, x)",
+                "Bessel function of the first kind of integer order n",
+                Arity::Exact(2),
+                |args| bessel_order("besselj", args[0]).map(|n| math::bessel_j(n, args[1])),
+            ),
+        );
+        registry.register(
+            "bessely",
+            Function::new(
+                "bessely(n, x)",
+                "Bessel function of the second kind of integer order n",
+                Arity::Exact(2),
+                |args| bessel_order("bessely", args[0]).map(|n| math::bessel_y(n, args[1])),
+            ),
+        );
+        let statistics_functions = [
+            (
+                "min",
+                "min(x, ...)",
+                "smallest of the values",
+                1,
+                statistics::min as fn(&[f64]) -> Result<f64, String>,
+            ),
+            (
+                "max",
+                "max(x, ...)",
+                "largest of the values",
+                1,
+                statistics::max,
+            ),
+            (
+                "sum",
+                "sum(x, ...)",
+                "sum of the values, see also sum(k, a, b, f)",
+                1,
+                statistics::sum,
+            ),
+            (
+                "mean",
+                "mean(x, ...)",
+                "arithmetic mean of the values",
+                1,
+                statistics::mean,
+            ),
+            (
+                "median",
+                "median(x, ...)",
+                "median of the values",
+                1,
+                statistics::median,
+            ),
+            (
+                "mode",
+                "mode(x, ...)",
+                "most frequent of the values, the smallest one in case of a tie",
+                1,
+                statistics::mode,
+            ),
+            (
+                "var",
+                "var(x, ...)",
+                "variance of a sample",
+                2,
+                statistics::var,
+            ),
+            (
+                "varp",
+                "varp(x, ...)",
+                "variance of a whole population",
+                1,
+                statistics::varp,
+            ),
+            (
+                "stdev",
+                "stdev(x, ...)",
+                "standard deviation of a sample",
+                2,
+                statistics::stdev,
+            ),
+            (
+                "stdevp",
+                "stdevp(x, ...)",
+                "standard deviation of a whole population",
+                1,
+                statistics::stdevp,
+            ),
+            (
+                "percentile",
+                "percentile(p, x, ...)",
+                "p-th percentile of the values, interpolating between the closest ones",
+                2,
+                statistics::percentile,
+            ),
+            (
+                "geomean",
+                "geomean(x, ...)",
+                "geometric mean of the values",
+                1,
+                statistics::geomean,
+            ),
+        ];
+
+        for (name, signature, description, min_args, f) in statistics_functions {
+            registry.register(
+                name,
+                Function::new(signature, description, Arity::AtLeast(min_args), f),
+            );
+        }
+
+        distributions::register_all(&mut registry);
+        integers::register_all(&mut registry);
+
+        registry.register(
+            "if",
+            Function::special(
+                "if(cond, a, b)",
+                "a if cond is not zero, b otherwise, only evaluating the chosen branch",
+                Arity::Exact(3),
+            ),
+        );
+        registry.register(
+            "integrate",
+            Function::special(
+                "integrate(f, x, a, b)",
+                "integral of f with respect to x from a to b, either of which may be infinite",
+                Arity::Exact(4),
+            ),
+        );
+        registry.register(
+            "limit",
+            Function::special(
+                "limit(f, x, a)",
+                "limit of f as x tends to a, which may be inf or -inf",
+                Arity::Exact(3),
+            ),
+        );
+        registry.register(
+            "limitleft",
+            Function::special(
+                "limitleft(f, x, a)",
+                "limit of f as x tends to a from below",
+                Arity::Exact(3),
+            ),
+        );
+        registry.register(
+            "limitright",
+            Function::special(
+                "limitright(f, x, a)",
+                "limit of f as x tends to a from above",
+                Arity::Exact(3),
+            ),
+        );
+        registry.register(
+            "deriv",
+            Function::special(
+                "deriv(f, x, a, n)",
+                "n-th derivative of f with respect to x where x is a, computed numerically",
+                Arity::Exact(4),
+            ),
+        );
+        registry.register(
+            "prod",
+            Function::special(
+                "prod(k, a, b, f)",
+                "product of f for every integer k from a to b, which may be inf",
+                Arity::Exact(4),
+            ),
+        );
+
+        registry
+    }
+
+    /// Adds a function, replacing any previous one with the same name.
+    pub fn register(&mut self, name: &str, function: Function) {
+        self.functions.insert(name.to_owned(), function);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.contains_key(name)
+    }
+
+    /// Iterates over the registered functions, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Function)> {
+        self.functions
+            .iter()
+            .map(|(name, function)| (name.as_str(), function))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arity_accepts() {
+        assert!(Arity::Exact(2).accepts(2));
+        assert!(!Arity::Exact(2).accepts(1));
+        assert!(Arity::AtLeast(1).accepts(1));
+        assert!(Arity::AtLeast(1).accepts(5));
+        assert!(!Arity::AtLeast(1).accepts(0));
+        assert!(Arity::Between(1, 3).accepts(2));
+        assert!(!Arity::Between(1, 3).accepts(4));
+    }
+
+    #[test]
+    fn registry_builtin() {
+        let registry = FunctionRegistry::builtin();
+
+        assert!(registry.contains("sqrt"));
+        assert_eq!(registry.get("logab").unwrap().arity(), Arity::Exact(2));
+        assert_eq!(
+            registry.get("sqrt").unwrap().native().unwrap()(&[4.]),
+            Ok(2.)
+        );
+        assert!(registry.get("if").unwrap().native().is_none());
+
+        let polygamma = registry.get("polygamma").unwrap().native().unwrap();
+        assert!(polygamma(&[170., 1.]).unwrap().is_finite());
+        assert!(polygamma(&[200., 1.]).is_err());
+        assert!(polygamma(&[1e9, 1.]).is_err());
+    }
+
+    #[test]
+    fn registry_register() {
+        let mut registry = FunctionRegistry::new();
+        let offset = 10.;
+
+        registry.register(
+            "add",
+            Function::new("add(x, ...)", "", Arity::AtLeast(1), move |args| {
+                Ok(args.iter().sum::<f64>() + offset)
+            }),
+        );
+
+        assert_eq!(
+            registry.get("add").unwrap().native().unwrap()(&[1., 2., 3.]),
+            Ok(16.)
+        );
+        assert_eq!(registry.iter().count(), 1);
+    }
+}
@@ -0,0 +1,744 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
+use std::fmt;
+
+use crate::backend::{Backend, Session};
+use crate::context::Context;
+use crate::errors::SyntaxError;
+use crate::eval::Evaluator;
+use crate::exact::{self, parse_decimal, MAX_EXACT_EXPONENT, MAX_EXACT_FACTORIAL};
+use crate::functions::{Arity, Function, FunctionRegistry};
+use crate::parser::{parse_expression, INTEGER_DIVISION};
+
+/// Largest number of factors `nCr` and `nPr` multiply together, past which computing their result
+/// would take too long.
+const MAX_FACTORS: u64 = 100_000;
+
+/// Largest magnitude below which every integer is represented exactly by an `f64`, 2^53. Integer
+/// functions evaluated in floating point reject arguments and results beyond it, which may have
+/// been rounded.
+const MAX_EXACT_INTEGER: u64 = 1 << 53;
+
+/// Largest index, in absolute value, of the Fibonacci numbers `fib` computes.
+const MAX_FIBONACCI: u64 = 1_000_000;
+
+/// Bases of the Miller-Rabin test, which is deterministic below 3.3e24 with them.
+const WITNESSES: [u32; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// An exact function of integers.
+pub(crate) type IntegerFunction = fn(&[BigInt]) -> Result<BigInt, String>;
+
+const FUNCTIONS: [(&str, &str, &str, Arity, IntegerFunction); 10] = [
+    (
+        "nCr",
+        "nCr(n, k)",
+        "number of ways to choose k of n items, the binomial coefficient",
+        Arity::Exact(2),
+        combinations,
+    ),
+    (
+        "nPr",
+        "nPr(n, k)",
+        "number of ways to arrange k of n items",
+        Arity::Exact(2),
+        permutations,
+    ),
+    (
+        "gcd",
+        "gcd(a, b, ...)",
+        "greatest common divisor of the integers",
+        Arity::AtLeast(2),
+        gcd,
+    ),
+    (
+        "lcm",
+        "lcm(a, b, ...)",
+        "least common multiple of the integers",
+        Arity::AtLeast(2),
+        lcm,
+    ),
+    (
+        "isprime",
+        "isprime(n)",
+        "1 if n is prime, 0 otherwise",
+        Arity::Exact(1),
+        |args| Ok(BigInt::from(is_prime(&args[0]) as u8)),
+    ),
+    (
+        "nextprime",
+        "nextprime(n)",
+        "smallest prime larger than n",
+        Arity::Exact(1),
+        next_prime,
+    ),
+    (
+        "totient",
+        "totient(n)",
+        "Euler's totient, the number of integers from 1 to n coprime with n",
+        Arity::Exact(1),
+        totient,
+    ),
+    (
+        "modpow",
+        "modpow(b, e, m)",
+        "b^e modulo m, between 0 and m - 1",
+        Arity::Exact(3),
+        modpow,
+    ),
+    (
+        "modinv",
+        "modinv(a, m)",
+        "inverse of a modulo m, between 0 and m - 1",
+        Arity::Exact(2),
+        modinv,
+    ),
+    (
+        "fib",
+        "fib(n)",
+        "n-th Fibonacci number, fib(0) = 0 and fib(1) = 1",
+        Arity::Exact(1),
+        fibonacci,
+    ),
+];
+
+/// The exact implementation of the integer function `name`, if there is one.
+pub(crate) fn function(name: &str) -> Option<IntegerFunction> {
+    FUNCTIONS
+        .iter()
+        .find(|(function, ..)| *function == name)
+        .map(|(.., f)| *f)
+}
+
+/// Error of the integer function `name` given or returning an integer beyond
+/// [`MAX_EXACT_INTEGER`] in floating point.
+fn inexact(name: &str) -> String {
+    format!(
+        "{} is only exact up to 2^53 in floating point, use --int or --exact for larger integers",
+        name
+    )
+}
+
+/// Registers the integer functions, which reject arguments that aren't integers, and arguments or
+/// results too large for an `f64` to hold exactly.
+pub(crate) fn register_all(registry: &mut FunctionRegistry) {
+    for (name, signature, description, arity, f) in FUNCTIONS {
+        registry.register(
+            name,
+            Function::new(signature, description, arity, move |args| {
+                let args = args
+                    .iter()
+                    .map(|x| match BigInt::from_f64(*x) {
+                        Some(_) if x.abs() > MAX_EXACT_INTEGER as f64 => Err(inexact(name)),
+                        Some(n) if x.fract() == 0. => Ok(n),
+                        _ => Err(format!("{} is only defined for integers", name)),
+                    })
+                    .collect::<Result<Vec<BigInt>, String>>()?;
+
+                let res = f(&args)?;
+                match res.abs().to_u64() {
+                    Some(n) if n <= MAX_EXACT_INTEGER => Ok(res.to_f64().unwrap()),
+                    _ => Err(inexact(name)),
+                }
+            }),
+        );
+    }
+}
+
+/// Checks that `n` and `k` are non-negative, returning `min(k, n - k)` if it is at most `n`.
+fn choice(name: &str, n: &BigInt, k: &BigInt) -> Result<Option<u64>, String> {
+    if n.is_negative() || k.is_negative() {
+        return Err(format!("{} is only defined for non-negative n and k", name));
+    } else if k > n {
+        return Ok(None);
+    }
+
+    Ok(Some(k.min(&(n - k)).to_u64().unwrap_or(u64::MAX)))
+}
+
+fn combinations(args: &[BigInt]) -> Result<BigInt, String> {
+    let (n, k) = (&args[0], &args[1]);
+
+    let k = match choice("nCr", n, k)? {
+        Some(k) if k > MAX_FACTORS => return Err("nCr(n, k) is too large to compute".to_owned()),
+        Some(k) => k,
+        None => return Ok(BigInt::zero()),
+    };
+
+    // Each partial product is itself a binomial coefficient, so the divisions are exact
+    Ok((1..=k).fold(BigInt::one(), |res, i| res * (n - k + i) / i))
+}
+
+fn permutations(args: &[BigInt]) -> Result<BigInt, String> {
+    let (n, k) = (&args[0], &args[1]);
+
+    if choice("nPr", n, k)?.is_none() {
+        return Ok(BigInt::zero());
+    }
+
+    match k.to_u64() {
+        Some(k) if k <= MAX_FACTORS => Ok((0..k).fold(BigInt::one(), |res, i| res * (n - i))),
+        _ => Err("nPr(n, k) is too large to compute".to_owned()),
+    }
+}
+
+fn gcd(args: &[BigInt]) -> Result<BigInt, String> {
+    Ok(args.iter().fold(BigInt::zero(), |res, x| res.gcd(x)))
+}
+
+fn lcm(args: &[BigInt]) -> Result<BigInt, String> {
+    Ok(args.iter().fold(BigInt::one(), |res, x| res.lcm(x)))
+}
+
+/// Whether `n` is prime, with the Miller-Rabin test. It is exact below 3.3e24, and larger numbers
+/// it accepts are only probable primes, though no counterexample is known.
+pub(crate) fn is_prime(n: &BigInt) -> bool {
+    if n < &BigInt::from(2) {
+        return false;
+    }
+
+    for p in WITNESSES {
+        if *n == BigInt::from(p) {
+            return true;
+        } else if (n % p).is_zero() {
+            return false;
+        }
+    }
+
+    // n - 1 = d 2^s with d odd
+    let m: BigInt = n - 1;
+    let s = m.trailing_zeros().unwrap_or(0);
+    let d = &m >> s;
+
+    'witnesses: for a in WITNESSES {
+        let mut x = BigInt::from(a).modpow(&d, n);
+        if x.is_one() || x == m {
+            continue;
+        }
+
+        for _ in 1..s {
+            x = &x * &x % n;
+            if x == m {
+                continue 'witnesses;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
+fn next_prime(args: &[BigInt]) -> Result<BigInt, String> {
+    let mut n = args[0].clone().max(BigInt::one()) + 1;
+    while !is_prime(&n) {
+        n += 1;
+    }
+
+    Ok(n)
+}
+
+fn totient(args: &[BigInt]) -> Result<BigInt, String> {
+    let n = match args[0].to_u64() {
+        Some(n) if n > 0 => n,
+        _ if args[0].is_positive() => {
+            return Err("totient is only computed for integers below 2^64".to_owned())
+        }
+        _ => return Err("totient is only defined for positive integers".to_owned()),
+    };
+
+    // φ(n) = n Π (1 - 1/p) over the primes p dividing n
+    let res = prime_factors(n)
+        .iter()
+        .fold(n as u128, |res, (p, _)| res / *p as u128 * (*p as u128 - 1));
+
+    Ok(BigInt::from(res))
+}
+
+/// The modulus of a modular operation, which must be positive.
+fn modulus<'a>(name: &str, m: &'a BigInt) -> Result<&'a BigInt, String> {
+    if m.is_positive() {
+        Ok(m)
+    } else {
+        Err(format!("the modulus of {} must be positive", name))
+    }
+}
+
+fn modpow(args: &[BigInt]) -> Result<BigInt, String> {
+    let (b, e) = (&args[0], &args[1]);
+    let m = modulus("modpow", &args[2])?;
+
+    let b = if e.is_negative() {
+        modinv(&[b.clone(), m.clone()])?
+    } else {
+        b.mod_floor(m)
+    };
+
+    Ok(b.modpow(&e.abs(), m))
+}
+
+fn modinv(args: &[BigInt]) -> Result<BigInt, String> {
+    let a = &args[0];
+    let m = modulus("modinv", &args[1])?;
+
+    let res = a.mod_floor(m).extended_gcd(m);
+    if !res.gcd.is_one() {
+        return Err(format!("{} has no inverse modulo {}", a, m));
+    }
+
+    Ok(res.x.mod_floor(m))
+}
+
+fn fibonacci(args: &[BigInt]) -> Result<BigInt, String> {
+    let n = match args[0].abs().to_u64() {
+        Some(n) if n <= MAX_FIBONACCI => n,
+        _ => return Err("fib(n) is too large to compute".to_owned()),
+    };
+
+    // Fast doubling: F(2k) = F(k) (2 F(k+1) - F(k)) and F(2k+1) = F(k)^2 + F(k+1)^2
+    let (mut a, mut b) = (BigInt::zero(), BigInt::one());
+    for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+        let c = &a * (&b * 2 - &a);
+        let d = &a * &a + &b * &b;
+
+        (a, b) = if (n >> bit) & 1 == 1 {
+            (d.clone(), c + d)
+        } else {
+            (c, d)
+        };
+    }
+
+    // F(-n) = (-1)^(n+1) F(n)
+    if args[0].is_negative() && n.is_multiple_of(2) {
+        a = -a;
+    }
+
+    Ok(a)
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+/// A non-trivial divisor of `n`, an odd composite number, found with Pollard's rho algorithm.
+fn pollard_rho(n: u64) -> u64 {
+    for c in 1..n {
+        let f = |x: u64| ((mul_mod(x, x, n) as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = x.abs_diff(y).gcd(&n);
+        }
+
+        // Otherwise the sequence cycled before finding a divisor, another one is tried
+        if d != n {
+            return d;
+        }
+    }
+
+    unreachable!("{} is prime", n)
+}
+
+/// The prime factors of `n`, in increasing order along with their multiplicity.
+pub(crate) fn prime_factors(mut n: u64) -> Vec<(u64, u32)> {
+    let mut primes = Vec::new();
+
+    for p in WITNESSES.map(u64::from) {
+        while n.is_multiple_of(p) {
+            primes.push(p);
+            n /= p;
+        }
+    }
+
+    let mut composites = vec![n];
+    while let Some(n) = composites.pop() {
+        if n == 1 {
+            continue;
+        } else if is_prime(&BigInt::from(n)) {
+            primes.push(n);
+        } else {
+            let d = pollard_rho(n);
+            composites.extend([d, n / d]);
+        }
+    }
+
+    primes.sort_unstable();
+    primes
+        .chunk_by(|p, q| p == q)
+        .map(|run| (run[0], run.len() as u32))
+        .collect()
+}
+
+/// Prime factorization of a non-zero integer, as returned by [`factor`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Factorization {
+    negative: bool,
+    factors: Vec<(u64, u32)>,
+}
+
+impl Factorization {
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The prime factors in increasing order, along with their multiplicity.
+    pub fn factors(&self) -> &[(u64, u32)] {
+        &self.factors
+    }
+}
+
+impl fmt::Display for Factorization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        if self.factors.is_empty() {
+            return write!(f, "1");
+        }
+
+        for (i, (p, multiplicity)) in self.factors.iter().enumerate() {
+            if i > 0 {
+                write!(f, " * ")?;
+            }
+
+            match multiplicity {
+                1 => write!(f, "{}", p)?,
+                _ => write!(f, "{}^{}", p, multiplicity)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Evaluates `src` and factors its value into primes, which requires it to be a non-zero integer
+/// smaller than 2^64 in absolute value. Displays as `2^3 * 3^2 * 5` for `360`.
+///
+/// `src` is evaluated exactly as with [`IntegerBackend`], where the integer variables of `ctx` are
+/// defined, and only in floating point if that fails, as for `sqrt(16)`. Its value must then be
+/// at most 2^53 in absolute value, beyond which it may have been rounded.
+pub fn factor(ctx: &Context, src: &str) -> Result<Factorization, SyntaxError> {
+    let expr = parse_expression(src)?;
+    let error = |msg: &str| Err(SyntaxError::at(src, expr.span, msg.to_owned()));
+
+    let mut session = Session::new(IntegerBackend::new());
+    for (name, value) in ctx.variables() {
+        if let Some(n) = BigInt::from_f64(value).filter(|_| value.fract() == 0.) {
+            session.set_variable(&name, n);
+        }
+    }
+
+    let value = match session.evaluate(src) {
+        Ok(value) => value,
+        Err(_) => {
+            let value = Evaluator::new(src, ctx).eval(&expr)?;
+
+            if value.fract() != 0. || !value.is_finite() {
+                return error("only integers can be factored");
+            } else if value.abs() > MAX_EXACT_INTEGER as f64 {
+                return error("only integers up to 2^53 in absolute value can be factored when they aren't computed exactly, as they may have been rounded");
+            }
+
+            BigInt::from_f64(value).unwrap()
+        }
+    };
+
+    if value.is_zero() {
+        return error("0 has no prime factorization");
+    }
+
+    match value.abs().to_u64() {
+        Some(n) => Ok(Factorization {
+            negative: value.is_negative(),
+            factors: prime_factors(n),
+        }),
+        None => error("only integers smaller than 2^64 in absolute value can be factored"),
+    }
+}
+
+/// Evaluates expressions over arbitrary size integers, failing on anything whose result isn't an
+/// integer rather than approximating it.
+#[derive(Debug, Clone, Default)]
+pub struct IntegerBackend;
+
+impl IntegerBackend {
+    pub fn new() -> IntegerBackend {
+        IntegerBackend
+    }
+}
+
+impl Backend for IntegerBackend {
+    type Value = BigInt;
+
+    fn literal(&self, text: &str) -> Result<BigInt, String> {
+        match parse_decimal(text) {
+            Some(x) if x.is_integer() => Ok(x.to_integer()),
+            Some(_) => Err(format!("{} is not an integer", text)),
+            None => Err(format!("{} is too large", text)),
+        }
+    }
+
+    fn constant(&self, _name: &str) -> Option<BigInt> {
+        None
+    }
+
+    fn unary(&self, op: char, operand: BigInt) -> Result<BigInt, String> {
+        match op {
+            '+' => Ok(operand),
+            '-' => Ok(-operand),
+            '!' if operand.is_negative() => {
+                Err("the factorial is only defined for non-negative integers".to_owned())
+            }
+            '!' => match operand.to_u64() {
+                Some(n) if n <= MAX_EXACT_FACTORIAL => Ok(exact::factorial(n)),
+                _ => Err(format!("{}! is too large to compute", operand)),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn binary(&self, op: char, a: BigInt, b: BigInt) -> Result<BigInt, String> {
+        if matches!(op, '/' | '%' | INTEGER_DIVISION) && b.is_zero() {
+            return Err("division by zero".to_owned());
+        }
+
+        Ok(match op {
+            '+' => a + b,
+            '-' => a - b,
+            '*' => a * b,
+            '/' if (&a % &b).is_zero() => a / b,
+            '/' => {
+                return Err(format!(
+                    "{} / {} is not an integer, use // for the truncated quotient",
+                    a, b
+                ))
+            }
+            // Both truncate towards zero, so that a = b (a // b) + a % b
+            '%' => a % b,
+            INTEGER_DIVISION => a / b,
+            '^' => {
+                if a.is_zero() && !b.is_positive() {
+                    return Err(if b.is_zero() {
+                        "0^0 is undefined".to_owned()
+                    } else {
+                        "division by zero".to_owned()
+                    });
+                } else if a.abs().is_one() {
+                    // Powers of 1 and -1 only depend on the parity of the exponent
+                    return Ok(if b.is_even() { BigInt::one() } else { a });
+                } else if b.is_negative() {
+                    return Err(format!("{}^{} is not an integer", a, b));
+                }
+
+                match b.to_u32() {
+                    Some(n) if n <= MAX_EXACT_EXPONENT => num_traits::pow(a, n as usize),
+                    _ => return Err(format!("{}^{} is too large to compute", a, b)),
+                }
+            }
+            _ => unreachable!(),
+        })
+    }
+
+    fn arity(&self, name: &str) -> Option<Arity> {
+        FUNCTIONS
+            .iter()
+            .find(|(function, ..)| *function == name)
+            .map(|(_, _, _, arity, _)| *arity)
+    }
+
+    fn call(&self, name: &str, args: &[BigInt]) -> Result<BigInt, String> {
+        function(name).unwrap()(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Session;
+
+    fn call(name: &str, args: &[f64]) -> Result<f64, String> {
+        FunctionRegistry::builtin()
+            .get(name)
+            .and_then(|f| f.native())
+            .unwrap()(args)
+    }
+
+    fn exact(name: &str, args: &[i64]) -> Result<String, String> {
+        let args = args.iter().map(|x| BigInt::from(*x)).collect::<Vec<_>>();
+        function(name).unwrap()(&args).map(|res| res.to_string())
+    }
+
+    #[test]
+    fn integer_combinatorics() {
+        assert_eq!(call("nCr", &[5., 2.]), Ok(10.));
+        assert_eq!(call("nCr", &[5., 7.]), Ok(0.));
+        assert_eq!(call("nCr", &[56., 28.]), Ok(7648690600760440.));
+        assert_eq!(
+            call("nCr", &[60., 30.]),
+            Err("nCr is only exact up to 2^53 in floating point, use --int or --exact for larger integers".to_owned())
+        );
+        assert_eq!(
+            exact("nCr", &[100, 50]),
+            Ok("100891344545564193334812497256".to_owned())
+        );
+        assert_eq!(call("nPr", &[5., 2.]), Ok(20.));
+        assert_eq!(call("nPr", &[5., 0.]), Ok(1.));
+        assert_eq!(call("nPr", &[5., 6.]), Ok(0.));
+        assert_eq!(call("fib", &[0.]), Ok(0.));
+        assert_eq!(call("fib", &[10.]), Ok(55.));
+        assert_eq!(call("fib", &[-6.]), Ok(-8.));
+        assert_eq!(call("fib", &[-7.]), Ok(13.));
+        assert_eq!(exact("fib", &[100]), Ok("354224848179261915075".to_owned()));
+        assert!(call("nCr", &[-1., 2.]).is_err());
+        assert!(call("fib", &[1e7]).is_err());
+        assert!(call("fib", &[100.]).is_err());
+    }
+
+    #[test]
+    fn integer_number_theory() {
+        assert_eq!(call("gcd", &[12., 18., -27.]), Ok(3.));
+        assert_eq!(call("gcd", &[0., 5.]), Ok(5.));
+        assert_eq!(call("lcm", &[4., 6., 10.]), Ok(60.));
+        assert_eq!(call("lcm", &[0., 5.]), Ok(0.));
+        assert_eq!(call("totient", &[1.]), Ok(1.));
+        assert_eq!(call("totient", &[36.]), Ok(12.));
+        assert_eq!(call("totient", &[97.]), Ok(96.));
+        assert_eq!(call("modpow", &[4., 13., 497.]), Ok(445.));
+        assert_eq!(call("modpow", &[-2., 3., 5.]), Ok(2.));
+        assert_eq!(call("modpow", &[3., -1., 7.]), Ok(5.));
+        assert_eq!(call("modinv", &[3., 11.]), Ok(4.));
+        assert_eq!(call("modinv", &[-3., 11.]), Ok(7.));
+        assert_eq!(
+            call("modinv", &[6., 9.]),
+            Err("6 has no inverse modulo 9".to_owned())
+        );
+        assert!(call("modpow", &[2., 3., 0.]).is_err());
+        assert!(call("totient", &[0.]).is_err());
+    }
+
+    #[test]
+    fn integer_primes() {
+        let primes = (0..50)
+            .filter(|n| call("isprime", &[*n as f64]) == Ok(1.))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            primes,
+            [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47]
+        );
+        assert_eq!(call("isprime", &[-7.]), Ok(0.));
+        assert_eq!(call("isprime", &[561.]), Ok(0.));
+        assert_eq!(call("isprime", &[2147483647.]), Ok(1.));
+        assert_eq!(call("nextprime", &[-5.]), Ok(2.));
+        assert_eq!(call("nextprime", &[13.]), Ok(17.));
+        assert_eq!(call("nextprime", &[1e6]), Ok(1000003.));
+        assert_eq!(call("isprime", &[9007199254740881.]), Ok(1.));
+        assert!(call("isprime", &[2f64.powi(61) - 1.]).is_err());
+        assert!(call("nextprime", &[2f64.powi(64)]).is_err());
+    }
+
+    #[test]
+    fn integer_factorization() {
+        let factor = |src| {
+            factor(&Context::new(), src)
+                .map(|res| res.to_string())
+                .map_err(|err| err.to_string())
+        };
+
+        assert_eq!(factor("360"), Ok("2^3 * 3^2 * 5".to_owned()));
+        assert_eq!(factor("-97"), Ok("-97".to_owned()));
+        assert_eq!(factor("1"), Ok("1".to_owned()));
+        assert_eq!(factor("2^31 - 1"), Ok("2147483647".to_owned()));
+        assert_eq!(
+            factor("600851475143"),
+            Ok("71 * 839 * 1471 * 6857".to_owned())
+        );
+        assert_eq!(
+            prime_factors(18446744073709551557),
+            [(18446744073709551557, 1)]
+        );
+        assert_eq!(
+            prime_factors(4294967291 * 4294967279),
+            [(4294967279, 1), (4294967291, 1)]
+        );
+        assert_eq!(factor("2^61 - 1"), Ok("2305843009213693951".to_owned()));
+        assert_eq!(factor("-(2^60 + 2^59)"), Ok("-2^59 * 3".to_owned()));
+        assert_eq!(factor("sqrt(16) * 3"), Ok("2^2 * 3".to_owned()));
+        assert!(factor("sqrt(2^120)")
+            .unwrap_err()
+            .contains("may have been rounded"));
+
+        let mut ctx = Context::new();
+        ctx.set_variable("n", 2f64.powi(31));
+        assert_eq!(
+            super::factor(&ctx, "n^2 - 1").unwrap().to_string(),
+            "3 * 715827883 * 2147483647"
+        );
+        assert!(factor("0").is_err());
+        assert!(factor("2.5").is_err());
+        assert!(factor("2^70").is_err());
+    }
+
+    fn int(src: &str) -> Result<String, String> {
+        Session::new(IntegerBackend::new())
+            .evaluate(src)
+            .map(|res| res.to_string())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn integer_mode_operators() {
+        assert_eq!(
+            int("2^100"),
+            Ok("1267650600228229401496703205376".to_owned())
+        );
+        assert_eq!(
+            int("30!"),
+            Ok("265252859812191058636308480000000".to_owned())
+        );
+        assert_eq!(int("100!").unwrap().len(), 158);
+        assert_eq!(int("1e20 + 1"), Ok("100000000000000000001".to_owned()));
+        assert_eq!(int("12 / 4 - 3 * 5"), Ok("-12".to_owned()));
+        assert_eq!(int("-7 // 2"), Ok("-3".to_owned()));
+        assert_eq!(int("-7 % 2"), Ok("-1".to_owned()));
+        assert_eq!(int("(-1)^-3"), Ok("-1".to_owned()));
+        assert_eq!(
+            int("x = 2^64; x * x"),
+            Ok("340282366920938463463374607431768211456".to_owned())
+        );
+        assert_eq!(int("nCr(100, 50) % 1000"), Ok("256".to_owned()));
+        assert_eq!(int("fib(100)"), Ok("354224848179261915075".to_owned()));
+        assert_eq!(int("gcd([2^40, 6^20])"), Ok("1048576".to_owned()));
+        assert_eq!(int("isprime(2^61 - 1)"), Ok("1".to_owned()));
+        assert_eq!(
+            int("nextprime(2^64)"),
+            Ok("18446744073709551629".to_owned())
+        );
+    }
+
+    #[test]
+    fn integer_mode_errors() {
+        assert!(int("7 / 2")
+            .unwrap_err()
+            .contains("7 / 2 is not an integer, use // for the truncated quotient"));
+        assert!(int("2^-1").unwrap_err().contains("2^-1 is not an integer"));
+        assert!(int("1.5").unwrap_err().contains("1.5 is not an integer"));
+        assert!(int("(-1)!").is_err());
+        assert!(int("5 // 0").unwrap_err().contains("division by zero"));
+        assert!(int("0^0").is_err());
+        assert!(int("pi").is_err());
+        assert!(int("sin(1)").is_err());
+    }
+
+    #[test]
+    fn integer_errors() {
+        assert_eq!(
+            call("nCr", &[5.5, 2.]),
+            Err("nCr is only defined for integers".to_owned())
+        );
+        assert!(call("gcd", &[f64::NAN, 2.]).is_err());
+        assert!(call("fib", &[f64::INFINITY]).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use crate::errors::*;
+use crate::parser::{INTEGER_DIVISION, OPERATORS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Number(f64),
+    Identifier(String),
+    Operator(char),
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Quote,
+    Equals,
+    Separator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat_while(&mut self, pred: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&pred) {
+            self.bump();
+        }
+    }
+
+    fn number(&mut self, start: usize) -> Result<TokenKind, SyntaxError> {
+        self.eat_while(|c| c.is_ascii_digit());
+
+        if self.peek() == Some('.') {
+            self.bump();
+            self.eat_while(|c| c.is_ascii_digit());
+        }
+
+        // Only treat `e` as an exponent when digits follow, so that `2e` still reads as `2` `e`
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let exponent = match self.peek_nth(1) {
+                Some('+' | '-') => self.peek_nth(2).is_some_and(|c| c.is_ascii_digit()),
+                Some(c) => c.is_ascii_digit(),
+                None => false,
+            };
+
+            if exponent {
+                self.bump();
+                if matches!(self.peek(), Some('+' | '-')) {
+                    self.bump();
+                }
+                self.eat_while(|c| c.is_ascii_digit());
+            }
+        }
+
+        let text = &self.src[start..self.pos];
+
+        text.parse::<f64>().map(TokenKind::Number).map_err(|_| {
+            SyntaxError::at(
+                self.src,
+                Span::new(start, self.pos),
+                "unkown token".to_owned(),
+            )
+        })
+    }
+
+    fn next_token(&mut self) -> Option<Result<Token, SyntaxError>> {
+        self.eat_while(|c| c.is_whitespace() && c != '\n');
+
+        let start = self.pos;
+        let c = self.peek()?;
+
+        let kind = if c.is_ascii_digit() || c == '.' {
+            self.number(start)
+        } else if c.is_alphabetic() || c == '_' {
+            self.eat_while(|c| c.is_alphanumeric() || c == '_');
+            Ok(TokenKind::Identifier(self.src[start..self.pos].to_owned()))
+        } else {
+            self.bump();
+
+            match c {
+                '(' => Ok(TokenKind::OpenParen),
+                ')' => Ok(TokenKind::CloseParen),
+                '[' => Ok(TokenKind::OpenBracket),
+                ']' => Ok(TokenKind::CloseBracket),
+                ',' => Ok(TokenKind::Comma),
+                '"' => Ok(TokenKind::Quote),
+                '=' => Ok(TokenKind::Equals),
+                ';' | '\n' => Ok(TokenKind::Separator),
+                '/' if self.peek() == Some('/') => {
+                    self.bump();
+                    Ok(TokenKind::Operator(INTEGER_DIVISION))
+                }
+                c if c != INTEGER_DIVISION && OPERATORS.contains_key(&c) => {
+                    Ok(TokenKind::Operator(c))
+                }
+                _ => Err(SyntaxError::at(
+                    self.src,
+                    Span::new(start, self.pos),
+                    "unkown token".to_owned(),
+                )),
+            }
+        };
+
+        Some(kind.map(|kind| Token {
+            kind,
+            span: Span::new(start, self.pos),
+        }))
+    }
+}
+
+/// Splits `src` into tokens, each carrying the byte span it was read from.
+pub fn tokenize(src: &str) -> Result<Vec<Token>, SyntaxError> {
+    let mut lexer = Lexer { src, pos: 0 };
+    let mut tokens = Vec::new();
+
+    while let Some(token) = lexer.next_token() {
+        tokens.push(token?);
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        tokenize(src)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_empty() {
+        assert!(kinds("").is_empty());
+        assert!(kinds("   ").is_empty());
+    }
+
+    #[test]
+    fn tokenize_numbers() {
+        assert_eq!(kinds("1"), vec![TokenKind::Number(1.)]);
+        assert_eq!(kinds("1."), vec![TokenKind::Number(1.)]);
+        assert_eq!(kinds(".5"), vec![TokenKind::Number(0.5)]);
+        assert_eq!(kinds("1e3"), vec![TokenKind::Number(1000.)]);
+        assert_eq!(kinds("1e-3"), vec![TokenKind::Number(0.001)]);
+    }
+
+    #[test]
+    fn tokenize_number_before_e() {
+        assert_eq!(
+            kinds("2e"),
+            vec![TokenKind::Number(2.), TokenKind::Identifier("e".to_owned())]
+        );
+    }
+
+    #[test]
+    fn tokenize_expression() {
+        assert_eq!(
+            kinds("sin(pi) * -2!"),
+            vec![
+                TokenKind::Identifier("sin".to_owned()),
+                TokenKind::OpenParen,
+                TokenKind::Identifier("pi".to_owned()),
+                TokenKind::CloseParen,
+                TokenKind::Operator('*'),
+                TokenKind::Operator('-'),
+                TokenKind::Number(2.),
+                TokenKind::Operator('!'),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_integer_division() {
+        assert_eq!(
+            kinds("7 // 2 / 1"),
+            vec![
+                TokenKind::Number(7.),
+                TokenKind::Operator(INTEGER_DIVISION),
+                TokenKind::Number(2.),
+                TokenKind::Operator('/'),
+                TokenKind::Number(1.),
+            ]
+        );
+        assert!(tokenize("7 \\ 2").is_err());
+    }
+
+    #[test]
+    fn tokenize_brackets() {
+        assert_eq!(
+            kinds("[1, x]"),
+            vec![
+                TokenKind::OpenBracket,
+                TokenKind::Number(1.),
+                TokenKind::Comma,
+                TokenKind::Identifier("x".to_owned()),
+                TokenKind::CloseBracket,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_statements() {
+        assert_eq!(
+            kinds("x = 1; x\n2"),
+            vec![
+                TokenKind::Identifier("x".to_owned()),
+                TokenKind::Equals,
+                TokenKind::Number(1.),
+                TokenKind::Separator,
+                TokenKind::Identifier("x".to_owned()),
+                TokenKind::Separator,
+                TokenKind::Number(2.),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_spans() {
+        let tokens = tokenize(" 12 + abc").unwrap();
+
+        assert_eq!(tokens[0].span, Span::new(1, 3));
+        assert_eq!(tokens[1].span, Span::new(4, 5));
+        assert_eq!(tokens[2].span, Span::new(6, 9));
+    }
+
+    #[test]
+    fn tokenize_unkown_character() {
+        assert_eq!(
+            format!("{}", tokenize("1 $ 2").expect_err("")),
+            "error while parsing token \"$\" in expression \"1 $ 2\": unkown token, 1 $ <-- HERE"
+        );
+        assert!(tokenize(".").is_err());
+    }
+}
@@ -0,0 +1,69 @@
+//! Parsing and evaluation of mathematical expressions, as used by the `so` command line utility.
+//!
+//! ```
+//! use solve::{evaluate, Arity, Context, Function};
+//!
+//! assert_eq!(evaluate("1+2*3").unwrap(), 7.);
+//!
+//! let mut ctx = Context::new();
+//! ctx.set_constant("g", 9.81);
+//! ctx.set_function("double", 1, |args| args[0] * 2.);
+//! assert_eq!(ctx.evaluate("double(g)").unwrap(), 19.62);
+//!
+//! ctx.functions_mut().register(
+//!     "sum",
+//!     Function::new("sum(x, ...)", "sum of every argument", Arity::AtLeast(1), |args| {
+//!         Ok(args.iter().sum())
+//!     }),
+//! );
+//! assert_eq!(ctx.evaluate("sum(1, 2, 3)").unwrap(), 6.);
+//! ```
+
+#[cfg(test)]
+#[macro_use]
+mod helpers;
+mod ast;
+mod backend;
+mod complex;
+mod context;
+mod derivative;
+mod distributions;
+mod errors;
+mod eval;
+mod exact;
+mod functions;
+mod integers;
+mod lexer;
+mod limit;
+mod math;
+mod matrix;
+mod numeric;
+mod parser;
+mod polynomial;
+mod precise;
+mod quadrature;
+mod richardson;
+mod series;
+mod simplify;
+mod solver;
+mod statistics;
+mod symbolic;
+mod system;
+mod units;
+
+pub use backend::{Backend, Session};
+pub use complex::{ComplexBackend, ComplexValue};
+pub use context::Context;
+pub use derivative::{differentiate, Derivative};
+pub use errors::SyntaxError;
+pub use eval::evaluate;
+pub use exact::{ExactBackend, ExactValue};
+pub use functions::{Arity, Function, FunctionRegistry};
+pub use integers::{factor, Factorization, IntegerBackend};
+pub use matrix::{MatrixBackend, MatrixValue};
+pub use precise::{PreciseBackend, PreciseValue};
+pub use quadrature::{integrate, Integral};
+pub use simplify::simplify;
+pub use solver::{solve, solve_with, unknowns, Solutions, SolveOptions};
+pub use system::{solve_system, SystemSolution};
+pub use units::{Quantity, UnitsBackend};
@@ -0,0 +1,383 @@
+use crate::errors::*;
+use crate::lexer::Span;
+use crate::richardson;
+
+/// Number of steps, each half as long as the previous one, at which the function is sampled.
+const MAX_LEVELS: usize = 24;
+
+/// Once the estimated error is below [`ACCEPTED_ERROR`], sampling stops as soon as the estimates
+/// or their rounding errors get this much worse than the best one.
+const SAFE: f64 = 2.;
+
+/// Estimated error, relative to the result or absolute below 1, up to which a limit or derivative
+/// is taken as found.
+const ACCEPTED_ERROR: f64 = 1e-6;
+
+/// Number of steps after which the extrapolated values may be trusted, as agreeing early values
+/// such as `0.5^0.5` and `0.25^0.25` are a coincidence more often than not.
+const MIN_LEVELS: usize = 3;
+
+/// Number of samples whose magnitude must keep growing for a limit to be taken as infinite.
+const DIVERGING_SAMPLES: usize = 8;
+
+/// Highest order of the derivatives computed by [`Extrapolation::derivative`].
+pub(crate) const MAX_DERIVATIVE_ORDER: u32 = 8;
+
+/// Direction a limit is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Side {
+    Left,
+    Right,
+    Both,
+}
+
+/// Result of an extrapolation, along with an estimate of its absolute error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Estimate {
+    pub value: f64,
+    pub error: f64,
+    /// Whether sampling stopped once the estimates got worse rather than after [`MAX_LEVELS`]
+    /// steps. Estimates that were still improving, such as those of `x ln(x)` at 0 whose error
+    /// isn't a series in powers of `x`, may be further off than their estimated error.
+    pub settled: bool,
+}
+
+impl Estimate {
+    fn is_found(&self) -> bool {
+        self.error <= ACCEPTED_ERROR * self.value.abs().max(1.)
+    }
+
+    pub fn is_accurate(&self) -> bool {
+        self.settled && self.is_found()
+    }
+}
+
+/// Computes limits and derivatives of `f` at a point from its values at ever closer points, using
+/// Richardson's extrapolation.
+pub(crate) struct Extrapolation<'a, F> {
+    src: &'a str,
+    span: Span,
+    f: F,
+}
+
+impl<'a, F: FnMut(f64) -> Result<f64, SyntaxError>> Extrapolation<'a, F> {
+    /// `span` is the call being evaluated, and is only used to report errors.
+    pub fn new(src: &'a str, span: Span, f: F) -> Extrapolation<'a, F> {
+        Extrapolation { src, span, f }
+    }
+
+    fn error(&self, msg: String) -> SyntaxError {
+        SyntaxError::at(self.src, self.span, msg)
+    }
+
+    /// Limit of `f` as its argument tends to `a` from `side`. Infinite values of `a` can only be
+    /// tended to from finite ones, whatever the side. Limits that aren't found but towards which
+    /// `f` keeps growing are infinite. Without a side, a side where `f` isn't defined is ignored,
+    /// so that the limit of `sqrt(x)` at 0 is 0.
+    pub fn limit(&mut self, a: f64, side: Side) -> Result<Estimate, SyntaxError> {
+        if a.is_nan() {
+            return Err(self.error("the limit point must be a number".to_owned()));
+        }
+
+        let (left, right) = match (a.is_infinite(), side) {
+            (true, _) | (false, Side::Right) => (None, self.one_sided(a, Side::Right)?),
+            (false, Side::Left) => (self.one_sided(a, Side::Left)?, None),
+            (false, Side::Both) => (
+                self.one_sided(a, Side::Left)?,
+                self.one_sided(a, Side::Right)?,
+            ),
+        };
+
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                let (tolerance, scale) = (ACCEPTED_ERROR, left.value.abs().max(1.));
+                let difference = (left.value - right.value).abs();
+                if left.value == right.value || difference <= tolerance * scale && scale.is_finite()
+                {
+                    return Ok(Estimate {
+                        value: (left.value + right.value) / 2.,
+                        error: left.error.max(right.error),
+                        settled: left.settled && right.settled,
+                    });
+                }
+
+                Err(self.error(format!(
+                    "the limit from the left, {}, differs from the limit from the right, {}",
+                    left.value, right.value
+                )))
+            }
+            (Some(limit), None) | (None, Some(limit)) => Ok(limit),
+            (None, None) => Err(self.error(format!("the function is not defined near {}", a))),
+        }
+    }
+
+    /// Limit from `side`, or `None` if `f` isn't defined on that side. Errors are assumed to be
+    /// series in powers of the distance to `a`, or failing that of its square root, which covers
+    /// functions such as `sqrt(x)` at 0. Failing both, the closest estimate is kept, but it isn't
+    /// accurate.
+    fn one_sided(&mut self, a: f64, side: Side) -> Result<Option<Estimate>, SyntaxError> {
+        let point = move |h: f64| match (a.is_infinite(), side) {
+            (true, _) => a.signum() / h,
+            (false, Side::Left) => a - h * a.abs().max(1.),
+            (false, _) => a + h * a.abs().max(1.),
+        };
+
+        let mut samples = Vec::new();
+        let mut closest: Option<Estimate> = None;
+
+        for power in [1., 0.5] {
+            samples.clear();
+            let estimate = self.extrapolate(0.125, power, |f, h| {
+                let value = f(point(h))?;
+                samples.push(value);
+                Ok((value, value.abs() * f64::EPSILON))
+            })?;
+
+            if estimate.is_accurate() {
+                return Ok(Some(estimate));
+            }
+            if estimate.is_found() && closest.is_none_or(|closest| estimate.error < closest.error) {
+                closest = Some(estimate);
+            }
+        }
+
+        if closest.is_some() {
+            return Ok(closest);
+        }
+
+        if samples.iter().all(|value| value.is_nan()) {
+            return Ok(None);
+        }
+
+        // The last samples growing in magnitude, with the same sign, hint at an infinite limit
+        let last = &samples[samples.len().saturating_sub(DIVERGING_SAMPLES)..];
+        let is_diverging = last.len() == DIVERGING_SAMPLES
+            && last
+                .windows(2)
+                .all(|pair| pair[1].abs() > pair[0].abs() && pair[1].signum() == pair[0].signum());
+
+        if is_diverging {
+            return Ok(Some(Estimate {
+                value: f64::INFINITY.copysign(last[0]),
+                error: 0.,
+                settled: true,
+            }));
+        }
+
+        Err(self.error("the limit doesn't exist or could not be found".to_owned()))
+    }
+
+    /// Derivative of `order` of `f` at `a`, from central differences. Its error is estimated, see
+    /// [`Estimate::is_accurate`], as high orders quickly lose precision.
+    pub fn derivative(&mut self, a: f64, order: u32) -> Result<Estimate, SyntaxError> {
+        let scale = a.abs().max(1.);
+        let coefficients = binomials(order);
+
+        // Central differences, whose error only has even powers of the step. Their rounding error
+        // grows as the step to the power of `-order`, so higher orders start with longer steps.
+        let h0 = 0.25 * 2f64.sqrt().powi(order as i32);
+        let estimate = self.extrapolate(h0, 2., |f, h| {
+            let h = h * scale;
+            let (mut res, mut magnitude) = (0., 0.);
+
+            for (i, coefficient) in coefficients.iter().enumerate() {
+                let x = a + (order as f64 / 2. - i as f64) * h;
+                let sign = if i % 2 == 0 { 1. } else { -1. };
+                let term = coefficient * f(x)?;
+                res += sign * term;
+                magnitude += term.abs();
+            }
+
+            let power = h.powi(order as i32);
+            Ok((res / power, magnitude * f64::EPSILON / power))
+        })?;
+
+        if estimate.value.is_nan() {
+            return Err(self.error(format!("the function is not defined around {}", a)));
+        }
+
+        Ok(estimate)
+    }
+
+    /// Extrapolates `sample(f, h)`, which returns a value and its rounding error, to `h = 0` from
+    /// steps `h0`, `h0 / 2`, `h0 / 4`..., assuming its error is a series in powers of `h^power`.
+    /// Returns the value with the lowest estimated error of the extrapolation table.
+    fn extrapolate(
+        &mut self,
+        h0: f64,
+        power: f64,
+        mut sample: impl FnMut(&mut F, f64) -> Result<(f64, f64), SyntaxError>,
+    ) -> Result<Estimate, SyntaxError> {
+        let mut best = Estimate {
+            value: f64::NAN,
+            error: f64::INFINITY,
+            settled: false,
+        };
+        let mut table = richardson::Table::new(power);
+        let mut h = h0;
+
+        for level in 0..MAX_LEVELS {
+            let (value, rounding) = sample(&mut self.f, h)?;
+            let row = table.push(value, rounding);
+
+            if level == 0 && value.is_finite() {
+                best.value = value;
+            }
+
+            for entry in &row[1..] {
+                if level >= MIN_LEVELS && entry.error <= best.error {
+                    best = Estimate {
+                        value: entry.value,
+                        error: entry.error,
+                        settled: false,
+                    };
+                }
+            }
+
+            // Later estimates can't do better once they change by no more than their rounding
+            // error, nor once they or their rounding errors get worse than the best one
+            let last = row[row.len() - 1];
+            if best.is_found()
+                && row.len() > 1
+                && (last.error <= last.rounding
+                    || last.error >= SAFE * best.error
+                    || last.rounding >= SAFE * best.error)
+            {
+                best.settled = true;
+                break;
+            }
+
+            h /= 2.;
+        }
+
+        Ok(best)
+    }
+}
+
+/// Binomial coefficients of `n`.
+fn binomials(n: u32) -> Vec<f64> {
+    let mut coefficients = vec![1.];
+
+    for k in 1..=n {
+        let previous = coefficients[k as usize - 1];
+        coefficients.push(previous * (n - k + 1) as f64 / k as f64);
+    }
+
+    coefficients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(f: fn(f64) -> f64, a: f64, side: Side) -> Result<f64, SyntaxError> {
+        Extrapolation::new("", Span::new(0, 0), |x| Ok(f(x)))
+            .limit(a, side)
+            .map(|limit| limit.value)
+    }
+
+    fn derivative(f: fn(f64) -> f64, a: f64, order: u32) -> Estimate {
+        Extrapolation::new("", Span::new(0, 0), |x| Ok(f(x)))
+            .derivative(a, order)
+            .unwrap()
+    }
+
+    #[test]
+    fn limit_finite() {
+        assert_close!(limit(|x| x.sin() / x, 0., Side::Both).unwrap(), 1., 1e-10);
+        assert_close!(
+            limit(|x| (1. - x.cos()) / (x * x), 0., Side::Both).unwrap(),
+            0.5,
+            1e-7
+        );
+        assert_close!(
+            limit(|x| (x * x - 1.) / (x - 1.), 1., Side::Both).unwrap(),
+            2.,
+            1e-10
+        );
+        assert_close!(limit(|x| x.powf(x), 0., Side::Both).unwrap(), 1., 1e-6);
+        assert_close!(limit(|x| x.sqrt(), 0., Side::Both).unwrap(), 0., 1e-10);
+        assert_close!(
+            limit(|x| 1. + (x - 1.).sqrt(), 1., Side::Right).unwrap(),
+            1.,
+            1e-10
+        );
+        assert!(limit(|x| x.sqrt(), 0., Side::Left).is_err());
+        assert_close!(limit(|x| x * x, 3., Side::Both).unwrap(), 9., 1e-12);
+    }
+
+    #[test]
+    fn limit_one_sided() {
+        let sign = |x: f64| x / x.abs();
+        assert_close!(limit(sign, 0., Side::Left).unwrap(), -1., 1e-12);
+        assert_close!(limit(sign, 0., Side::Right).unwrap(), 1., 1e-12);
+        assert!(limit(sign, 0., Side::Both).is_err());
+    }
+
+    #[test]
+    fn limit_infinite() {
+        assert_close!(
+            limit(|x| (1. + 1. / x).powf(x), f64::INFINITY, Side::Both).unwrap(),
+            std::f64::consts::E,
+            1e-8
+        );
+        assert_close!(
+            limit(|x| 1. / x, f64::NEG_INFINITY, Side::Both).unwrap(),
+            0.,
+            1e-12
+        );
+        assert_eq!(
+            limit(|x| 1. / (x * x), 0., Side::Both).unwrap(),
+            f64::INFINITY
+        );
+        assert_eq!(
+            limit(|x| x.ln(), 0., Side::Right).unwrap(),
+            f64::NEG_INFINITY
+        );
+        assert_eq!(
+            limit(|x| 1. / x, 0., Side::Left).unwrap(),
+            f64::NEG_INFINITY
+        );
+        assert!(limit(|x| 1. / x, 0., Side::Both).is_err());
+        assert!(limit(|x| (1. / x).sin(), 0., Side::Right).is_err());
+    }
+
+    #[test]
+    fn limit_slowly_converging() {
+        // Errors in powers of x times ln(x), which extrapolation doesn't remove
+        let limit = |f: fn(f64) -> f64| {
+            Extrapolation::new("", Span::new(0, 0), |x| Ok(f(x)))
+                .limit(0., Side::Both)
+                .unwrap()
+        };
+
+        let estimate = limit(|x| x * x.ln());
+        assert!(!estimate.is_accurate(), "{:?}", estimate);
+        assert_close!(estimate.value, 0., 1e-6);
+
+        let estimate = limit(|x| x.powf(x));
+        assert!(!estimate.is_accurate(), "{:?}", estimate);
+        assert_close!(estimate.value, 1., 1e-6);
+    }
+
+    #[test]
+    fn derivative_orders() {
+        for order in 0..=7 {
+            let estimate = derivative(f64::exp, 0., order);
+            assert!(estimate.is_accurate(), "{} {:?}", order, estimate);
+            assert_close!(estimate.value, 1., 1e-6);
+        }
+        assert_close!(derivative(f64::exp, 0., 8).value, 1., 1e-5);
+
+        let estimate = derivative(|_| 5., 0., 3);
+        assert!(estimate.is_accurate(), "{:?}", estimate);
+        assert_eq!(estimate.value, 0.);
+
+        assert_close!(derivative(f64::sin, 1., 1).value, 1f64.cos(), 1e-10);
+        assert_close!(derivative(|x| x.powi(3), 100., 1).value, 30000., 1e-10);
+        assert_close!(derivative(|x| x.powi(3), 2., 2).value, 12., 1e-8);
+        assert_close!(derivative(|x| 1. / x, 0.5, 1).value, -4., 1e-8);
+        assert_close!(derivative(f64::sin, 1., 6).value, -1f64.sin(), 1e-6);
+        assert_close!(derivative(f64::ln, 2., 4).value, -0.375, 1e-6);
+    }
+}
@@ -1,36 +1,421 @@
 use std::env;
+use std::process;
 
-#[macro_use]
-mod helpers;
-mod errors;
-mod math;
-mod parser;
+use std::iter::Peekable;
 
-use parser::*;
+use solve::{
+    differentiate, evaluate, factor, simplify, solve_system, solve_with, unknowns, Backend,
+    ComplexBackend, Context, ExactBackend, IntegerBackend, MatrixBackend, PreciseBackend, Session,
+    SolveOptions, UnitsBackend,
+};
+
+mod repl;
+
+/// Number of fractional digits printed by `--decimal` before cutting the expansion short.
+const DECIMAL_DIGITS: usize = 50;
+
+const USAGE: &str = "usage: so [--exact] [--decimal] [--precision <digits>] [--complex] [--polar] [--units] [--matrix] [--int] [<expression>...]
+       so solve [--interval <a> <b>] [--guess <x>] [--tolerance <tol>] [--iterations <n>] <equations> [for <unknown>, ...]
+       so diff <expression> [by <variable>] [at <value>]
+       so simplify <expression>
+       so factor <expression>";
+
+/// Options given before the expression.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    exact: bool,
+    /// Also print the decimal expansion of exact results, implies `exact`.
+    decimal: bool,
+    /// Number of significant digits to evaluate with, instead of using `f64`.
+    precision: Option<usize>,
+    complex: bool,
+    /// Print complex results in polar form, implies `complex`.
+    polar: bool,
+    units: bool,
+    matrix: bool,
+    /// Evaluate with arbitrary size integers.
+    int: bool,
+}
 
 fn main() {
-    if env::args().len() < 2 {
-        println!("Incorrect usage, please use \"so <expression> [<expression>...]\"");
-        std::process::exit(1);
-    }
-
-    let expr: String = env::args()
-        .skip(1)
-        .fold(String::new(), |mut partial, current| {
-            partial.push_str(&current);
-            partial
-        })
-        .chars()
-        .filter(|c: &char| c != &' ')
-        .collect();
-
-    match parse(&expr, &expr, 0) {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1).peekable();
+
+    while let Some(arg) = args.next_if(|arg| arg.starts_with("--")) {
+        match arg.as_str() {
+            "--exact" => options.exact = true,
+            "--decimal" => {
+                options.exact = true;
+                options.decimal = true;
+            }
+            "--complex" => options.complex = true,
+            "--units" => options.units = true,
+            "--matrix" => options.matrix = true,
+            "--int" => options.int = true,
+            "--polar" => {
+                options.complex = true;
+                options.polar = true;
+            }
+            "--precision" => match args.next().and_then(|digits| digits.parse().ok()) {
+                Some(digits) if digits > 0 => options.precision = Some(digits),
+                _ => {
+                    println!(
+                        "expected a positive number of digits after --precision, {}",
+                        USAGE
+                    );
+                    process::exit(1);
+                }
+            },
+            _ => {
+                println!("unknown option \"{}\", {}", arg, USAGE);
+                process::exit(1);
+            }
+        }
+    }
+
+    let modes = [
+        options.exact,
+        options.precision.is_some(),
+        options.complex,
+        options.units,
+        options.matrix,
+        options.int,
+    ];
+
+    if modes.iter().filter(|mode| **mode).count() > 1 {
+        println!(
+            "only one of --exact, --precision, --complex, --units, --matrix and --int can be given, {}",
+            USAGE
+        );
+        process::exit(1);
+    }
+
+    if args.peek().map(String::as_str) == Some("solve") {
+        if options != Options::default() {
+            println!(
+                "options are not supported when solving equations, {}",
+                USAGE
+            );
+            process::exit(1);
+        }
+
+        args.next();
+        let options = solve_options(&mut args);
+        run_solve(&args.collect::<Vec<String>>().join(" "), &options);
+        return;
+    }
+
+    if args.peek().map(String::as_str) == Some("diff") {
+        if options != Options::default() {
+            println!("options are not supported when differentiating, {}", USAGE);
+            process::exit(1);
+        }
+
+        args.next();
+        run_diff(&args.collect::<Vec<String>>().join(" "));
+        return;
+    }
+
+    if args.peek().map(String::as_str) == Some("simplify") {
+        if options != Options::default() {
+            println!("options are not supported when simplifying, {}", USAGE);
+            process::exit(1);
+        }
+
+        args.next();
+        let expr = args.collect::<Vec<String>>().join(" ");
+
+        if expr.trim().is_empty() {
+            println!("expected an expression, {}", USAGE);
+            process::exit(1);
+        }
+
+        match simplify(&Context::new(), &expr) {
+            Ok(res) => println!("{}", res),
+            Err(err) => {
+                println!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.peek().map(String::as_str) == Some("factor") {
+        if options != Options::default() {
+            println!("options are not supported when factoring, {}", USAGE);
+            process::exit(1);
+        }
+
+        args.next();
+        let expr = args.collect::<Vec<String>>().join(" ");
+
+        if expr.trim().is_empty() {
+            println!("expected an expression, {}", USAGE);
+            process::exit(1);
+        }
+
+        match factor(&Context::new(), &expr) {
+            Ok(res) => println!("{}", res),
+            Err(err) => {
+                println!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let expr = args.collect::<Vec<String>>().join(" ");
+
+    if expr.is_empty() {
+        if options != Options::default() {
+            println!("expected an expression, {}", USAGE);
+            process::exit(1);
+        }
+
+        repl::run();
+        return;
+    }
+
+    if options.exact {
+        run(ExactBackend::new(), &expr, |res| {
+            let decimal = res.decimal(DECIMAL_DIGITS);
+
+            if options.decimal && res.is_exact() && decimal != res.to_string() {
+                println!("{} = {}", res, decimal);
+            } else {
+                println!("{}", res);
+            }
+        });
+        return;
+    }
+
+    if let Some(digits) = options.precision {
+        run(PreciseBackend::new(digits), &expr, |res| {
+            println!("{:.*}", digits, res)
+        });
+        return;
+    }
+
+    if options.complex {
+        run(ComplexBackend::new(), &expr, |res| {
+            if options.polar {
+                println!("{}", res.polar());
+            } else {
+                println!("{}", res);
+            }
+        });
+        return;
+    }
+
+    if options.units {
+        run(UnitsBackend::new(), &expr, |res| println!("{}", res));
+        return;
+    }
+
+    if options.matrix {
+        run(MatrixBackend::new(), &expr, |res| println!("{}", res));
+        return;
+    }
+
+    if options.int {
+        run(IntegerBackend::new(), &expr, |res| println!("{}", res));
+        return;
+    }
+
+    let mut ctx = Context::new();
+
+    match ctx.execute(&expr) {
         Ok(res) => {
-            println!("{}", res);
+            for warning in ctx.take_warnings() {
+                println!("warning: {}", warning);
+            }
+
+            if let Some(res) = res {
+                println!("{}", res);
+            }
+        }
+        Err(err) => {
+            println!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Evaluates `expr` with an alternative backend, printing its result with `print`.
+fn run<B: Backend>(backend: B, expr: &str, print: impl Fn(B::Value)) {
+    match Session::new(backend).execute(expr) {
+        Ok(Some(res)) => print(res),
+        Ok(None) => {}
+        Err(err) => {
+            println!("{}", err);
+            process::exit(1);
         }
+    }
+}
+
+/// Parses the options given right after `solve`, which configure the numeric root finder.
+fn solve_options(args: &mut Peekable<impl Iterator<Item = String>>) -> SolveOptions {
+    let mut options = SolveOptions::default();
+
+    let value = |args: &mut Peekable<_>, option: &str| match args
+        .next()
+        .map(|value: String| evaluate(&value))
+    {
+        Some(Ok(value)) if value.is_finite() => value,
+        _ => {
+            println!("expected a number after {}, {}", option, USAGE);
+            process::exit(1);
+        }
+    };
+
+    while let Some(arg) = args.next_if(|arg| arg.starts_with("--")) {
+        match arg.as_str() {
+            "--interval" => options.interval = Some((value(args, &arg), value(args, &arg))),
+            "--guess" => options.guess = Some(value(args, &arg)),
+            "--tolerance" => match value(args, &arg) {
+                tolerance if tolerance > 0. => options.tolerance = tolerance,
+                _ => {
+                    println!("expected a positive tolerance, {}", USAGE);
+                    process::exit(1);
+                }
+            },
+            "--iterations" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(iterations) if iterations > 0 => options.max_iterations = iterations,
+                _ => {
+                    println!(
+                        "expected a positive number of iterations after --iterations, {}",
+                        USAGE
+                    );
+                    process::exit(1);
+                }
+            },
+            _ => {
+                println!("unknown option \"{}\", {}", arg, USAGE);
+                process::exit(1);
+            }
+        }
+    }
+
+    options
+}
+
+/// Solves `src`, one or more equations optionally followed by `for <unknown>, ...`. Without
+/// `for`, every unknown name is solved for. Several equations or unknowns are solved as a system
+/// of linear equations.
+fn run_solve(src: &str, options: &SolveOptions) {
+    let ctx = Context::new();
+    let words = src.split_whitespace().collect::<Vec<&str>>();
+
+    let (equations, variables) = match words.iter().rposition(|word| *word == "for") {
+        Some(i) => {
+            let variables = words[i + 1..]
+                .iter()
+                .flat_map(|word| word.split(','))
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned)
+                .collect::<Vec<String>>();
+
+            if variables.is_empty() {
+                println!("expected an unknown after \"for\", {}", USAGE);
+                process::exit(1);
+            }
+
+            (words[..i].join(" "), variables)
+        }
+        None => match unknowns(&ctx, src) {
+            Ok(names) if names.is_empty() => (src.to_owned(), vec!["x".to_owned()]),
+            Ok(names) => (src.to_owned(), names),
+            Err(err) => {
+                println!("{}", err);
+                process::exit(1);
+            }
+        },
+    };
+
+    if equations.trim().is_empty() {
+        println!("expected an equation, {}", USAGE);
+        process::exit(1);
+    }
+
+    let res = if variables.len() > 1 || equations.contains([';', '\n']) {
+        if *options != SolveOptions::default() {
+            println!(
+                "the numeric solver options only apply to single equations, {}",
+                USAGE
+            );
+            process::exit(1);
+        }
+
+        let variables = variables.iter().map(String::as_str).collect::<Vec<&str>>();
+        solve_system(&ctx, &equations, &variables).map(|solution| solution.to_string())
+    } else {
+        solve_with(&ctx, &equations, &variables[0], options)
+            .map(|solutions| solutions.describe(&variables[0]))
+    };
+
+    match res {
+        Ok(res) => println!("{}", res),
+        Err(err) => {
+            println!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Differentiates `src`, an expression optionally followed by `by <variable>` (`x` by default)
+/// and `at <value>`. Prints the derivative, or its value at the given point.
+fn run_diff(src: &str) {
+    let ctx = Context::new();
+    let mut words = src.split_whitespace().collect::<Vec<&str>>();
+
+    let point = match words.iter().rposition(|word| *word == "at") {
+        Some(i) => {
+            let point = words.split_off(i)[1..].join(" ");
+
+            if point.is_empty() {
+                println!("expected a value after \"at\", {}", USAGE);
+                process::exit(1);
+            }
+
+            match evaluate(&point) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    let variable = match words.iter().rposition(|word| *word == "by") {
+        Some(i) => match words.split_off(i)[1..] {
+            [variable] => variable.to_owned(),
+            _ => {
+                println!("expected a single variable after \"by\", {}", USAGE);
+                process::exit(1);
+            }
+        },
+        None => "x".to_owned(),
+    };
+
+    let expr = words.join(" ");
+
+    if expr.is_empty() {
+        println!("expected an expression, {}", USAGE);
+        process::exit(1);
+    }
+
+    let res = differentiate(&ctx, &expr, &variable).and_then(|derivative| match point {
+        Some(value) => derivative.at(&ctx, value).map(|res| res.to_string()),
+        None => Ok(derivative.to_string()),
+    });
+
+    match res {
+        Ok(res) => println!("{}", res),
         Err(err) => {
             println!("{}", err);
-            std::process::exit(1);
+            process::exit(1);
         }
     }
 }
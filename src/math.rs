@@ -1,5 +1,8 @@
+use num_traits::ToPrimitive;
 use std::f64;
 
+use crate::exact;
+
 // Gamma function implementation taken from statrs (https://crates.io/crates/statrs), as it was
 // easyer than importing the whole crate just to use it
 
@@ -20,7 +23,18 @@ const GAMMA_DK: &[f64] = &[
     -2.719_949_084_886_077_2e-9,
 ];
 
-fn gamma(x: f64) -> f64 {
+/// The gamma function, which is undefined at its poles, the non-positive integers. It is rounded
+/// from the exact factorial at positive integers, and elsewhere accurate to about 1e-13 relative,
+/// the error growing with the magnitude of `x` from a few 1e-15 below 10.
+pub fn gamma(x: f64) -> f64 {
+    if x <= 0. && x == x.trunc() {
+        return f64::NAN;
+    }
+
+    if x == x.trunc() && x <= 172. {
+        return fact_int(x as u64 - 1);
+    }
+
     if x < 0.5 {
         let s = GAMMA_DK
             .iter()
@@ -40,39 +54,847 @@ fn gamma(x: f64) -> f64 {
             .skip(1)
             .fold(GAMMA_DK[0], |s, t| s + t.1 / (x + t.0 as f64 - 1.0));
 
-        s * TWO_SQRT_E_OVER_PI * ((x - 0.5 + GAMMA_R) / f64::consts::E).powf(x - 0.5)
+        let base = (x - 0.5 + GAMMA_R) / f64::consts::E;
+        let power = base.powf(x - 0.5);
+
+        if power.is_finite() {
+            s * TWO_SQRT_E_OVER_PI * power
+        } else {
+            // Near the overflow of the result, the power is split in two halves which only
+            // overflow along with it
+            let half = base.powf((x - 0.5) / 2.);
+            s * TWO_SQRT_E_OVER_PI * half * half
+        }
     }
 }
 
-fn fact_int(x: u64) -> f64 {
-    let mut count: f64 = 1.;
+const LN_PI: f64 = 1.144_729_885_849_400_2;
+const LN_2_SQRT_E_OVER_PI: f64 = 0.620_782_237_635_245_2;
 
-    if x == 0 || x == 1 {
-        return 1.;
-    } else if x > 170 {
+/// Natural logarithm of the absolute value of the gamma function, from the same approximation as
+/// `gamma`, which keeps it finite for large arguments.
+pub fn ln_gamma(x: f64) -> f64 {
+    if x <= 0. && x == x.trunc() {
         return f64::INFINITY;
     }
 
-    for i in 2..x + 1 {
-        count *= i as f64;
+    if x < 0.5 {
+        let s = GAMMA_DK
+            .iter()
+            .enumerate()
+            .skip(1)
+            .fold(GAMMA_DK[0], |s, t| s + t.1 / (t.0 as f64 - x));
+
+        LN_PI
+            - (f64::consts::PI * x).sin().abs().ln()
+            - s.ln()
+            - LN_2_SQRT_E_OVER_PI
+            - (0.5 - x) * ((0.5 - x + GAMMA_R) / f64::consts::E).ln()
+    } else {
+        let s = GAMMA_DK
+            .iter()
+            .enumerate()
+            .skip(1)
+            .fold(GAMMA_DK[0], |s, t| s + t.1 / (x + t.0 as f64 - 1.0));
+
+        s.ln() + LN_2_SQRT_E_OVER_PI + (x - 0.5) * ((x - 0.5 + GAMMA_R) / f64::consts::E).ln()
+    }
+}
+
+/// ln(√(2π))
+const LN_SQRT_2PI: f64 = 0.918_938_533_204_672_8;
+
+/// `x ln(y)`, taken as its limit 0 when `x` is 0.
+fn xlny(x: f64, y: f64) -> f64 {
+    if x == 0. {
+        0.
+    } else {
+        x * y.ln()
+    }
+}
+
+/// Error of Stirling's approximation of ln(n!), ln(n!) - (n + 1/2) ln(n) + n - ln(√(2π)), from
+/// its asymptotic series above 15, where the difference would cancel.
+pub(crate) fn stirling_error(n: f64) -> f64 {
+    if n <= 15. {
+        return ln_gamma(n + 1.) - (n + 0.5) * n.ln() + n - LN_SQRT_2PI;
+    }
+
+    let n2 = n * n;
+    (1. / 12. - (1. / 360. - (1. / 1260. - 1. / (1680. * n2)) / n2) / n2) / n
+}
+
+/// `x ln(x / m) + m - x`, from its series in `(x - m) / (x + m)` when `x` is close to `m`, where
+/// the terms would cancel.
+pub(crate) fn deviance(x: f64, m: f64) -> f64 {
+    if (x - m).abs() >= 0.1 * (x + m) {
+        return xlny(x, x / m) + m - x;
+    }
+
+    let v = (x - m) / (x + m);
+    let mut res = (x - m) * v;
+    let mut term = 2. * x * v;
+
+    for j in 1.. {
+        term *= v * v;
+        let next = res + term / (2 * j + 1) as f64;
+        if next == res {
+            break;
+        }
+        res = next;
+    }
+
+    res
+}
+
+/// `ln(1 + x) - x`, from the series of `ln(1 + x) = 2 atanh(x / (2 + x))` for small `x`, where
+/// the terms would cancel.
+fn ln_1p_mx(x: f64) -> f64 {
+    if x.abs() >= 0.2 {
+        return x.ln_1p() - x;
+    }
+
+    let v = x / (2. + x);
+    let mut res = -x * v;
+    let mut term = 2. * v;
+
+    for j in 1.. {
+        term *= v * v;
+        let next = res + term / (2 * j + 1) as f64;
+        if next == res {
+            break;
+        }
+        res = next;
+    }
+
+    res
+}
+
+/// Number of terms of the series, or of iterations of the continued fractions, after which the
+/// incomplete gamma and beta functions give up and return NaN.
+const MAX_ITERATIONS: usize = 1000;
+
+/// Values below this replace zeros in the modified Lentz's algorithm, which divides by them.
+const LENTZ_TINY: f64 = 1e-300;
+
+/// Parameters above which the incomplete gamma and beta functions switch to their uniform
+/// asymptotic expansions near the mean, where the series and continued fractions need a number of
+/// terms growing with the square root of the parameters.
+const UNIFORM_THRESHOLD: f64 = 100.;
+
+/// Bound on `|ζ|`, the transformed distance from the mean, below which the uniform expansions are
+/// used. The series and continued fractions converge within a few dozen terms beyond it.
+const UNIFORM_MAX_ZETA: f64 = 0.5;
+
+/// Bound on `(a + b) x` below which the incomplete beta function above its mean is taken from its
+/// series rather than from the continued fraction in `1 - x`.
+const BETA_SERIES_MAX: f64 = 500.;
+
+/// Number of Taylor coefficients in ζ kept for the terms of the uniform expansions.
+const UNIFORM_COEFFICIENTS: usize = 40;
+
+/// Number of terms `c_k(ζ) / n^k` of the uniform expansions.
+const UNIFORM_TERMS: usize = 8;
+
+/// Regularized lower incomplete gamma function P(a, x), the integral of `t^(a-1) e^-t` from 0 to
+/// `x` divided by Γ(a). Its relative error is a few times the rounding error of `x` amplified by
+/// the sensitivity of the result to it, `x^a e^-x / (Γ(a) P(a, x))`: about 1e-14 around the mean
+/// `x = a` whatever `a`, and up to `|x - a|` times 1e-16 in the lower tail.
+pub fn gamma_p(a: f64, x: f64) -> f64 {
+    if a.is_nan() || x.is_nan() || a <= 0. || x < 0. {
+        return f64::NAN;
+    } else if x == 0. {
+        return 0.;
+    } else if x == f64::INFINITY {
+        return 1.;
+    }
+
+    let zeta = gamma_zeta(a, x);
+    if a > UNIFORM_THRESHOLD && zeta.abs() < UNIFORM_MAX_ZETA {
+        0.5 * erfc(-zeta * (a / 2.).sqrt()) - gamma_uniform(a, zeta)
+    } else if x < a + 1. {
+        gamma_series(a, x)
+    } else {
+        1. - gamma_fraction(a, x)
+    }
+}
+
+/// Regularized upper incomplete gamma function Q(a, x) = 1 - P(a, x), computed directly so that
+/// it keeps its precision when small, which it does as `gamma_p` does in the lower tail.
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if a.is_nan() || x.is_nan() || a <= 0. || x < 0. {
+        return f64::NAN;
+    } else if x == 0. {
+        return 1.;
+    } else if x == f64::INFINITY {
+        return 0.;
+    }
+
+    let zeta = gamma_zeta(a, x);
+    if a > UNIFORM_THRESHOLD && zeta.abs() < UNIFORM_MAX_ZETA {
+        0.5 * erfc(zeta * (a / 2.).sqrt()) + gamma_uniform(a, zeta)
+    } else if x < a + 1. {
+        1. - gamma_series(a, x)
+    } else {
+        gamma_fraction(a, x)
+    }
+}
+
+/// x^a e^-x / Γ(a), the factor in front of both expansions of the incomplete gamma function. Above
+/// 15 it is written with the deviance of `x` from `a`, as its logarithm would cancel.
+fn gamma_prefactor(a: f64, x: f64) -> f64 {
+    if a <= 15. {
+        return (a * x.ln() - x - ln_gamma(a)).exp();
+    }
+
+    (-deviance(a, x) - stirling_error(a)).exp() * (a / (2. * f64::consts::PI)).sqrt()
+}
+
+/// P(a, x) from its power series, which converges quickly for `x < a + 1`.
+fn gamma_series(a: f64, x: f64) -> f64 {
+    let (mut term, mut sum, mut n) = (1. / a, 1. / a, a);
+
+    for _ in 0..MAX_ITERATIONS {
+        n += 1.;
+        term *= x / n;
+        sum += term;
+
+        if term.abs() < sum.abs() * f64::EPSILON {
+            return sum * gamma_prefactor(a, x);
+        }
+    }
+
+    f64::NAN
+}
+
+/// Q(a, x) from its continued fraction, evaluated with the modified Lentz's algorithm, which
+/// converges quickly for `x >= a + 1`.
+fn gamma_fraction(a: f64, x: f64) -> f64 {
+    let mut b = x + 1. - a;
+    let mut c = 1. / LENTZ_TINY;
+    let mut d = 1. / b;
+    let mut h = d;
+
+    for i in 1..MAX_ITERATIONS {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.;
+        d = an * d + b;
+        if d.abs() < LENTZ_TINY {
+            d = LENTZ_TINY;
+        }
+        c = b + an / c;
+        if c.abs() < LENTZ_TINY {
+            c = LENTZ_TINY;
+        }
+        d = 1. / d;
+
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.).abs() < f64::EPSILON {
+            return h * gamma_prefactor(a, x);
+        }
+    }
+
+    f64::NAN
+}
+
+/// ζ of the uniform expansion of the incomplete gamma function, with the sign of `x - a` and
+/// `a ζ² / 2` the deviance of `x` from `a`.
+fn gamma_zeta(a: f64, x: f64) -> f64 {
+    (2. * deviance(a, x) / a).sqrt().copysign(x - a)
+}
+
+/// Q(a, x) - erfc(ζ √(a / 2)) / 2 from Temme's uniform asymptotic expansion.
+fn gamma_uniform(a: f64, zeta: f64) -> f64 {
+    let sum = uniform_sum(zeta, a, |j| {
+        let sign = if j % 2 == 1 { 1. } else { -1. };
+        sign / j as f64
+    });
+
+    (-stirling_error(a) - a * zeta * zeta / 2.).exp() / (2. * f64::consts::PI * a).sqrt() * sum
+}
+
+/// Sum of the terms `c_k(ζ) / n^k` of the uniform asymptotic expansions of the incomplete gamma
+/// and beta functions, whose variable ω, the relative distance from the mean, is transformed into
+/// ζ by `ζ² = ω² - 2 Σ psi(j) ω^j` over `j >= 3`. The terms follow from the Taylor series of
+/// `ζ / ω`, which is found by Lagrange inversion: `c_0 = (ζ / ω - 1) / ζ`, and each `c_k` is the
+/// derivative of `c_(k-1)` less its value at 0, over ζ.
+fn uniform_sum(zeta: f64, n: f64, psi: impl Fn(usize) -> f64) -> f64 {
+    const N: usize = UNIFORM_COEFFICIENTS;
+    let product = |a: &[f64], b: &[f64]| -> Vec<f64> {
+        (0..N)
+            .map(|k| (0..=k).map(|j| a[j] * b[k - j]).sum())
+            .collect()
+    };
+    let reciprocal = |a: &[f64]| {
+        let mut res = vec![1.; N];
+        for k in 1..N {
+            res[k] = -(1..=k).map(|j| a[j] * res[k - j]).sum::<f64>();
+        }
+        res
+    };
+
+    // ζ / ω = √(1 - 2 Σ psi(j + 2) ω^j)
+    let mut root = vec![1.; N];
+    for k in 1..N {
+        let square = (1..k).map(|j| root[j] * root[k - j]).sum::<f64>();
+        root[k] = (-2. * psi(k + 2) - square) / 2.;
+    }
+
+    // ω / ζ, whose coefficient of ζ^(k-1) is that of ω^(k-1) in (ω / ζ)^k, over k
+    let inverse = reciprocal(&root);
+    let mut power = vec![0.; N];
+    power[0] = 1.;
+    let mut ratio = vec![0.; N];
+    for k in 1..=N {
+        power = product(&power, &inverse);
+        ratio[k - 1] = power[k - 1] / k as f64;
+    }
+
+    let mut coefficients = reciprocal(&ratio).split_off(1);
+    let (mut sum, mut weight) = (0., 1.);
+    for _ in 0..UNIFORM_TERMS {
+        sum += weight * coefficients.iter().rev().fold(0., |s, c| s * zeta + c);
+        weight /= n;
+        coefficients = (2..coefficients.len())
+            .map(|i| i as f64 * coefficients[i])
+            .collect();
+    }
+
+    sum
+}
+
+/// Error function, 2/√π times the integral of `e^(-t^2)` from 0 to `x`, as P(1/2, x²).
+pub fn erf(x: f64) -> f64 {
+    let p = gamma_p(0.5, x * x);
+    if x < 0. {
+        -p
+    } else {
+        p
+    }
+}
+
+/// Complementary error function, 2/√π times the integral of `e^(-t^2)` from `x` to infinity,
+/// as Q(1/2, x²).
+pub fn erfc(x: f64) -> f64 {
+    if x < 0. {
+        1. + gamma_p(0.5, x * x)
+    } else {
+        gamma_q(0.5, x * x)
+    }
+}
+
+/// Logarithm of the beta function B(a, b) = Γ(a) Γ(b) / Γ(a + b), for positive arguments. When
+/// the larger one is above 15, the difference of the logarithms of its gamma functions, which
+/// would cancel, is written with Stirling's series.
+pub fn ln_beta(a: f64, b: f64) -> f64 {
+    let (a, b) = if a <= b { (a, b) } else { (b, a) };
+    if b <= 15. {
+        return ln_gamma(a) + ln_gamma(b) - ln_gamma(a + b);
+    }
+
+    ln_gamma(a) + stirling_error(b) - stirling_error(a + b) - a * (a + b).ln() - b * ln_1p_mx(a / b)
+        + 0.5 * (a / b).ln_1p()
+}
+
+/// The beta function B(a, b) = Γ(a) Γ(b) / Γ(a + b), from its logarithm for positive arguments so
+/// that it doesn't overflow.
+pub fn beta(a: f64, b: f64) -> f64 {
+    if a > 0. && b > 0. {
+        ln_beta(a, b).exp()
+    } else {
+        gamma(a) * gamma(b) / gamma(a + b)
+    }
+}
+
+/// Regularized incomplete beta function I_x(a, b), the integral of `t^(a-1) (1-t)^(b-1)` from 0
+/// to `x` divided by B(a, b). It is accurate to about 1e-13, and like `gamma_p` limited by the
+/// rounding of `x` when `a` and `b` are large, as the result then changes a lot with it.
+pub fn beta_reg(a: f64, b: f64, x: f64) -> f64 {
+    if a.is_nan() || b.is_nan() || a <= 0. || b <= 0. || !(0. ..=1.).contains(&x) {
+        return f64::NAN;
+    } else if x == 0. || x == 1. {
+        return x;
+    }
+
+    if a.min(b) > UNIFORM_THRESHOLD {
+        if let Some(res) = beta_uniform(a, b, x) {
+            return res;
+        }
+    }
+
+    // The continued fraction converges quickly below the mean, and symmetry covers the rest
+    let prefactor = beta_prefactor(a, b, x);
+    if x < (a + 1.) / (a + b + 2.) {
+        prefactor * beta_fraction(a, b, x) / a
+    } else if x < 0.5 && (a + b) * x < BETA_SERIES_MAX {
+        // Close to 0, 1 - x would round off the digits of x that the other fraction depends on
+        prefactor * beta_series(a, b, x) / a
+    } else {
+        1. - prefactor * beta_fraction(b, a, 1. - x) / b
+    }
+}
+
+/// x^a (1-x)^b / B(a, b), the factor in front of the continued fraction of the incomplete beta
+/// function. Above 15 it is written with Stirling's series and the distance of `x` from the
+/// mean, as its logarithm would cancel.
+fn beta_prefactor(a: f64, b: f64, x: f64) -> f64 {
+    if a.max(b) <= 15. {
+        return (a * x.ln() + b * (-x).ln_1p() - ln_beta(a, b)).exp();
+    }
+
+    let r = a + b;
+    let exponent =
+        stirling_error(r) - stirling_error(a) - stirling_error(b) - beta_exponent(a, b, x);
+
+    exponent.exp() * (a / r * b / (2. * f64::consts::PI)).sqrt()
+}
+
+/// `-a ln(x / p) - b ln((1 - x) / q)` with p = a / (a + b) and q = b / (a + b), the deviance of
+/// `x` from the mean. It is written in terms of `(a + b) x - a`, which is taken as `x b - (1 - x)
+/// a` so that it doesn't round `(a + b) x` when it is much smaller than `a + b`.
+fn beta_exponent(a: f64, b: f64, x: f64) -> f64 {
+    let distance = x * b - (1. - x) * a;
+    -a * ln_1p_mx(distance / a) - b * ln_1p_mx(-distance / b)
+}
+
+/// Continued fraction of the incomplete beta function, evaluated with the modified Lentz's
+/// algorithm.
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let step = |d: f64, c: f64, an: f64| {
+        let d = an * d + 1.;
+        let c = 1. + an / c;
+        let d = if d.abs() < LENTZ_TINY { LENTZ_TINY } else { d };
+        let c = if c.abs() < LENTZ_TINY { LENTZ_TINY } else { c };
+        (1. / d, c)
+    };
+
+    let mut c = 1.;
+    let mut d = 1. - (a + b) * x / (a + 1.);
+    if d.abs() < LENTZ_TINY {
+        d = LENTZ_TINY;
+    }
+    d = 1. / d;
+    let mut h = d;
+
+    for m in 1..MAX_ITERATIONS {
+        let m = m as f64;
+
+        // Even step
+        let an = m * (b - m) * x / ((a + 2. * m - 1.) * (a + 2. * m));
+        (d, c) = step(d, c, an);
+        h *= d * c;
+
+        // Odd step
+        let an = -(a + m) * (a + b + m) * x / ((a + 2. * m) * (a + 2. * m + 1.));
+        (d, c) = step(d, c, an);
+        let delta = d * c;
+        h *= delta;
+
+        if (delta - 1.).abs() < f64::EPSILON {
+            return h;
+        }
     }
 
-    count
+    f64::NAN
+}
+
+/// The hypergeometric series of the incomplete beta function, `Σ Π (a + b + i) x / (a + 1 + i)`,
+/// whose terms are all positive and which needs about `(a + b) x` of them.
+fn beta_series(a: f64, b: f64, x: f64) -> f64 {
+    let (mut term, mut sum) = (1., 1.);
+
+    for i in 0..MAX_ITERATIONS {
+        let i = i as f64;
+        term *= (a + b + i) * x / (a + 1. + i);
+        sum += term;
+
+        if term < sum * f64::EPSILON {
+            return sum;
+        }
+    }
+
+    f64::NAN
+}
+
+/// I_x(a, b) from Temme's uniform asymptotic expansion, if `x` is close enough to the mean. It is
+/// written for `a <= b`, and I_x(a, b) = 1 - I_(1-x)(b, a) covers the other case.
+fn beta_uniform(a: f64, b: f64, x: f64) -> Option<f64> {
+    let (a, b, x, sign) = if a <= b {
+        (a, b, x, 1.)
+    } else {
+        (b, a, 1. - x, -1.)
+    };
+
+    // With p = a / r and q = b / r, the exponent r η² / 2 is the deviance from the mean, and ζ
+    // rescales η by √(q / p) so that the expansion is in powers of 1 / n with n = a / q
+    let r = a + b;
+    let q = b / r;
+    let n = a / q;
+    let exponent = beta_exponent(a, b, x);
+    let zeta = (2. * exponent / n).sqrt().copysign(x * b - (1. - x) * a);
+    if zeta.abs() >= UNIFORM_MAX_ZETA {
+        return None;
+    }
+
+    let sum = uniform_sum(zeta, n, |j| {
+        let sign = if j % 2 == 1 { 1. } else { -1. };
+        q * (sign - (a / b).powi(j as i32 - 1)) / j as f64
+    });
+    let rest = (stirling_error(r) - stirling_error(a) - stirling_error(b) - exponent).exp()
+        / (2. * f64::consts::PI * n).sqrt()
+        * sum;
+
+    Some(0.5 * erfc(-sign * zeta * (n / 2.).sqrt()) - sign * rest)
+}
+
+/// `x!` rounded once from its exact value.
+fn fact_int(x: u64) -> f64 {
+    if x > 170 {
+        return f64::INFINITY;
+    }
+
+    exact::factorial(x).to_f64().unwrap()
 }
 
 pub fn fact(x: f64) -> f64 {
     if x < 0. {
         f64::NAN
-    } else if (x - x.trunc()).abs() < std::f64::EPSILON {
+    } else if (x - x.trunc()).abs() < f64::EPSILON {
         fact_int(x.round() as u64)
     } else {
         gamma(x + 1.)
     }
 }
 
+/// Bernoulli numbers B2, B4, ..., B20, used by the asymptotic expansion of the polygamma function.
+const BERNOULLI: [f64; 10] = [
+    1. / 6.,
+    -1. / 30.,
+    1. / 42.,
+    -1. / 30.,
+    5. / 66.,
+    -691. / 2730.,
+    7. / 6.,
+    -3617. / 510.,
+    43867. / 798.,
+    -174611. / 330.,
+];
+
+/// Smallest argument the asymptotic expansion of the polygamma function is used at, on top of
+/// the order. Smaller ones are shifted up with the recurrence relation.
+const ASYMPTOTIC_THRESHOLD: f64 = 20.;
+
+/// Largest order of the polygamma function, past which `n!` overflows.
+pub const MAX_POLYGAMMA_ORDER: u32 = 170;
+
+/// The `n`-th derivative of the digamma function, the logarithmic derivative of the gamma
+/// function, which is the derivative of the factorial `x!` divided by `(x - 1)!`. The order `n`
+/// is at most [`MAX_POLYGAMMA_ORDER`].
+pub fn polygamma(n: u32, x: f64) -> f64 {
+    if x.is_nan() || (x <= 0. && x == x.trunc()) {
+        return f64::NAN;
+    } else if x == f64::INFINITY {
+        return if n == 0 { x } else { 0. };
+    }
+
+    // Reflection formula, so that large negative arguments don't take forever to shift
+    if n == 0 && x < 0. {
+        return polygamma(0, 1. - x) - f64::consts::PI / (f64::consts::PI * x).tan();
+    }
+
+    // Shifting x by m = ⌈-x⌉ into (0, 1) adds the same terms as shifting 1 - x - m to 1 - x, so
+    // ψ⁽ⁿ⁾(x) = ψ⁽ⁿ⁾(x + m) + (-1)ⁿ (ψ⁽ⁿ⁾(1 - x) - ψ⁽ⁿ⁾(1 - x - m)), all at positive arguments
+    if x < 0. {
+        let fraction = x + (-x).ceil();
+        let difference = polygamma(n, 1. - x) - polygamma(n, 1. - fraction);
+        let sign = if n.is_multiple_of(2) { 1. } else { -1. };
+        return polygamma(n, fraction) + sign * difference;
+    }
+
+    let sign = if n.is_multiple_of(2) { -1. } else { 1. };
+    let factorial = fact_int(n as u64);
+    let (mut x, mut res) = (x, 0.);
+
+    // ψ⁽ⁿ⁾(x) = ψ⁽ⁿ⁾(x + 1) - (-1)ⁿ n! / xⁿ⁺¹
+    while x < ASYMPTOTIC_THRESHOLD + n as f64 {
+        res += sign * factorial / x.powi(n as i32 + 1);
+        x += 1.;
+    }
+
+    if n == 0 {
+        let series = BERNOULLI
+            .iter()
+            .enumerate()
+            .map(|(k, b)| b / ((2 * k + 2) as f64 * x.powi(2 * k as i32 + 2)))
+            .sum::<f64>();
+
+        return res + x.ln() - 0.5 / x - series;
+    }
+
+    // Each term is B₂ₖ (2k + n - 1)! / ((2k)! x²ᵏ⁺ⁿ), whose factors are taken over x one by one
+    // so that neither the factorials nor the powers overflow
+    let series = BERNOULLI
+        .iter()
+        .enumerate()
+        .map(|(k, b)| {
+            let k = 2 * k as u32 + 2;
+            let ratio = (k + 1..k + n).fold(1., |product, j| product * j as f64 / x);
+            b * ratio / x.powi(k as i32 + 1)
+        })
+        .sum::<f64>();
+
+    // (n - 1)! / xⁿ + n! / (2 xⁿ⁺¹)
+    let power = (1..n).fold(1. / x, |product, j| product * j as f64 / x);
+    let leading = power * (1. + n as f64 / (2. * x));
+
+    res + sign * (leading + series)
+}
+
+/// Number of terms of Borwein's series for the zeta function, whose error is about 5.8^-n.
+const ZETA_TERMS: usize = 30;
+
+/// The Riemann zeta function, the sum of `1/k^s` for `k` from 1 when `s > 1`. It is computed from
+/// the alternating series of the Dirichlet eta function, accelerated with Borwein's algorithm,
+/// for non-negative arguments, and from the reflection formula for negative ones. It is accurate
+/// to about 1e-15, and loses precision close to its pole at 1.
+pub fn zeta(s: f64) -> f64 {
+    if s.is_nan() || s == 1. {
+        return f64::NAN;
+    } else if s == f64::INFINITY {
+        return 1.;
+    } else if s < 0. && s % 2. == 0. {
+        // Trivial zeros
+        return 0.;
+    } else if s < 0. {
+        let reflected = 2f64.powf(s)
+            * f64::consts::PI.powf(s - 1.)
+            * (f64::consts::PI * s / 2.).sin()
+            * gamma(1. - s);
+
+        return reflected * zeta(1. - s);
+    }
+
+    // d_k = n Σ (n + i - 1)! 4^i / ((n - i)! (2i)!) for i from 0 to k
+    let n = ZETA_TERMS as f64;
+    let mut d = Vec::with_capacity(ZETA_TERMS + 1);
+    let (mut term, mut sum) = (1., 1.);
+    d.push(sum);
+
+    for i in 1..=ZETA_TERMS {
+        let i = i as f64;
+        term *= 4. * (n + i - 1.) * (n - i + 1.) / ((2. * i - 1.) * (2. * i));
+        sum += term;
+        d.push(sum);
+    }
+
+    let eta = (0..ZETA_TERMS)
+        .map(|k| {
+            let sign = if k % 2 == 0 { 1. } else { -1. };
+            sign * (d[ZETA_TERMS] - d[k]) / ((k + 1) as f64).powf(s)
+        })
+        .sum::<f64>()
+        / d[ZETA_TERMS];
+
+    // ζ(s) = η(s) / (1 - 2^(1-s))
+    eta / -((1. - s) * f64::consts::LN_2).exp_m1()
+}
+
+/// Number of Halley iterations after which the Lambert W function stops converging further.
+const LAMBERT_W_ITERATIONS: usize = 50;
+
+/// The Lambert W function, the inverse of `w e^w`, on its principal branch `k = 0` where
+/// `W(x) >= -1`, or its lower branch `k = -1` where `W(x) <= -1`, defined from `-1/e` to 0. It is
+/// found with Halley's method to about 1e-15.
+pub fn lambert_w(x: f64, k: i32) -> f64 {
+    let branch_point = -(-1f64).exp();
+
+    if x.is_nan() || x < branch_point || (k == -1 && x > 0.) {
+        return f64::NAN;
+    } else if x == branch_point {
+        return -1.;
+    } else if x == 0. {
+        return if k == 0 { 0. } else { f64::NEG_INFINITY };
+    } else if x == f64::INFINITY {
+        return x;
+    }
+
+    // Series around the branch point, or the asymptotic expansion away from it
+    let p = (2. * (f64::consts::E * x + 1.)).max(0.).sqrt();
+    let mut w = match k {
+        0 if x < 1. => -1. + p - p * p / 3. + 11. / 72. * p * p * p,
+        0 => x.ln() - x.ln().ln().max(0.),
+        _ if x < -0.25 => -1. - p - p * p / 3. - 11. / 72. * p * p * p,
+        _ => (-x).ln() - (-(-x).ln()).ln(),
+    };
+
+    for _ in 0..LAMBERT_W_ITERATIONS {
+        let exp = w.exp();
+        let f = w * exp - x;
+        let step = f / (exp * (w + 1.) - (w + 2.) * f / (2. * w + 2.));
+
+        if !step.is_finite() {
+            break;
+        }
+
+        w -= step;
+        if step.abs() <= 4. * f64::EPSILON * w.abs() {
+            break;
+        }
+    }
+
+    w
+}
+
+/// Highest order, in absolute value, of the Bessel functions.
+pub const MAX_BESSEL_ORDER: u32 = 1000;
+
+/// Smallest argument the asymptotic expansion of the Bessel functions is used at, as long as it
+/// is at least half the square of the order.
+const BESSEL_ASYMPTOTIC_THRESHOLD: f64 = 25.;
+
+/// Bessel functions of the first and second kind of integer order `n`, J_n(x) and Y_n(x), for
+/// `x > 0`. Large arguments use their asymptotic expansion, and others Miller's backward
+/// recurrence for J, normalized by J_0 + 2 J_2 + 2 J_4 + ... = 1, from which Neumann's series
+/// give Y_0 and Y_1, and then Y_n by forward recurrence. They are accurate to about 1e-14, in
+/// absolute terms near their zeros.
+fn bessel(n: u32, x: f64) -> (f64, f64) {
+    let nf = n as f64;
+
+    if x >= BESSEL_ASYMPTOTIC_THRESHOLD.max(nf * nf / 2.) {
+        return bessel_asymptotic(nf, x);
+    }
+
+    let start = 2 * ((n.max(x as u32) as f64 + 20. + (40. * nf.max(x)).sqrt()) as usize / 2);
+    let mut j = vec![0.; start + 2];
+    j[start] = 1e-30;
+
+    for k in (1..=start).rev() {
+        j[k - 1] = 2. * k as f64 / x * j[k] - j[k + 1];
+
+        // Rescales the values computed so far before they overflow
+        if j[k - 1].abs() > 1e250 {
+            for value in &mut j[k - 1..] {
+                *value *= 1e-250;
+            }
+        }
+    }
+
+    let norm = j[0] + 2. * j.iter().step_by(2).skip(1).sum::<f64>();
+    for value in &mut j {
+        *value /= norm;
+    }
+
+    // (π/2) Y_0 = (ln(x/2) + γ) J_0 - 2 Σ (-1)^k J_2k / k
+    // (π/2) Y_1 = -J_0 / x + (ln(x/2) + γ) J_1 + Σ (-1)^k (J_2k-1 - J_2k+1) / k
+    let log = (x / 2.).ln() + EULER_GAMMA;
+    let (mut sum0, mut sum1) = (0., 0.);
+
+    for k in 1..start / 2 {
+        let sign = if k % 2 == 0 { 1. } else { -1. };
+        sum0 += sign * j[2 * k] / k as f64;
+        sum1 += sign * (j[2 * k - 1] - j[2 * k + 1]) / k as f64;
+    }
+
+    let mut y = (
+        2. / f64::consts::PI * (log * j[0] - 2. * sum0),
+        2. / f64::consts::PI * (-j[0] / x + log * j[1] + sum1),
+    );
+
+    // Y_k+1 = 2k/x Y_k - Y_k-1
+    for k in 1..n {
+        y = (y.1, 2. * k as f64 / x * y.1 - y.0);
+    }
+
+    (j[n as usize], if n == 0 { y.0 } else { y.1 })
+}
+
+/// Hankel's asymptotic expansion of J_n(x) and Y_n(x), summed until its terms stop decreasing.
+fn bessel_asymptotic(n: f64, x: f64) -> (f64, f64) {
+    let mu = 4. * n * n;
+    let (mut p, mut q) = (1., 0.);
+    let mut term = 1.;
+
+    for k in 1..MAX_ITERATIONS {
+        let next = term * (mu - (2. * k as f64 - 1.).powi(2)) / (k as f64 * 8. * x);
+        if next.abs() >= term.abs() || next == 0. {
+            break;
+        }
+        term = next;
+
+        // Terms alternate between Q and P, with signs + + - - + + ...
+        let sign = if (k / 2) % 2 == 0 { 1. } else { -1. };
+        if k % 2 == 1 {
+            q += sign * term;
+        } else {
+            p += sign * term;
+        }
+
+        if term.abs() < f64::EPSILON {
+            break;
+        }
+    }
+
+    let chi = x - (n / 2. + 0.25) * f64::consts::PI;
+    let scale = (2. / (f64::consts::PI * x)).sqrt();
+
+    (
+        scale * (p * chi.cos() - q * chi.sin()),
+        scale * (p * chi.sin() + q * chi.cos()),
+    )
+}
+
+/// Euler-Mascheroni constant γ.
+const EULER_GAMMA: f64 = 0.577_215_664_901_532_9;
+
+/// Bessel function of the first kind J_n(x), of integer order `n` at most [`MAX_BESSEL_ORDER`].
+pub fn bessel_j(n: i32, x: f64) -> f64 {
+    // J_-n = (-1)^n J_n, and J_n(-x) = (-1)^n J_n(x)
+    let sign = if n % 2 != 0 && (n < 0) != (x < 0.) {
+        -1.
+    } else {
+        1.
+    };
+
+    if x.is_nan() {
+        f64::NAN
+    } else if x == 0. {
+        if n == 0 {
+            1.
+        } else {
+            0.
+        }
+    } else if x.is_infinite() {
+        0.
+    } else {
+        sign * bessel(n.unsigned_abs(), x.abs()).0
+    }
+}
+
+/// Bessel function of the second kind Y_n(x), of integer order `n` at most [`MAX_BESSEL_ORDER`],
+/// which is only real for `x >= 0`.
+pub fn bessel_y(n: i32, x: f64) -> f64 {
+    // Y_-n = (-1)^n Y_n
+    let sign = if n % 2 != 0 && n < 0 { -1. } else { 1. };
+
+    if x.is_nan() || x < 0. {
+        f64::NAN
+    } else if x == 0. {
+        f64::NEG_INFINITY * sign
+    } else if x.is_infinite() {
+        0.
+    } else {
+        sign * bessel(n.unsigned_abs(), x).1
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::fact;
+    use super::{
+        bessel_j, bessel_y, beta, beta_reg, erf, erfc, fact, gamma, gamma_p, gamma_q, lambert_w,
+        ln_gamma, polygamma, zeta,
+    };
+    use std::f64;
 
     #[test]
     fn fact_negative() {
@@ -100,4 +922,158 @@ mod tests {
         assert!((fact(2.5) - 3.3233509704478403).abs() < f64::EPSILON);
         assert!((fact(3.5) - 11.631728396567521).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn polygamma_values() {
+        let cases = [
+            (0, 1., -0.5772156649015329),
+            (0, 0.5, -1.9635100260214235),
+            (0, 10., 2.251752589066721),
+            (0, -0.5, 0.03648997397857652),
+            (0, 100.5, 4.605174352581845),
+            (1, 1., 1.6449340668482264),
+            (1, 2.5, 0.49035775610023485),
+            (1, -1.5, 9.379246644989124),
+            (2, 3., -0.15411380631918858),
+            (3, 0.25, 1538.7821440091884),
+            (4, -7.6, -2037.399918010941),
+            (1, -1e12 - 0.5, 9.86960440108836),
+            (170, 1., -7.257415615307999e306),
+            (170, 300., -4.335360007808032e-117),
+        ];
+
+        for (n, x, expected) in cases {
+            let res = polygamma(n, x);
+            assert!(
+                (res - expected).abs() <= 1e-14 * expected.abs().max(1.),
+                "polygamma({}, {}) = {}, expected {}",
+                n,
+                x,
+                res,
+                expected
+            );
+        }
+
+        assert!(polygamma(0, 0.).is_nan());
+        assert!(polygamma(1, -2.).is_nan());
+        assert_eq!(polygamma(170, 0.5), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn gamma_values() {
+        assert_eq!(gamma(5.), 24.);
+        assert_eq!(gamma(100.), 9.332621544394415e155);
+        assert_eq!(gamma(170.), 4.269068009004705e304);
+        assert_eq!(gamma(171.), 7.257415615307999e306);
+        assert_eq!(gamma(172.), f64::INFINITY);
+        assert_close!(gamma(171.5), 9.4833675668248e307, 1e-13);
+        assert_close!(gamma(20.7), 9.852430240890154e17, 1e-13);
+        assert_close!(gamma(0.7), 1.2980553326475577, 1e-15);
+        assert_close!(gamma(-2.5), -0.9453087204829419, 1e-15);
+        assert!(gamma(-3.).is_nan());
+    }
+
+    #[test]
+    fn ln_gamma_values() {
+        assert_close!(ln_gamma(0.5), 0.5723649429247001, 1e-14);
+        assert_close!(ln_gamma(10.), 12.801827480081469, 1e-14);
+        assert_close!(ln_gamma(1000.), 5905.220423209181, 1e-14);
+        assert_close!(ln_gamma(-2.5), -0.05624371649767405, 1e-13);
+    }
+
+    #[test]
+    fn incomplete_gamma_values() {
+        assert_close!(gamma_p(2.5, 1.5), 0.3000141641213725, 1e-14);
+        assert_close!(gamma_p(100., 90.), 0.15822098918643017, 1e-12);
+        assert_close!(gamma_q(0.5, 20.), 2.539628589470865e-10, 1e-13);
+        assert_close!(gamma_p(1., 2.) + gamma_q(1., 2.), 1., 1e-15);
+        assert!(gamma_p(-1., 2.).is_nan());
+        assert_close!(erfc(0.5), 0.4795001221869535, 1e-14);
+        assert_close!(erfc(-1.), 1.842700792949715, 1e-14);
+        assert_close!(erfc(5.), 1.537459794428035e-12, 1e-13);
+
+        // Uniform expansion, where the series would need thousands of terms
+        assert_close!(gamma_p(1e6, 1e6), 0.5001329807608726, 1e-14);
+        assert_close!(gamma_q(1e6 + 1., 1e6), 0.5002659614862837, 1e-14);
+        assert_close!(gamma_p(1e300, 1e300), 0.5, 1e-15);
+        assert_close!(gamma_p(1e5, 1e5), 0.5004205221103651, 1e-14);
+        assert_close!(gamma_p(1e7, 1e7), 0.5000420522087237, 1e-14);
+        assert_close!(gamma_p(1e4, 7000.), 9.711672437705851e-249, 1e-12);
+    }
+
+    #[test]
+    fn incomplete_beta_values() {
+        assert_close!(beta_reg(2., 3., 0.4), 0.5248, 1e-14);
+        assert_close!(beta_reg(0.5, 0.5, 0.9), 0.7951672353008666, 1e-14);
+        assert_close!(beta_reg(50., 60., 0.5), 0.8309072939016694, 1e-12);
+        assert_eq!(beta_reg(2., 3., 1.), 1.);
+        assert!(beta_reg(2., 3., 1.5).is_nan());
+
+        assert_close!(beta_reg(1e6, 1e6, 0.4995), 0.07864957758090163, 1e-12);
+        assert_close!(beta_reg(1e6, 1e6, 0.5004), 0.8710504906836167, 1e-12);
+        assert_close!(beta_reg(300., 1e7, 3.1e-5), 0.7226674269144304, 1e-12);
+        assert_close!(beta_reg(5e299, 5e299, 0.5), 0.5, 1e-15);
+        assert_close!(beta_reg(0.5, 5e5, 2e-6), 0.8427008967267665, 1e-13);
+    }
+
+    #[test]
+    fn special_function_values() {
+        assert_close!(gamma(5.5), 52.34277778455352, 1e-14);
+        assert_close!(gamma(-1.5), 2.363271801207355, 1e-14);
+        assert!(gamma(-2.).is_nan());
+        assert_eq!(ln_gamma(0.), f64::INFINITY);
+        assert_close!(beta(2.5, 3.5), 0.03681553890925539, 1e-14);
+        assert_close!(beta(-0.5, 2.), -4., 1e-14);
+        assert_close!(erf(0.5), 0.5204998778130465, 1e-15);
+        assert_close!(erf(-0.5), -0.5204998778130465, 1e-15);
+        assert_eq!(erf(0.), 0.);
+    }
+
+    #[test]
+    fn zeta_values() {
+        assert_close!(zeta(2.), f64::consts::PI.powi(2) / 6., 1e-15);
+        assert_close!(zeta(3.), 1.2020569031595942, 1e-15);
+        assert_close!(zeta(0.5), -1.4603545088095868, 1e-15);
+        assert_close!(zeta(1.001), 1000.5772884760116, 1e-14);
+        assert_close!(zeta(-3.), 1. / 120., 1e-14);
+        assert_close!(zeta(-7.5), 0.00326903957260022, 1e-13);
+        assert_eq!(zeta(0.), -0.5);
+        assert_eq!(zeta(-4.), 0.);
+        assert!(zeta(1.).is_nan());
+    }
+
+    #[test]
+    fn lambert_w_values() {
+        assert_close!(lambert_w(1., 0), 0.5671432904097838, 1e-15);
+        assert_close!(lambert_w(1e10, 0), 20.028685413304952, 1e-15);
+        assert_close!(lambert_w(-0.2, 0), -0.25917110181907377, 1e-15);
+        assert_close!(lambert_w(-0.2, -1), -2.5426413577735265, 1e-15);
+        assert_close!(lambert_w(-0.3678, -1), -1.0209272394094255, 1e-14);
+        assert_close!(lambert_w(-1e-300, -1), -697.3227762954601, 1e-15);
+        assert_eq!(lambert_w(-(-1f64).exp(), -1), -1.);
+        assert_eq!(lambert_w(0., -1), f64::NEG_INFINITY);
+        assert!(lambert_w(-0.5, 0).is_nan());
+        assert!(lambert_w(1., -1).is_nan());
+    }
+
+    #[test]
+    fn bessel_values() {
+        assert_close!(bessel_j(0, 1.), 0.7651976865579666, 1e-15);
+        assert_close!(bessel_j(5, 3.), 0.043028434877047584, 1e-15);
+        assert_close!(bessel_j(-3, 2.), -0.12894324947440205, 1e-15);
+        assert_close!(bessel_j(3, -2.), -0.12894324947440205, 1e-15);
+        assert_close!(bessel_j(50, 10.), 1.7845136078715953e-30, 1e-14);
+        assert_close!(bessel_j(3, 30.), 0.129211228759725, 1e-14);
+        assert_close!(bessel_j(1000, 1500.), 0.022929733509152398, 1e-14);
+        assert_eq!(bessel_j(0, 0.), 1.);
+        assert_eq!(bessel_j(2, 0.), 0.);
+
+        assert_close!(bessel_y(0, 1.), 0.08825696421567696, 1e-15);
+        assert_close!(bessel_y(5, 3.), -1.9059459538286737, 1e-15);
+        assert_close!(bessel_y(10, 0.1), -1.1831335132045191e18, 1e-14);
+        assert_close!(bessel_y(20, 24.), 0.14420188606021456, 1e-14);
+        assert_close!(bessel_y(7, 25.), 0.16252257251113247, 1e-14);
+        assert_eq!(bessel_y(1, 0.), f64::NEG_INFINITY);
+        assert!(bessel_y(1, -1.).is_nan());
+    }
 }
@@ -0,0 +1,652 @@
+use std::fmt;
+
+use crate::backend::Backend;
+use crate::complex::ComplexValue;
+use crate::eval::{self, CONSTANTS};
+use crate::functions::{Arity, FunctionRegistry};
+use crate::math;
+use crate::parser::INTEGER_DIVISION;
+use crate::polynomial;
+
+/// Functions on vectors and matrices, on top of the built-in ones, which apply element-wise.
+const MATRIX_FUNCTIONS: [(&str, usize); 8] = [
+    ("det", 1),
+    ("inv", 1),
+    ("transpose", 1),
+    ("dot", 2),
+    ("cross", 2),
+    ("norm", 1),
+    ("rank", 1),
+    ("eig", 1),
+];
+
+/// Result of evaluating an expression in matrix mode. Vectors have no orientation, they act as
+/// columns on the right of a matrix product and as rows on its left.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatrixValue {
+    Scalar(f64),
+    Vector(Vec<f64>),
+    /// Rows of a matrix, which all have the same length.
+    Matrix(Vec<Vec<f64>>),
+}
+
+impl MatrixValue {
+    /// Describes the shape of the value in error messages, such as `a 2x3 matrix`.
+    pub fn shape(&self) -> String {
+        match self {
+            MatrixValue::Scalar(_) => "a number".to_owned(),
+            MatrixValue::Vector(v) => format!("a vector of length {}", v.len()),
+            MatrixValue::Matrix(m) => format!("a {}x{} matrix", m.len(), m[0].len()),
+        }
+    }
+
+    /// Applies `f` to every element.
+    fn map(self, mut f: impl FnMut(f64) -> f64) -> MatrixValue {
+        match self {
+            MatrixValue::Scalar(x) => MatrixValue::Scalar(f(x)),
+            MatrixValue::Vector(v) => MatrixValue::Vector(v.into_iter().map(f).collect()),
+            MatrixValue::Matrix(m) => MatrixValue::Matrix(
+                m.into_iter()
+                    .map(|row| row.into_iter().map(&mut f).collect())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for MatrixValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let vector = |v: &[f64]| {
+            let items = v.iter().map(f64::to_string).collect::<Vec<String>>();
+            format!("[{}]", items.join(", "))
+        };
+
+        match self {
+            MatrixValue::Scalar(x) => write!(f, "{}", x),
+            MatrixValue::Vector(v) => write!(f, "{}", vector(v)),
+            MatrixValue::Matrix(m) => {
+                let rows = m.iter().map(|row| vector(row)).collect::<Vec<String>>();
+                write!(f, "[{}]", rows.join(", "))
+            }
+        }
+    }
+}
+
+type Matrix = Vec<Vec<f64>>;
+
+fn identity(n: usize) -> Matrix {
+    (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1. } else { 0. }).collect())
+        .collect()
+}
+
+fn product(a: &Matrix, b: &Matrix) -> Matrix {
+    a.iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|j| row.iter().zip(b).map(|(x, b_row)| x * b_row[j]).sum())
+                .collect()
+        })
+        .collect()
+}
+
+fn transpose(m: &Matrix) -> Matrix {
+    (0..m[0].len())
+        .map(|j| m.iter().map(|row| row[j]).collect())
+        .collect()
+}
+
+/// Elements smaller than this fraction of the largest one, times the size of the matrix, are
+/// taken as zero while eliminating, so that rounding errors don't make singular matrices regular.
+fn negligible(m: &Matrix) -> f64 {
+    let largest = m.iter().flatten().fold(0., |max: f64, x| max.max(x.abs()));
+    largest * f64::EPSILON * m.len().max(m[0].len()) as f64
+}
+
+/// Reduces `m` to row echelon form with partial pivoting, applying the same row operations to
+/// `augmented` if any. Returns the number of non-zero rows, and whether rows were swapped an odd
+/// number of times.
+fn eliminate(m: &mut Matrix, mut augmented: Option<&mut Matrix>) -> (usize, bool) {
+    let tolerance = negligible(m);
+    let (rows, cols) = (m.len(), m[0].len());
+    let mut rank = 0;
+    let mut odd = false;
+
+    for col in 0..cols {
+        if rank == rows {
+            break;
+        }
+
+        let pivot = (rank..rows)
+            .max_by(|&i, &j| m[i][col].abs().total_cmp(&m[j][col].abs()))
+            .unwrap();
+
+        if m[pivot][col].abs() <= tolerance {
+            continue;
+        }
+
+        if pivot != rank {
+            m.swap(pivot, rank);
+            if let Some(augmented) = augmented.as_deref_mut() {
+                augmented.swap(pivot, rank);
+            }
+            odd = !odd;
+        }
+
+        for i in 0..rows {
+            // Rows above the pivot are only reduced when inverting, to reach the identity
+            if i == rank || (i < rank && augmented.is_none()) {
+                continue;
+            }
+
+            let factor = m[i][col] / m[rank][col];
+            if factor == 0. {
+                continue;
+            }
+
+            subtract(m, i, rank, factor);
+            if let Some(augmented) = augmented.as_deref_mut() {
+                subtract(augmented, i, rank, factor);
+            }
+        }
+
+        rank += 1;
+    }
+
+    (rank, odd)
+}
+
+/// Subtracts `factor` times the row `from` to the row `to`.
+fn subtract(m: &mut Matrix, to: usize, from: usize, factor: f64) {
+    let from = m[from].clone();
+
+    for (x, y) in m[to].iter_mut().zip(from) {
+        *x -= factor * y;
+    }
+}
+
+fn determinant(m: &Matrix) -> f64 {
+    let mut m = m.clone();
+    let (rank, odd) = eliminate(&mut m, None);
+
+    if rank < m.len() {
+        return 0.;
+    }
+
+    let det = (0..m.len()).map(|i| m[i][i]).product::<f64>();
+    if odd {
+        -det
+    } else {
+        det
+    }
+}
+
+fn inverse(m: &Matrix) -> Result<Matrix, String> {
+    let mut m = m.clone();
+    let mut inverse = identity(m.len());
+
+    if eliminate(&mut m, Some(&mut inverse)).0 < m.len() {
+        return Err("the matrix is singular".to_owned());
+    }
+
+    for (i, row) in inverse.iter_mut().enumerate() {
+        for x in row.iter_mut() {
+            *x /= m[i][i];
+        }
+    }
+
+    Ok(inverse)
+}
+
+fn power(m: &Matrix, n: f64) -> Result<Matrix, String> {
+    if n.fract() != 0. || n.abs() > u32::MAX as f64 {
+        return Err("matrices can only be raised to integer powers".to_owned());
+    }
+
+    let mut base = if n < 0. { inverse(m)? } else { m.clone() };
+    let mut n = n.abs() as u32;
+    let mut res = identity(m.len());
+
+    while n > 0 {
+        if n % 2 == 1 {
+            res = product(&res, &base);
+        }
+        base = product(&base, &base);
+        n /= 2;
+    }
+
+    Ok(res)
+}
+
+/// Eigenvalues of `m` in increasing order, repeated according to their multiplicity, as the roots
+/// of its characteristic polynomial. Its coefficients are found with the Faddeev-LeVerrier
+/// algorithm, which is exact for integer matrices of moderate size.
+fn eigenvalues(m: &Matrix) -> Result<Vec<f64>, String> {
+    let n = m.len();
+    // Coefficients of det(λI - m), highest degree first
+    let mut coefficients = vec![1.];
+    let mut previous = vec![vec![0.; n]; n];
+
+    for k in 1..=n {
+        let c = coefficients[k - 1];
+        let mut current = product(m, &previous);
+        for (i, row) in current.iter_mut().enumerate() {
+            row[i] += c;
+        }
+
+        let am = product(m, &current);
+        let trace = (0..n).map(|i| am[i][i]).sum::<f64>();
+        coefficients.push(-trace / k as f64);
+        previous = current;
+    }
+
+    coefficients.reverse();
+    let mut values = Vec::new();
+
+    for (root, multiplicity) in polynomial::roots(&coefficients) {
+        if root.im != 0. {
+            return Err(format!(
+                "eig only finds real eigenvalues, but the matrix has {} as one",
+                ComplexValue::new(root.re, root.im)
+            ));
+        }
+
+        values.extend(std::iter::repeat_n(root.re + 0., multiplicity));
+    }
+
+    values.sort_by(f64::total_cmp);
+    Ok(values)
+}
+
+/// Evaluates expressions over numbers, vectors written `[1, 2, 3]` and matrices written
+/// `[[1, 2], [3, 4]]`.
+#[derive(Debug, Clone)]
+pub struct MatrixBackend {
+    functions: FunctionRegistry,
+}
+
+impl Default for MatrixBackend {
+    fn default() -> MatrixBackend {
+        MatrixBackend {
+            functions: FunctionRegistry::builtin(),
+        }
+    }
+}
+
+impl MatrixBackend {
+    pub fn new() -> MatrixBackend {
+        MatrixBackend::default()
+    }
+}
+
+fn square(m: &Matrix, name: &str) -> Result<(), String> {
+    if m.len() != m[0].len() {
+        return Err(format!(
+            "{} requires a square matrix, got {}",
+            name,
+            MatrixValue::Matrix(m.clone()).shape()
+        ));
+    }
+
+    Ok(())
+}
+
+impl Backend for MatrixBackend {
+    type Value = MatrixValue;
+
+    fn literal(&self, text: &str) -> Result<MatrixValue, String> {
+        text.parse::<f64>()
+            .map(MatrixValue::Scalar)
+            .map_err(|_| "unkown token".to_owned())
+    }
+
+    fn constant(&self, name: &str) -> Option<MatrixValue> {
+        CONSTANTS.get(name).map(|x| MatrixValue::Scalar(*x))
+    }
+
+    fn unary(&self, op: char, operand: MatrixValue) -> Result<MatrixValue, String> {
+        match op {
+            '+' => Ok(operand),
+            '-' => Ok(operand.map(|x| -x)),
+            '!' => Ok(operand.map(math::fact)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn binary(&self, op: char, lhs: MatrixValue, rhs: MatrixValue) -> Result<MatrixValue, String> {
+        use MatrixValue::*;
+
+        let res = match (op, &lhs, &rhs) {
+            (_, Scalar(x), Scalar(y)) => Scalar(eval::binary(op, *x, *y)),
+            ('+' | '-', Vector(u), Vector(v)) if u.len() == v.len() => Vector(
+                u.iter()
+                    .zip(v)
+                    .map(|(x, y)| eval::binary(op, *x, *y))
+                    .collect(),
+            ),
+            ('+' | '-', Matrix(a), Matrix(b)) if a.len() == b.len() && a[0].len() == b[0].len() => {
+                Matrix(
+                    a.iter()
+                        .zip(b)
+                        .map(|(u, v)| {
+                            u.iter()
+                                .zip(v)
+                                .map(|(x, y)| eval::binary(op, *x, *y))
+                                .collect()
+                        })
+                        .collect(),
+                )
+            }
+            ('*', Scalar(x), _) => rhs.clone().map(|y| x * y),
+            ('*' | '/' | '%' | INTEGER_DIVISION, _, Scalar(y)) => {
+                lhs.clone().map(|x| eval::binary(op, x, *y))
+            }
+            ('*', Matrix(a), Matrix(b)) if a[0].len() == b.len() => Matrix(product(a, b)),
+            ('*', Matrix(a), Vector(v)) if a[0].len() == v.len() => {
+                Vector(a.iter().map(|row| dot(row, v)).collect())
+            }
+            ('*', Vector(v), Matrix(a)) if v.len() == a.len() => {
+                Vector(transpose(a).iter().map(|col| dot(v, col)).collect())
+            }
+            ('*', Vector(_), Vector(_)) => {
+                return Err(format!(
+                    "cannot multiply {} by {}, use dot or cross instead",
+                    lhs.shape(),
+                    rhs.shape()
+                ))
+            }
+            ('^', Matrix(a), Scalar(n)) => {
+                square(a, "raising to a power")?;
+                Matrix(power(a, *n)?)
+            }
+            _ => {
+                let (lhs, rhs) = (lhs.shape(), rhs.shape());
+
+                return Err(match op {
+                    '+' => format!("cannot add {} and {}", lhs, rhs),
+                    '-' => format!("cannot subtract {} from {}", rhs, lhs),
+                    '*' => format!("cannot multiply {} by {}", lhs, rhs),
+                    '/' | INTEGER_DIVISION => format!("cannot divide {} by {}", lhs, rhs),
+                    '^' => format!("cannot raise {} to the power of {}", lhs, rhs),
+                    '%' => format!("cannot take the remainder of {} by {}", lhs, rhs),
+                    _ => unreachable!(),
+                });
+            }
+        };
+
+        Ok(res)
+    }
+
+    fn arity(&self, name: &str) -> Option<Arity> {
+        match MATRIX_FUNCTIONS
+            .iter()
+            .find(|(function, _)| *function == name)
+        {
+            Some((_, n)) => Some(Arity::Exact(*n)),
+            None => self
+                .functions
+                .get(name)
+                .filter(|f| f.native().is_some())
+                .map(|f| f.arity()),
+        }
+    }
+
+    fn call(&self, name: &str, args: &[MatrixValue]) -> Result<MatrixValue, String> {
+        use MatrixValue::*;
+
+        let invalid = |arg: &MatrixValue| format!("{} is not defined for {}", name, arg.shape());
+
+        let res = match (name, args) {
+            ("det", [Matrix(m)]) => {
+                square(m, name)?;
+                Scalar(determinant(m))
+            }
+            ("inv", [Matrix(m)]) => {
+                square(m, name)?;
+                Matrix(inverse(m)?)
+            }
+            ("eig", [Matrix(m)]) => {
+                square(m, name)?;
+                Vector(eigenvalues(m)?)
+            }
+            ("transpose", [Matrix(m)]) => Matrix(transpose(m)),
+            ("transpose", [x]) => x.clone(),
+            ("rank", [Matrix(m)]) => Scalar(eliminate(&mut m.clone(), None).0 as f64),
+            ("rank", [Vector(v)]) => Scalar(if v.iter().all(|x| *x == 0.) { 0. } else { 1. }),
+            ("norm", [Scalar(x)]) => Scalar(x.abs()),
+            ("norm", [Vector(v)]) => Scalar(dot(v, v).sqrt()),
+            ("norm", [Matrix(m)]) => Scalar(m.iter().map(|row| dot(row, row)).sum::<f64>().sqrt()),
+            ("dot", [Vector(u), Vector(v)]) if u.len() == v.len() => Scalar(dot(u, v)),
+            ("cross", [Vector(u), Vector(v)]) if u.len() == 3 && v.len() == 3 => Vector(vec![
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ]),
+            ("dot" | "cross", [u, v]) => {
+                return Err(format!(
+                    "{} is not defined for {} and {}",
+                    name,
+                    u.shape(),
+                    v.shape()
+                ))
+            }
+            (_, [arg])
+                if MATRIX_FUNCTIONS
+                    .iter()
+                    .any(|(function, _)| *function == name) =>
+            {
+                return Err(invalid(arg))
+            }
+            _ => {
+                let function = self.functions.get(name).unwrap();
+                let native = function.native().unwrap();
+
+                // Variadic functions take every element, such as mean([1, 2] * 2)
+                if let Arity::AtLeast(_) = function.arity() {
+                    let leading = eval::LEADING_PARAMETERS.get(name).map_or(0, |n| *n);
+
+                    if args
+                        .iter()
+                        .take(leading)
+                        .any(|arg| !matches!(arg, Scalar(_)))
+                    {
+                        return Err(eval::list_parameter(name));
+                    }
+
+                    let values = args
+                        .iter()
+                        .flat_map(|arg| match arg {
+                            Scalar(x) => vec![*x],
+                            Vector(v) => v.clone(),
+                            Matrix(m) => m.concat(),
+                        })
+                        .collect::<Vec<f64>>();
+
+                    return native(&values).map(Scalar);
+                }
+
+                // Functions of a single number apply element-wise, others only take numbers
+                if let [arg] = args {
+                    let mut error = None;
+                    let res = arg.clone().map(|x| {
+                        native(&[x]).unwrap_or_else(|err| {
+                            error.get_or_insert(err);
+                            f64::NAN
+                        })
+                    });
+
+                    return match error {
+                        Some(err) => Err(err),
+                        None => Ok(res),
+                    };
+                }
+
+                let values = args
+                    .iter()
+                    .map(|arg| match arg {
+                        Scalar(x) => Ok(*x),
+                        _ => Err(invalid(arg)),
+                    })
+                    .collect::<Result<Vec<f64>, String>>()?;
+
+                Scalar(native(&values)?)
+            }
+        };
+
+        Ok(res)
+    }
+
+    fn list(&self, items: Vec<MatrixValue>) -> Result<MatrixValue, String> {
+        use MatrixValue::*;
+
+        if items.iter().all(|item| matches!(item, Scalar(_))) {
+            return Ok(Vector(
+                items
+                    .into_iter()
+                    .map(|item| match item {
+                        Scalar(x) => x,
+                        _ => unreachable!(),
+                    })
+                    .collect(),
+            ));
+        }
+
+        let mut rows: Vec<Vec<f64>> = Vec::new();
+
+        for item in items {
+            match item {
+                Vector(row) if rows.is_empty() || rows[0].len() == row.len() => rows.push(row),
+                Vector(row) => {
+                    return Err(format!(
+                        "the rows of a matrix must have the same length, got {} and {}",
+                        rows[0].len(),
+                        row.len()
+                    ))
+                }
+                Scalar(_) => return Err("a matrix can't mix numbers and vectors".to_owned()),
+                Matrix(_) => return Err("matrices can't be nested".to_owned()),
+            }
+        }
+
+        Ok(Matrix(rows))
+    }
+}
+
+fn dot(u: &[f64], v: &[f64]) -> f64 {
+    u.iter().zip(v).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Session;
+
+    fn matrix(src: &str) -> String {
+        Session::new(MatrixBackend::new())
+            .evaluate(src)
+            .unwrap()
+            .to_string()
+    }
+
+    fn error(src: &str) -> String {
+        format!(
+            "{}",
+            Session::new(MatrixBackend::new())
+                .evaluate(src)
+                .expect_err("")
+        )
+    }
+
+    #[test]
+    fn matrix_literals() {
+        assert_eq!(matrix("[1, 2, 3]"), "[1, 2, 3]");
+        assert_eq!(matrix("[[1, 2], [3, 4]]"), "[[1, 2], [3, 4]]");
+        assert_eq!(matrix("[[1, 2]]"), "[[1, 2]]");
+        assert_eq!(matrix("[1 + 1, 2^3]"), "[2, 8]");
+        assert_eq!(matrix("2"), "2");
+    }
+
+    #[test]
+    fn matrix_operators() {
+        assert_eq!(matrix("[1, 2] + [3, 4]"), "[4, 6]");
+        assert_eq!(
+            matrix("[[1, 2], [3, 4]] - [[1, 1], [1, 1]]"),
+            "[[0, 1], [2, 3]]"
+        );
+        assert_eq!(matrix("2 [1, 2]"), "[2, 4]");
+        assert_eq!(matrix("[1, 2] / 2"), "[0.5, 1]");
+        assert_eq!(matrix("-[1, 2]"), "[-1, -2]");
+        assert_eq!(
+            matrix("[[1, 2], [3, 4]] * [[5, 6], [7, 8]]"),
+            "[[19, 22], [43, 50]]"
+        );
+        assert_eq!(matrix("[[1, 2], [3, 4]] * [1, 1]"), "[3, 7]");
+        assert_eq!(matrix("[1, 1] * [[1, 2], [3, 4]]"), "[4, 6]");
+        assert_eq!(matrix("[[1, 1], [0, 1]]^3"), "[[1, 3], [0, 1]]");
+        assert_eq!(matrix("[[1, 2], [3, 4]]^0"), "[[1, 0], [0, 1]]");
+        assert_eq!(matrix("[[2, 0], [0, 4]]^-1"), "[[0.5, 0], [0, 0.25]]");
+        assert_eq!(matrix("[3, 4]!"), "[6, 24]");
+    }
+
+    #[test]
+    fn matrix_functions() {
+        assert_eq!(matrix("det([[1, 2], [3, 4]])"), "-2");
+        assert_eq!(matrix("det([[2, 0, 1], [1, 3, 2], [1, 1, 2]])"), "6");
+        assert_eq!(matrix("det([[1, 2], [2, 4]])"), "0");
+        assert_eq!(matrix("inv([[2, 1], [1, 1]])"), "[[1, -1], [-1, 2]]");
+        assert_eq!(matrix("inv([[2, 0], [0, 4]])"), "[[0.5, 0], [0, 0.25]]");
+        assert_eq!(
+            matrix("transpose([[1, 2, 3], [4, 5, 6]])"),
+            "[[1, 4], [2, 5], [3, 6]]"
+        );
+        assert_eq!(matrix("dot([1, 2, 3], [4, 5, 6])"), "32");
+        assert_eq!(matrix("cross([1, 0, 0], [0, 1, 0])"), "[0, 0, 1]");
+        assert_eq!(matrix("norm([3, 4])"), "5");
+        assert_eq!(matrix("norm([[1, 1], [1, 1]])"), "2");
+        assert_eq!(matrix("rank([[1, 2], [2, 4]])"), "1");
+        assert_eq!(matrix("rank([[1, 2, 3], [4, 5, 6], [7, 8, 10]])"), "3");
+        assert_eq!(matrix("eig([[2, 1], [1, 2]])"), "[1, 3]");
+        assert_eq!(
+            matrix("eig([[2, 0, 0], [0, 3, 4], [0, 4, 9]])"),
+            "[1, 2, 11]"
+        );
+        assert_eq!(matrix("eig([[1, 1], [0, 1]])"), "[1, 1]");
+        assert_eq!(matrix("sqrt([4, 9])"), "[2, 3]");
+        assert_eq!(matrix("mean([1, 2] * 2, 9)"), "5");
+        assert_eq!(matrix("max([[1, 7], [3, 4]])"), "7");
+        assert_eq!(matrix("percentile(50, [1, 2] * 2, [6, 8])"), "5");
+        assert_eq!(matrix("logab(2, 8)"), "3");
+    }
+
+    #[test]
+    fn matrix_errors() {
+        assert_eq!(
+            error("[1, 2] + [1, 2, 3]"),
+            "error while parsing token \"[1, 2] + [1, 2, 3]\" in expression \"[1, 2] + [1, 2, 3]\": cannot add a vector of length 2 and a vector of length 3, [1, 2] + [1, 2, 3] <-- HERE"
+        );
+        assert_eq!(
+            error("1 + [[1, 2], [3]]"),
+            "error while parsing token \"[[1, 2], [3]]\" in expression \"1 + [[1, 2], [3]]\": the rows of a matrix must have the same length, got 2 and 1, 1 + [[1, 2], [3]] <-- HERE"
+        );
+        assert_eq!(
+            error("[[1, 2, 3]] * [[1, 2]]"),
+            "error while parsing token \"[[1, 2, 3]] * [[1, 2]]\" in expression \"[[1, 2, 3]] * [[1, 2]]\": cannot multiply a 1x3 matrix by a 1x2 matrix, [[1, 2, 3]] * [[1, 2]] <-- HERE"
+        );
+        assert!(error("[1, 2] * [3, 4]").contains("use dot or cross instead"));
+        assert!(
+            error("det([[1, 2, 3]])").contains("det requires a square matrix, got a 1x3 matrix")
+        );
+        assert!(error("inv([[1, 2], [2, 4]])").contains("the matrix is singular"));
+        assert!(error("eig([[0, -1], [1, 0]])").contains("eig only finds real eigenvalues"));
+        assert!(error("cross([1, 2], [3, 4])")
+            .contains("cross is not defined for a vector of length 2"));
+        assert!(error("det(2)").contains("det is not defined for a number"));
+        assert!(error("[[1, 2], [3, 4]]^0.5").contains("integer powers"));
+        assert!(error("[1, [2]]").contains("can't mix numbers and vectors"));
+        assert!(error("[[[1]]]").contains("matrices can't be nested"));
+        assert!(error("logab([2], 8)").contains("logab is not defined for a vector of length 1"));
+        assert!(error("2 / [1, 2]").contains("cannot divide a number by a vector of length 2"));
+        assert!(error("percentile([1, 2, 3, 4] * 2, 50)").contains("can't be lists"));
+        assert!(error("percentile([1, 2, 3, 4], 50)").contains("can't be lists"));
+    }
+}
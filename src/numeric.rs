@@ -0,0 +1,254 @@
+use crate::errors::*;
+use crate::lexer::Span;
+use crate::solver::SolveOptions;
+
+/// Number of pieces an interval is split into when looking for sign changes.
+const SAMPLES: usize = 64;
+
+/// Half-width of the first and of the widest interval scanned around the initial guess when no
+/// interval is given. The interval grows fourfold between both.
+const MIN_SEARCH_RADIUS: f64 = 1.;
+const MAX_SEARCH_RADIUS: f64 = 1e9;
+
+/// An interval whose ends have values of opposite signs, or a single point where the value is 0.
+#[derive(Debug, Clone, Copy)]
+struct Bracket {
+    a: f64,
+    b: f64,
+    fa: f64,
+    fb: f64,
+}
+
+/// Finds a root of `f`, a function of the unknown of an equation, as described by `options`.
+pub(crate) struct RootFinder<'a, F> {
+    src: &'a str,
+    options: &'a SolveOptions,
+    f: F,
+}
+
+impl<'a, F: FnMut(f64) -> Result<f64, SyntaxError>> RootFinder<'a, F> {
+    /// `src` is the equation `f` evaluates, and is only used to report errors.
+    pub fn new(src: &'a str, options: &'a SolveOptions, f: F) -> RootFinder<'a, F> {
+        RootFinder { src, options, f }
+    }
+
+    fn error(&self, msg: String) -> SyntaxError {
+        SyntaxError::at(self.src, Span::new(0, self.src.len()), msg)
+    }
+
+    /// With an interval, returns the leftmost root in it at which `f` changes sign. Otherwise,
+    /// starts with Newton's method from the initial guess (0 by default), falling back to
+    /// searching for a sign change in ever wider intervals around it.
+    pub fn find(&mut self) -> Result<f64, SyntaxError> {
+        if let Some((a, b)) = self.options.interval {
+            let brackets = self.sign_changes(a.min(b), a.max(b))?;
+
+            if brackets.is_empty() {
+                return Err(self.error(format!("no sign change between {} and {}", a, b)));
+            }
+
+            for bracket in brackets {
+                if let Some(root) = self.bracketed(bracket)? {
+                    return Ok(root);
+                }
+            }
+
+            return Err(self.error(format!(
+                "the equation only changes sign at discontinuities between {} and {}",
+                a, b
+            )));
+        }
+
+        let guess = self.options.guess.unwrap_or(0.);
+
+        if let Some(root) = self.newton(guess)? {
+            return Ok(root);
+        }
+
+        let mut radius = MIN_SEARCH_RADIUS;
+
+        while radius <= MAX_SEARCH_RADIUS {
+            let mut brackets = self.sign_changes(guess - radius, guess + radius)?;
+            brackets.sort_by(|x, y| {
+                let distance = |bracket: &Bracket| (bracket.a + bracket.b - 2. * guess).abs();
+                distance(x).partial_cmp(&distance(y)).unwrap()
+            });
+
+            for bracket in brackets {
+                if let Some(root) = self.bracketed(bracket)? {
+                    return Ok(root);
+                }
+            }
+
+            radius *= 4.;
+        }
+
+        Err(self.error(format!(
+            "no solution found near {}, try another initial guess or an interval",
+            guess
+        )))
+    }
+
+    /// Whether `f`, which is 0 at `x`, is not 0 on either side of it, so that `x` is a root rather
+    /// than a point where `f` underflowed, as `e^x` does far enough to the left.
+    fn is_isolated_zero(&mut self, x: f64) -> Result<bool, SyntaxError> {
+        let h = f64::EPSILON.cbrt() * x.abs().max(1.);
+        Ok((self.f)(x - h)? != 0. && (self.f)(x + h)? != 0.)
+    }
+
+    fn has_converged(&self, step: f64, x: f64) -> bool {
+        step.abs() <= self.options.tolerance * x.abs().max(1.)
+    }
+
+    /// Samples `f` over `[a, b]`, returning every piece where it changes sign, from left to right.
+    fn sign_changes(&mut self, a: f64, b: f64) -> Result<Vec<Bracket>, SyntaxError> {
+        let mut brackets = Vec::new();
+        let mut previous: Option<(f64, f64)> = None;
+
+        for i in 0..=SAMPLES {
+            let x = a + (b - a) * i as f64 / SAMPLES as f64;
+            let fx = (self.f)(x)?;
+
+            if fx == 0. {
+                if self.is_isolated_zero(x)? {
+                    brackets.push(Bracket {
+                        a: x,
+                        b: x,
+                        fa: fx,
+                        fb: fx,
+                    });
+                }
+            } else if let Some((xp, fp)) = previous {
+                if fp != 0. && fp.signum() != fx.signum() {
+                    brackets.push(Bracket {
+                        a: xp,
+                        b: x,
+                        fa: fp,
+                        fb: fx,
+                    });
+                }
+            }
+
+            previous = if fx.is_finite() { Some((x, fx)) } else { None };
+        }
+
+        Ok(brackets)
+    }
+
+    /// Narrows down `bracket` with the Illinois variant of regula falsi, which halves the value
+    /// kept at an end whenever the other one moves twice in a row, so that both ends converge.
+    /// Returns `None` if `f` turns out to be discontinuous instead of crossing 0.
+    fn bracketed(&mut self, bracket: Bracket) -> Result<Option<f64>, SyntaxError> {
+        let Bracket {
+            mut a,
+            mut b,
+            mut fa,
+            mut fb,
+        } = bracket;
+
+        if fa == 0. {
+            return Ok(Some(a));
+        } else if fb == 0. {
+            return Ok(Some(b));
+        }
+
+        // Near a root |f| only decreases, while it grows without bound near a pole
+        let bound = fa.abs().min(fb.abs());
+        let mut best = (f64::NAN, f64::INFINITY);
+        let mut moved_b = None;
+
+        for _ in 0..self.options.max_iterations {
+            let mut x = (a * fb - b * fa) / (fb - fa);
+
+            if !(x > a.min(b) && x < a.max(b)) {
+                x = 0.5 * (a + b);
+            }
+
+            let fx = (self.f)(x)?;
+
+            if fx == 0. {
+                return Ok(Some(x));
+            } else if fx.is_nan() {
+                return Ok(None);
+            }
+
+            if fx.abs() < best.1.abs() {
+                best = (x, fx);
+            }
+
+            if fx.signum() == fb.signum() {
+                (b, fb) = (x, fx);
+
+                if moved_b == Some(true) {
+                    fa *= 0.5;
+                }
+
+                moved_b = Some(true);
+            } else {
+                (a, fa) = (x, fx);
+
+                if moved_b == Some(false) {
+                    fb *= 0.5;
+                }
+
+                moved_b = Some(false);
+            }
+
+            if self.has_converged(b - a, x) {
+                return Ok(if best.1.abs() <= bound {
+                    Some(best.0)
+                } else {
+                    None
+                });
+            }
+        }
+
+        Err(self.error(format!(
+            "no convergence after {} iterations",
+            self.options.max_iterations
+        )))
+    }
+
+    /// Newton's method with a numerical derivative, switching to [`RootFinder::bracketed`] as soon
+    /// as a step crosses a sign change. Returns `None` if it doesn't converge.
+    fn newton(&mut self, guess: f64) -> Result<Option<f64>, SyntaxError> {
+        let mut x = guess;
+        let mut fx = (self.f)(x)?;
+
+        for _ in 0..self.options.max_iterations {
+            if fx == 0. {
+                return Ok(self.is_isolated_zero(x)?.then_some(x));
+            } else if !fx.is_finite() {
+                return Ok(None);
+            }
+
+            // The cube root of the machine epsilon balances truncation and rounding errors
+            let h = f64::EPSILON.cbrt() * x.abs().max(1.);
+            let derivative = ((self.f)(x + h)? - (self.f)(x - h)?) / (2. * h);
+
+            if derivative == 0. || !derivative.is_finite() {
+                return Ok(None);
+            }
+
+            let next = x - fx / derivative;
+            let f_next = (self.f)(next)?;
+
+            if f_next.is_finite() && f_next != 0. && f_next.signum() != fx.signum() {
+                return self.bracketed(Bracket {
+                    a: x,
+                    b: next,
+                    fa: fx,
+                    fb: f_next,
+                });
+            }
+
+            if self.has_converged(next - x, next) {
+                return Ok(Some(next));
+            }
+
+            (x, fx) = (next, f_next);
+        }
+
+        Ok(None)
+    }
+}
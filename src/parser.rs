@@ -1,440 +1,871 @@
-use debug_print::{debug_print as dprint, debug_println as dprintln};
 use phf::phf_map;
-use std::f64;
 
+use crate::ast::*;
 use crate::errors::*;
-use crate::helpers::*;
-use crate::math;
+use crate::lexer::*;
 
-static OPERATORS: phf::Map<char, usize> = phf_map! {
+/// Operator the lexer reads `//`, the truncated division whose remainder is given by `%`.
+pub const INTEGER_DIVISION: char = '\\';
+
+/// How `op` is written in expressions, which isn't the character standing for it in the case of
+/// [`INTEGER_DIVISION`].
+pub(crate) fn operator_symbol(op: char) -> String {
+    match op {
+        INTEGER_DIVISION => "//".to_owned(),
+        _ => op.to_string(),
+    }
+}
+
+pub static OPERATORS: phf::Map<char, usize> = phf_map! {
     '+' => 0,
     '-' => 0,
     '*' => 1,
     '/' => 1,
+    '\\' => 1,
     '^' => 2,
     '%' => 3,
     '!' => 4,
 };
 
-static CONSTANTS: phf::Map<&str, f64> = phf_map! {
-    "pi" => f64::consts::PI,
-    "e" => f64::consts::E,
-};
+// Prefix signs bind looser than `^`, so that `-2^2` evaluates to `-(2^2)`
+const PREFIX_PRECEDENCE: usize = 2;
 
-static FUNCTIONS: phf::Map<&str, usize> = phf_map! {
-    "cos" => 1,
-    "sin" => 1,
-    "tan" => 1,
-    "acos" => 1,
-    "asin" => 1,
-    "atan" => 1,
-    "ln" => 1,
-    "logab" => 2,
-    "sqrt" => 1,
-    "nroot" => 2,
-};
+// Implicit products such as `2x` or `3 km` bind tighter than `/` but looser than `^`, so that
+// `3 km / 20 min` evaluates to `(3 km) / (20 min)` and `2x^2` to `2 (x^2)`
+const IMPLICIT_PRECEDENCE: usize = 2;
 
-pub fn parse(expr: &str, full_expr: &str, offset: usize) -> Result<f64, SyntaxError> {
-    dprint!("parse {} with ctx {} at {}: ", expr, full_expr, offset);
+/// Keywords converting the value on their left to the unit on their right, as in `3 km in m`.
+const CONVERSION_KEYWORDS: [&str; 2] = ["in", "to"];
 
-    if expr.chars().filter(|c: &char| *c == ' ').count() == expr.len() {
-        return Err(SyntaxError::new(
-            expr.to_owned(),
-            full_expr.to_owned(),
-            "trying to parse an empty token".to_owned(),
-            offset,
-        ));
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|token| &token.kind)
     }
 
-    let first_char = expr.chars().next().unwrap();
-    let last_char = expr.chars().last().unwrap();
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned()?;
+        self.pos += 1;
+        Some(token)
+    }
 
-    if first_char == '"' && last_char == '"' {
-        let inner = &expr[1..expr.len() - 1];
-        dprintln!("unwrap into {}", inner);
-        return parse(inner, full_expr, offset + 1);
-    } else if first_char == '(' {
-        if let Some(i) = find_closing_parenthesis(expr) {
-            if i == expr.len() - 1 {
-                let inner = &expr[1..expr.len() - 1];
-                dprintln!("unwrap into {}", inner);
-                return parse(inner, full_expr, offset + 1);
-            }
-        } else {
-            return Err(SyntaxError::new(
-                expr.to_owned(),
-                full_expr.to_owned(),
-                "missing closing parenthesis".to_owned(),
-                offset + expr.len(),
-            ));
+    fn previous(&self) -> Option<&Token> {
+        self.pos.checked_sub(1).and_then(|i| self.tokens.get(i))
+    }
+
+    /// Span ending right after the last consumed token.
+    fn consumed_since(&self, start: Span) -> Span {
+        match self.previous() {
+            Some(token) => start.to(token.span),
+            None => start,
         }
     }
 
-    let mut split_pos: usize = 0;
-    let mut split_precedence: usize = 0;
-    let mut split_char: char = ' ';
+    fn error(&self, span: Span, msg: &str) -> SyntaxError {
+        SyntaxError::at(self.src, span, msg.to_owned())
+    }
 
-    let mut skipping: bool = false;
-    let mut par_level: isize = 0;
+    fn unexpected(&self, start: Span, token: &Token) -> SyntaxError {
+        let span = start.to(token.span);
 
-    for (i, c) in expr.chars().enumerate() {
-        if c == '(' && !skipping {
-            skipping = true;
+        match (&token.kind, self.tokens.get(self.pos.wrapping_sub(2))) {
+            (TokenKind::CloseParen, _) => self.error(span, "missing opening parenthesis"),
+            (TokenKind::CloseBracket, _) => self.error(span, "missing opening bracket"),
+            (
+                _,
+                Some(Token {
+                    kind: TokenKind::Operator('!'),
+                    ..
+                }),
+            ) => self.error(span, "unexpected token after operator \"!\""),
+            _ => self.error(span, "unexpected token"),
         }
+    }
+
+    fn at_operand_end(&self) -> bool {
+        matches!(
+            self.peek(),
+            None | Some(
+                TokenKind::CloseParen
+                    | TokenKind::CloseBracket
+                    | TokenKind::Comma
+                    | TokenKind::Equals
+                    | TokenKind::Separator
+            )
+        )
+    }
+
+    fn at_conversion(&self) -> bool {
+        matches!(self.peek(), Some(TokenKind::Identifier(name)) if CONVERSION_KEYWORDS.contains(&name.as_str()))
+    }
+
+    /// Whether the next token starts an operand multiplying the previous one, as in `2x`.
+    fn at_implicit_product(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(TokenKind::Identifier(_) | TokenKind::OpenParen | TokenKind::OpenBracket)
+        ) && !self.at_conversion()
+    }
+
+    fn expression(&mut self, min_precedence: usize) -> Result<Expr, SyntaxError> {
+        let mut lhs = self.operand()?;
+
+        loop {
+            if min_precedence == 0 && self.at_conversion() {
+                let keyword = self.next().unwrap();
+                let span = lhs.span.to(keyword.span);
+
+                if self.at_operand_end() {
+                    let name = &self.src[keyword.span.start..keyword.span.end];
+                    return Err(self.error(span, &format!("expected unit after \"{}\"", name)));
+                }
+
+                let target = self.expression(0)?;
+                let span = span.to(target.span);
+
+                if matches!(target.kind, ExprKind::Convert { .. }) {
+                    return Err(self.error(span, "conversions can't be chained"));
+                }
+
+                lhs = Expr::new(
+                    ExprKind::Convert {
+                        value: Box::new(lhs),
+                        target: Box::new(target),
+                    },
+                    span,
+                );
+                continue;
+            }
 
-        if skipping {
-            par_level += match c {
-                '(' => 1,
-                ')' => -1,
-                _ => 0,
+            if min_precedence <= IMPLICIT_PRECEDENCE && self.at_implicit_product() {
+                let rhs = self.expression(IMPLICIT_PRECEDENCE)?;
+                let span = lhs.span.to(rhs.span);
+
+                lhs = Expr::new(
+                    ExprKind::Binary {
+                        op: '*',
+                        lhs: Box::new(lhs),
+                        rhs: Box::new(rhs),
+                    },
+                    span,
+                );
+                continue;
+            }
+
+            let op = match self.peek() {
+                Some(&TokenKind::Operator(op)) => op,
+                _ => break,
             };
 
-            if par_level == 0 {
-                skipping = false;
+            let precedence = OPERATORS[&op];
+
+            if precedence < min_precedence {
+                break;
+            }
+
+            let op_span = self.next().unwrap().span;
+            let span = lhs.span.to(op_span);
+
+            if op == '!' {
+                lhs = Expr::new(
+                    ExprKind::Postfix {
+                        op,
+                        operand: Box::new(lhs),
+                    },
+                    span,
+                );
+                continue;
             }
 
-            continue;
-        } else if OPERATORS.contains_key(&c)
-            && ((OPERATORS.get(&c).unwrap() <= &split_precedence && split_char != ' ')
-                || split_char == ' ') && !((c == '+' || c == '-')
-                && (i != 0
-                    && OPERATORS.contains_key(&expr.chars().nth(i - 1).unwrap())
-                    && expr.chars().nth(i - 1).unwrap() != '!')) {
-            split_char = c;
-            split_pos = i;
-            split_precedence = *OPERATORS.get(&c).unwrap();
+            if self.at_operand_end() {
+                return Err(self.error(
+                    span,
+                    &format!("expected token after operator {}", operator_symbol(op)),
+                ));
+            }
+
+            // Binding the right hand side one level tighter makes every operator left associative
+            let rhs = self.expression(precedence + 1)?;
+            let span = span.to(rhs.span);
+
+            lhs = Expr::new(
+                ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
         }
+
+        Ok(lhs)
     }
 
-    if split_char == ' ' {
-        if let Ok(val) = expr.parse::<f64>() {
-            dprintln!("float");
-            Ok(val)
-        } else if CONSTANTS.contains_key(expr) {
-            dprintln!("math constant");
-            return Ok(*CONSTANTS.get(expr).unwrap());
-        } else if let Some((name, args, pos)) = is_function_call(expr) {
-            dprintln!("function call: {} {}", name, args);
-
-            if let Some(expected_arg_num) = FUNCTIONS.get(name) {
-                let arg_num = count_args(args);
-
-                if arg_num != *expected_arg_num {
-                    return Err(SyntaxError::new(
-                            expr.to_owned(),
-                            full_expr.to_owned(),
-                            format!("incorrect number of arguments passed, function {} takes {} parameters but {} {} passed", name, expected_arg_num, arg_num, if arg_num == 1 {"was"} else {"were"}),
-                            offset + expr.len() - 1,
-                        ));
+    fn operand(&mut self) -> Result<Expr, SyntaxError> {
+        let token = match self.next() {
+            Some(token) => token,
+            None => {
+                let span = self.consumed_since(Span::new(self.src.len(), self.src.len()));
+                return Err(self.error(span, "trying to parse an empty token"));
+            }
+        };
+
+        match token.kind {
+            TokenKind::Number(value) => Ok(Expr::new(ExprKind::Number(value), token.span)),
+            TokenKind::Identifier(name) => {
+                if self.peek() == Some(&TokenKind::OpenParen) {
+                    self.call(name, token.span)
+                } else {
+                    Ok(Expr::new(ExprKind::Identifier(name), token.span))
+                }
+            }
+            TokenKind::OpenParen => self.group(token.span, TokenKind::CloseParen),
+            TokenKind::Quote => self.group(token.span, TokenKind::Quote),
+            TokenKind::OpenBracket => self.list(token.span),
+            TokenKind::Operator(op @ ('+' | '-')) => {
+                if self.at_operand_end() {
+                    return Err(self.error(
+                        token.span,
+                        &format!("expected token after operator {}", operator_symbol(op)),
+                    ));
                 }
 
-                match arg_num {
-                    1 => match name {
-                        "cos" => Ok(parse(args, full_expr, pos + 1)?.cos()),
-                        "sin" => Ok(parse(args, full_expr, pos + 1)?.sin()),
-                        "tan" => Ok(parse(args, full_expr, pos + 1)?.tan()),
-                        "acos" => Ok(parse(args, full_expr, pos + 1)?.acos()),
-                        "asin" => Ok(parse(args, full_expr, pos + 1)?.asin()),
-                        "atan" => Ok(parse(args, full_expr, pos + 1)?.atan()),
-                        "ln" => Ok(parse(args, full_expr, pos + 1)?.ln()),
-                        "sqrt" => Ok(parse(args, full_expr, pos + 1)?.sqrt()),
-                        _ => unreachable!(),
+                let operand = self.expression(PREFIX_PRECEDENCE)?;
+                let span = token.span.to(operand.span);
+
+                Ok(Expr::new(
+                    ExprKind::Prefix {
+                        op,
+                        operand: Box::new(operand),
                     },
-                    2 => {
-                        let split = find_nth_comma(args, 1).unwrap();
-                        let first = parse(&args[..split], full_expr, pos + 1)?;
-                        let second = parse(&args[split + 1..], full_expr, pos + split + 2)?;
-
-                        match name {
-                            "logab" => Ok(second.ln() / first.ln()), // log_a(b) = ln b / ln a
-                            "nroot" => Ok(f64::powf(second, 1. / first)),
-                            _ => unreachable!(),
-                        }
-                    }
-                    _ => unreachable!(),
-                }
-            } else {
-                return Err(SyntaxError::new(
-                    expr.to_owned(),
-                    full_expr.to_owned(),
-                    format!("unkown function name \"{}\"", name),
-                    offset + expr.len(),
-                ));
+                    span,
+                ))
             }
-        } else if last_char == ')' {
-            Err(SyntaxError::new(
-                expr.to_owned(),
-                full_expr.to_owned(),
-                "missing opening parenthesis".to_owned(),
-                offset + expr.len(),
-            ))
+            TokenKind::Operator(op) => Err(self.error(
+                token.span,
+                &format!("expected token before operator {}", operator_symbol(op)),
+            )),
+            TokenKind::CloseParen => Err(self.error(token.span, "missing opening parenthesis")),
+            TokenKind::CloseBracket => Err(self.error(token.span, "missing opening bracket")),
+            TokenKind::Comma | TokenKind::Equals | TokenKind::Separator => {
+                Err(self.error(token.span, "unexpected token"))
+            }
+        }
+    }
+
+    fn group(&mut self, open: Span, close: TokenKind) -> Result<Expr, SyntaxError> {
+        let missing = if close == TokenKind::Quote {
+            "missing closing quote"
         } else {
-            Err(SyntaxError::new(
-                expr.to_owned(),
-                full_expr.to_owned(),
-                "unkown token".to_owned(),
-                offset + expr.len(),
-            ))
+            "missing closing parenthesis"
+        };
+
+        if self.peek() == Some(&close) {
+            let span = open.to(self.next().unwrap().span);
+            return Err(self.error(span, "trying to parse an empty token"));
+        } else if self.peek().is_none() {
+            return Err(self.error(open, missing));
         }
-    } else {
-        let left = &expr[..split_pos];
-        let right = &expr[split_pos + 1..];
-
-        dprintln!("{} {} {}", left, split_char, right);
-
-        let left = if left.is_empty() {
-            if split_char == '+' || split_char == '-' {
-                Ok(0.)
-            } else {
-                Err(SyntaxError::new(
-                    expr.to_owned(),
-                    full_expr.to_owned(),
-                    format!("expected token before operator {}", split_char),
-                    offset + expr.len(),
-                ))
+
+        let mut inner = self.expression(0)?;
+
+        match self.next() {
+            Some(token) if token.kind == close => {
+                inner.span = open.to(token.span);
+                Ok(inner)
             }
+            Some(token) => Err(self.unexpected(open, &token)),
+            None => Err(self.error(open.to(inner.span), missing)),
+        }
+    }
+
+    fn call(&mut self, name: String, name_span: Span) -> Result<Expr, SyntaxError> {
+        self.next();
+
+        let mut args = Vec::new();
+
+        if self.peek() == Some(&TokenKind::CloseParen) {
+            self.next();
         } else {
-            parse(left, full_expr, offset)
-        }?;
-
-        let right = if right.is_empty() {
-            if split_char == '!' {
-                Ok(0.)
-            } else {
-                Err(SyntaxError::new(
-                    expr.to_owned(),
-                    full_expr.to_owned(),
-                    format!("expected token after operator {}", split_char),
-                    offset + expr.len(),
-                ))
+            loop {
+                if self.at_operand_end() {
+                    let span = self.consumed_since(name_span);
+                    return Err(match self.peek() {
+                        None => self.error(span, "missing closing parenthesis"),
+                        Some(_) => self.error(span, "trying to parse an empty token"),
+                    });
+                }
+
+                args.push(self.expression(0)?);
+
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::Comma,
+                        ..
+                    }) => continue,
+                    Some(Token {
+                        kind: TokenKind::CloseParen,
+                        ..
+                    }) => break,
+                    Some(token) => return Err(self.unexpected(name_span, &token)),
+                    None => {
+                        let span = self.consumed_since(name_span);
+                        return Err(self.error(span, "missing closing parenthesis"));
+                    }
+                }
             }
-        } else if split_char == '!' {
-            Err(SyntaxError::new(
-                expr.to_owned(),
-                full_expr.to_owned(),
-                "unexpected token after operator \"!\"".to_owned(),
-                offset + expr.len(),
-            ))
-        } else {
-            parse(right, full_expr, offset + split_pos + 1)
-        }?;
-
-        match split_char {
-            '+' => Ok(left + right),
-            '-' => Ok(left - right),
-            '*' => Ok(left * right),
-            '/' => Ok(left / right),
-            '^' => Ok(if (left + right).abs() < f64::EPSILON {
-                f64::NAN
-            } else {
-                f64::powf(left, right)
-            }),
-            '!' => Ok(math::fact(left)),
-            '%' => Ok(left % right),
-            _ => unreachable!(),
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let span = self.consumed_since(name_span);
 
-    #[test]
-    fn unkown_token() {
-        assert_parse_error!("error", "error while parsing token \"error\" in expression \"error\": unkown token, error <-- HERE");
+        Ok(Expr::new(ExprKind::Call { name, args }, span))
     }
 
-    #[test]
-    fn float_simple() {
-        assert_parse_result_float!("1", 1.0);
-        assert_parse_result_float!("-5", -5.0);
-    }
+    /// Parses the items of `[a, b, ...]`, after the opening bracket at `open`.
+    fn list(&mut self, open: Span) -> Result<Expr, SyntaxError> {
+        let mut items = Vec::new();
 
-    #[test]
-    fn float_decimals() {
-        assert_parse_result_float!("1.", 1.0);
-        assert_parse_result_float!(".1", 0.1);
-        assert_parse_result_float!("1.1", 1.1);
-    }
+        loop {
+            if self.at_operand_end() {
+                let span = self.consumed_since(open);
+                return Err(match self.peek() {
+                    None => self.error(span, "missing closing bracket"),
+                    Some(_) => self.error(span, "trying to parse an empty token"),
+                });
+            }
 
-    #[test]
-    fn float_nan() {
-        assert_parse_result_is!("nan", is_nan);
-    }
+            items.push(self.expression(0)?);
+
+            match self.next() {
+                Some(Token {
+                    kind: TokenKind::Comma,
+                    ..
+                }) => continue,
+                Some(Token {
+                    kind: TokenKind::CloseBracket,
+                    ..
+                }) => break,
+                Some(token) => return Err(self.unexpected(open, &token)),
+                None => {
+                    let span = self.consumed_since(open);
+                    return Err(self.error(span, "missing closing bracket"));
+                }
+            }
+        }
 
-    #[test]
-    fn float_inf() {
-        assert_parse_result_is!("inf", is_infinite);
+        let span = self.consumed_since(open);
+        Ok(Expr::new(ExprKind::List(items), span))
     }
 
-    #[test]
-    fn operator_sum() {
-        assert_parse_result_float!("5+3", 8.);
-    }
+    fn new(src: &'a str) -> Result<Parser<'a>, SyntaxError> {
+        let tokens = tokenize(src)?;
 
-    #[test]
-    fn operator_difference() {
-        assert_parse_result_float!("1-1", 0.);
-    }
+        if tokens
+            .iter()
+            .all(|token| token.kind == TokenKind::Separator)
+        {
+            return Err(SyntaxError::at(
+                src,
+                Span::new(0, src.len()),
+                "trying to parse an empty token".to_owned(),
+            ));
+        }
 
-    #[test]
-    fn operator_product() {
-        assert_parse_result_float!("2*4", 8.);
+        Ok(Parser {
+            src,
+            tokens,
+            pos: 0,
+        })
     }
 
-    #[test]
-    fn operator_quotient() {
-        assert_parse_result_float!("10/2", 5.);
+    /// Whether the upcoming tokens look like `name(param, ...) =`.
+    fn at_definition(&self) -> bool {
+        let mut tokens = self.tokens[self.pos..].iter().map(|token| &token.kind);
+
+        if !matches!(
+            (tokens.next(), tokens.next()),
+            (Some(TokenKind::Identifier(_)), Some(TokenKind::OpenParen))
+        ) {
+            return false;
+        }
+
+        let mut expect_param = true;
+
+        for kind in tokens.by_ref() {
+            match (kind, expect_param) {
+                (TokenKind::Identifier(_), true) | (TokenKind::Comma, false) => {
+                    expect_param = !expect_param
+                }
+                (TokenKind::CloseParen, _) => break,
+                _ => return false,
+            }
+        }
+
+        tokens.next() == Some(&TokenKind::Equals)
     }
 
-    #[test]
-    fn operator_exponent() {
-        assert_parse_result_float!("2^2", 4.);
+    fn definition(&mut self) -> Result<Stmt, SyntaxError> {
+        let (name, name_span) = match self.next() {
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                span,
+            }) => (name, span),
+            _ => unreachable!(),
+        };
+
+        let mut params: Vec<String> = Vec::new();
+
+        while let Some(token) = self.next() {
+            match token.kind {
+                TokenKind::Identifier(param) => {
+                    if params.contains(&param) {
+                        return Err(
+                            self.error(token.span, &format!("duplicate parameter \"{}\"", param))
+                        );
+                    }
+
+                    params.push(param);
+                }
+                TokenKind::Equals => break,
+                _ => {}
+            }
+        }
+
+        let span = self.consumed_since(name_span);
+
+        if self.at_operand_end() {
+            return Err(self.error(span, "expected token after operator ="));
+        }
+
+        let body = self.expression(0)?;
+        let span = span.to(body.span);
+
+        Ok(Stmt {
+            kind: StmtKind::Define { name, params, body },
+            span,
+        })
     }
 
-    #[test]
-    fn operator_modulo() {
-        assert_parse_result_float!("5%5", 0.);
-        assert_parse_result_float!("10%5", 0.);
-        assert_parse_result_float!("11%5", 1.);
+    fn statement(&mut self) -> Result<Stmt, SyntaxError> {
+        if self.at_definition() {
+            return self.definition();
+        }
+
+        if let (
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                span: name_span,
+            }),
+            Some(Token {
+                kind: TokenKind::Equals,
+                span: equals_span,
+            }),
+        ) = (self.tokens.get(self.pos), self.tokens.get(self.pos + 1))
+        {
+            let (name, span) = (name.clone(), name_span.to(*equals_span));
+            self.pos += 2;
+
+            if self.at_operand_end() {
+                return Err(self.error(span, "expected token after operator ="));
+            }
+
+            let value = self.expression(0)?;
+            let span = span.to(value.span);
+
+            return Ok(Stmt {
+                kind: StmtKind::Assign { name, value },
+                span,
+            });
+        }
+
+        let expr = self.expression(0)?;
+        let span = expr.span;
+
+        Ok(Stmt {
+            kind: StmtKind::Expr(expr),
+            span,
+        })
     }
+}
 
-    #[test]
-    fn operator_factorial() {
-        assert_parse_result_is!("(-1)!", is_nan);
-        assert_parse_result_float!("0!", 1.);
-        assert_parse_result_float!("1!", 1.);
-        assert_parse_result_float!("2!", 2.);
-        assert_parse_result_float!("3!", 6.);
-        assert_parse_result_float!("4!", 24.);
+/// Parses `src` into a list of statements separated by `;` or newlines. Each statement is either
+/// an assignment (`name = expr`), a function definition (`name(x, y) = expr`) or a plain
+/// expression.
+pub fn parse_statements(src: &str) -> Result<Vec<Stmt>, SyntaxError> {
+    let mut parser = Parser::new(src)?;
+    let mut stmts = Vec::new();
+
+    loop {
+        while parser.peek() == Some(&TokenKind::Separator) {
+            parser.next();
+        }
+
+        if parser.peek().is_none() {
+            break;
+        }
+
+        let stmt = parser.statement()?;
+
+        match parser.next() {
+            None
+            | Some(Token {
+                kind: TokenKind::Separator,
+                ..
+            }) => stmts.push(stmt),
+            Some(token) => return Err(parser.unexpected(stmt.span, &token)),
+        }
     }
 
-    #[test]
-    fn operator_precedence() {
-        assert_parse_result_float!("1+2*3", 7.);
-        assert_parse_result_float!("2*3-1", 5.);
-        assert_parse_result_float!("1+4/2", 3.);
-        assert_parse_result_float!("1^2/4", 0.25);
+    Ok(stmts)
+}
+
+/// Parses a single expression, without assignments or definitions.
+pub fn parse_expression(src: &str) -> Result<Expr, SyntaxError> {
+    let mut parser = Parser::new(src)?;
+    let expr = parser.expression(0)?;
+
+    match parser.next() {
+        None => Ok(expr),
+        Some(token) => Err(parser.unexpected(expr.span, &token)),
     }
+}
 
-    #[test]
-    fn operator_expected_token() {
-        assert_parse_error!("1*", "error while parsing token \"1*\" in expression \"1*\": expected token after operator *, 1* <-- HERE");
-        assert_parse_error!("*1", "error while parsing token \"*1\" in expression \"*1\": expected token before operator *, *1 <-- HERE");
+/// Parses an equation of the form `lhs = rhs`, returning both sides.
+pub fn parse_equation(src: &str) -> Result<(Expr, Expr), SyntaxError> {
+    let mut equations = parse_equations(src)?;
+
+    match equations.len() {
+        1 => Ok(equations.remove(0)),
+        _ => Err(SyntaxError::at(
+            src,
+            Span::new(0, src.len()),
+            "expected a single equation".to_owned(),
+        )),
     }
+}
 
-    #[test]
-    fn operator_unexpected_token() {
-        assert_parse_error!("1!1", "error while parsing token \"1!1\" in expression \"1!1\": unexpected token after operator \"!\", 1!1 <-- HERE");
+/// Parses a list of equations of the form `lhs = rhs`, separated by `;` or newlines.
+pub fn parse_equations(src: &str) -> Result<Vec<(Expr, Expr)>, SyntaxError> {
+    let mut parser = Parser::new(src)?;
+    let mut equations = Vec::new();
+
+    loop {
+        while parser.peek() == Some(&TokenKind::Separator) {
+            parser.next();
+        }
+
+        if parser.peek().is_none() {
+            break;
+        }
+
+        let lhs = parser.expression(0)?;
+
+        let equals = match parser.next() {
+            Some(Token {
+                kind: TokenKind::Equals,
+                span,
+            }) => span,
+            None
+            | Some(Token {
+                kind: TokenKind::Separator,
+                ..
+            }) => return Err(parser.error(lhs.span, "expected an equation of the form lhs = rhs")),
+            Some(token) => return Err(parser.unexpected(lhs.span, &token)),
+        };
+
+        if parser.at_operand_end() {
+            return Err(parser.error(lhs.span.to(equals), "expected token after operator ="));
+        }
+
+        let rhs = parser.expression(0)?;
+
+        match parser.next() {
+            None
+            | Some(Token {
+                kind: TokenKind::Separator,
+                ..
+            }) => equations.push((lhs, rhs)),
+            Some(token) => return Err(parser.unexpected(lhs.span.to(rhs.span), &token)),
+        }
     }
 
-    #[test]
-    fn implicit_operators() {
-        assert_parse_result_float!("2*-2", -4.);
-        assert_parse_result_float!("1--1", 2.);
-        assert_parse_result_float!("-1*-1", 1.);
+    Ok(equations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> Result<Expr, SyntaxError> {
+        match parse_statements(src)?.remove(0).kind {
+            StmtKind::Expr(expr) => Ok(expr),
+            kind => panic!("expected expression, got {:?}", kind),
+        }
     }
 
-    #[test]
-    fn unwrap_quotes() {
-        assert_parse_result_float!("\"10-10\"", 0.);
+    fn binary(op: char, lhs: Expr, rhs: Expr) -> ExprKind {
+        ExprKind::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        }
     }
 
-    #[test]
-    fn unwrap_parenthesis_simple() {
-        assert_parse_result_float!("(1+1)", 2.);
+    fn number(value: f64, start: usize) -> Expr {
+        Expr::new(ExprKind::Number(value), Span::new(start, start + 1))
+    }
+
+    #[test]
+    fn parse_number() {
+        assert_eq!(parse("1").unwrap(), number(1., 0));
+    }
+
+    #[test]
+    fn parse_precedence() {
+        assert_eq!(
+            parse("1+2*3").unwrap().kind,
+            binary(
+                '+',
+                number(1., 0),
+                Expr::new(binary('*', number(2., 2), number(3., 4)), Span::new(2, 5))
+            )
+        );
+    }
+
+    #[test]
+    fn parse_left_associative() {
+        assert_eq!(
+            parse("1-2-3").unwrap().kind,
+            binary(
+                '-',
+                Expr::new(binary('-', number(1., 0), number(2., 2)), Span::new(0, 3)),
+                number(3., 4)
+            )
+        );
+    }
+
+    #[test]
+    fn parse_implicit_product() {
+        let identifier = |name: &str, start: usize| {
+            Expr::new(
+                ExprKind::Identifier(name.to_owned()),
+                Span::new(start, start + name.len()),
+            )
+        };
+
+        // 2 / (3 x), binding tighter than the division
+        assert_eq!(
+            parse("2/3x").unwrap().kind,
+            binary(
+                '/',
+                number(2., 0),
+                Expr::new(
+                    binary('*', number(3., 2), identifier("x", 3)),
+                    Span::new(2, 4)
+                )
+            )
+        );
+
+        // 2 (x^2), binding looser than the exponent
+        assert_eq!(
+            parse("2x^2").unwrap().kind,
+            binary(
+                '*',
+                number(2., 0),
+                Expr::new(
+                    binary('^', identifier("x", 1), number(2., 3)),
+                    Span::new(1, 4)
+                )
+            )
+        );
+
+        assert!(matches!(
+            parse("2(1+1)").unwrap().kind,
+            ExprKind::Binary { op: '*', .. }
+        ));
+        assert!(parse("1 2").is_err());
     }
 
     #[test]
-    fn unwrap_parenthesis_nested() {
-        assert_parse_result_float!("(((1)+1)+(1+(1)))", 4.);
-        assert_parse_result_float!("(1+(1+(1+(1+(1+(1+(1)))))))", 7.);
+    fn parse_conversion() {
+        match parse("1+2 km in m/s").unwrap().kind {
+            ExprKind::Convert { value, target } => {
+                assert_eq!(value.span, Span::new(0, 6));
+                assert_eq!(target.span, Span::new(10, 13));
+            }
+            kind => panic!("expected conversion, got {:?}", kind),
+        }
+
+        assert_eq!(
+            format!("{}", parse("2 km to").expect_err("")),
+            "error while parsing token \"2 km to\" in expression \"2 km to\": expected unit after \"to\", 2 km to <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", parse("3 m in m in cm").expect_err("")),
+            "error while parsing token \"3 m in m in cm\" in expression \"3 m in m in cm\": conversions can't be chained, 3 m in m in cm <-- HERE"
+        );
     }
 
     #[test]
-    fn unwrap_parenthesis_missing_closing() {
-        assert_parse_error!("(1+1", "error while parsing token \"(1+1\" in expression \"(1+1\": missing closing parenthesis, (1+1 <-- HERE");
-        assert_parse_error!("(1+(1)", "error while parsing token \"(1+(1)\" in expression \"(1+(1)\": missing closing parenthesis, (1+(1) <-- HERE");
+    fn parse_call_arguments() {
+        match parse("logab(2, 1+1)").unwrap().kind {
+            ExprKind::Call { name, args } => {
+                assert_eq!(name, "logab");
+                assert_eq!(args.len(), 2);
+                assert_eq!(args[1].span, Span::new(9, 12));
+            }
+            kind => panic!("expected call, got {:?}", kind),
+        }
     }
 
     #[test]
-    fn unwrap_parenthesis_missing_opening() {
-        assert_parse_error!("1+1)", "error while parsing token \"1)\" in expression \"1+1)\": missing opening parenthesis, 1+1) <-- HERE");
-        assert_parse_error!("(1+1))", "error while parsing token \"(1+1))\" in expression \"(1+1))\": missing opening parenthesis, (1+1)) <-- HERE");
+    fn parse_lists() {
+        match parse("[[1, 2], [x, 4]]").unwrap().kind {
+            ExprKind::List(rows) => {
+                assert_eq!(rows.len(), 2);
+                assert_eq!(rows[1].span, Span::new(9, 15));
+                assert!(matches!(&rows[1].kind, ExprKind::List(items) if items.len() == 2));
+            }
+            kind => panic!("expected list, got {:?}", kind),
+        }
+
+        assert_eq!(
+            format!("{}", parse("[1, 2").expect_err("")),
+            "error while parsing token \"[1, 2\" in expression \"[1, 2\": missing closing bracket, [1, 2 <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", parse("1, 2]").expect_err("")),
+            "error while parsing token \"1,\" in expression \"1, 2]\": unexpected token, 1, <-- HERE"
+        );
+        assert!(parse("[]").is_err());
+        assert!(parse("[1,]").is_err());
+        assert!(parse("[1 2]").is_err());
+        assert!(parse("2]").is_err());
+    }
+
+    #[test]
+    fn parse_statements_simple() {
+        let stmts = parse_statements("x = 1; x + 1\n\n;").unwrap();
+
+        assert_eq!(stmts.len(), 2);
+        assert_eq!(
+            stmts[0].kind,
+            StmtKind::Assign {
+                name: "x".to_owned(),
+                value: number(1., 4)
+            }
+        );
+        assert_eq!(stmts[1].span, Span::new(7, 12));
     }
 
     #[test]
-    fn function_single_cos() {
-        assert_parse_result_float!("cos(0)", 1.0);
-        assert_parse_result_float!("cos(pi)", -1.0);
+    fn parse_equation_sides() {
+        let (lhs, rhs) = parse_equation("2 = 3").unwrap();
+        assert_eq!((lhs, rhs), (number(2., 0), number(3., 4)));
+
+        assert_eq!(
+            format!("{}", parse_equation("2 + 1").expect_err("")),
+            "error while parsing token \"2 + 1\" in expression \"2 + 1\": expected an equation of the form lhs = rhs, 2 + 1 <-- HERE"
+        );
+        assert!(parse_equation("1 = 2 = 3").is_err());
+        assert!(parse_equation("1 =").is_err());
+        assert!(parse_equation("1 = 2; 3 = 4").is_err());
     }
 
     #[test]
-    fn function_single_sin() {
-        assert_parse_result_float!("sin(0)", 0.0);
-        assert_parse_result_float!("sin(pi)", 0.0);
+    fn parse_equations_list() {
+        let equations = parse_equations("1 = 2; 3 = 4\n").unwrap();
+
+        assert_eq!(
+            equations,
+            vec![
+                (number(1., 0), number(2., 4)),
+                (number(3., 7), number(4., 11))
+            ]
+        );
+        assert_eq!(
+            format!("{}", parse_equations("1 = 2; 3").expect_err("")),
+            "error while parsing token \"3\" in expression \"1 = 2; 3\": expected an equation of the form lhs = rhs, 1 = 2; 3 <-- HERE"
+        );
     }
 
     #[test]
-    fn function_single_tan() {
-        assert_parse_result_float!("tan(0)", 0.0);
+    fn parse_definition() {
+        let stmts = parse_statements("f(x, y) = x^2 + y; g() = 1").unwrap();
+
+        match &stmts[0].kind {
+            StmtKind::Define { name, params, body } => {
+                assert_eq!(name, "f");
+                assert_eq!(params, &vec!["x".to_owned(), "y".to_owned()]);
+                assert_eq!(body.span, Span::new(10, 17));
+            }
+            kind => panic!("expected definition, got {:?}", kind),
+        }
+
+        assert!(matches!(&stmts[1].kind, StmtKind::Define { params, .. } if params.is_empty()));
     }
 
     #[test]
-    fn function_single_acos() {
-        assert_parse_result_float!("acos(0)", f64::consts::FRAC_PI_2);
+    fn parse_definition_errors() {
+        assert_parse_error!("f(x, x) = x", "error while parsing token \"x\" in expression \"f(x, x) = x\": duplicate parameter \"x\", f(x, x <-- HERE");
+        assert_parse_error!("f(x) =", "error while parsing token \"f(x) =\" in expression \"f(x) =\": expected token after operator =, f(x) = <-- HERE");
+        assert_parse_error!("f(2) = 1", "error while parsing token \"f(2) =\" in expression \"f(2) = 1\": unexpected token, f(2) = <-- HERE");
     }
 
     #[test]
-    fn function_single_asin() {
-        assert_parse_result_float!("asin(1)", f64::consts::FRAC_PI_2);
+    fn parse_statements_errors() {
+        assert_parse_error!("x =", "error while parsing token \"x =\" in expression \"x =\": expected token after operator =, x = <-- HERE");
+        assert_parse_error!("1 = 2", "error while parsing token \"1 =\" in expression \"1 = 2\": unexpected token, 1 = <-- HERE");
+        assert_parse_error!(";", "error while parsing token \";\" in expression \";\": trying to parse an empty token, ; <-- HERE");
     }
 
     #[test]
-    fn function_single_atan() {
-        assert_parse_result_float!("atan(1)", f64::consts::FRAC_PI_4);
+    fn parse_whitespace() {
+        assert_parse_result_float!("1 + 2 * 3", 7.);
+        assert_parse_result_float!(" sqrt( 4 ) ", 2.);
     }
 
     #[test]
-    fn function_single_ln() {
-        assert_parse_result_is!("ln(0)", is_infinite);
-        assert_parse_result_float!("ln(1)", 0.0);
-        assert_parse_result_float!("ln(e)", 1.0);
+    fn parse_empty() {
+        assert_parse_error!(
+            "",
+            "error while parsing token \"\" in expression \"\": trying to parse an empty token,  <-- HERE"
+        );
+        assert_parse_error!("()", "error while parsing token \"()\" in expression \"()\": trying to parse an empty token, () <-- HERE");
     }
 
     #[test]
-    fn function_single_sqrt() {
-        assert_parse_result_float!("sqrt(9)", 3.);
-        assert_parse_result_float!("sqrt(4)", 2.);
-        assert_parse_result_float!("sqrt(0)", 0.);
-        assert_parse_result_is!("sqrt(-1)", is_nan);
+    fn operator_expected_token() {
+        assert_parse_error!("1*", "error while parsing token \"1*\" in expression \"1*\": expected token after operator *, 1* <-- HERE");
+        assert_parse_error!("2//", "error while parsing token \"2//\" in expression \"2//\": expected token after operator //, 2// <-- HERE");
+        assert_parse_error!("// 2", "error while parsing token \"//\" in expression \"// 2\": expected token before operator //, // <-- HERE");
+        assert_parse_error!("*1", "error while parsing token \"*\" in expression \"*1\": expected token before operator *, * <-- HERE");
     }
 
     #[test]
-    fn function_two_logab() {
-        assert_parse_result_float!("logab(2,16)", 4.);
-        assert_parse_result_float!("logab(3,9)", 2.);
-        assert_parse_result_is!("logab(1,1)", is_nan);
+    fn operator_unexpected_token() {
+        assert_parse_error!("1!1", "error while parsing token \"1!1\" in expression \"1!1\": unexpected token after operator \"!\", 1!1 <-- HERE");
     }
 
     #[test]
-    fn function_two_nroot() {
-        assert_parse_result_float!("nroot(3,8)", 2.);
-        assert_parse_result_float!("nroot(4,16)", 2.);
+    fn unwrap_parenthesis_missing_closing() {
+        assert_parse_error!("(1+1", "error while parsing token \"(1+1\" in expression \"(1+1\": missing closing parenthesis, (1+1 <-- HERE");
+        assert_parse_error!("(1+(1)", "error while parsing token \"(1+(1)\" in expression \"(1+(1)\": missing closing parenthesis, (1+(1) <-- HERE");
     }
 
     #[test]
-    fn function_unkown_name() {
-        assert_parse_error!("test()", "error while parsing token \"test()\" in expression \"test()\": unkown function name \"test\", test() <-- HERE");
+    fn unwrap_parenthesis_missing_opening() {
+        assert_parse_error!("1+1)", "error while parsing token \"1+1)\" in expression \"1+1)\": missing opening parenthesis, 1+1) <-- HERE");
+        assert_parse_error!("(1+1))", "error while parsing token \"(1+1))\" in expression \"(1+1))\": missing opening parenthesis, (1+1)) <-- HERE");
     }
 
     #[test]
-    fn function_argument_missmatch() {
-        assert_parse_error!("sqrt()", "error while parsing token \"sqrt()\" in expression \"sqrt()\": incorrect number of arguments passed, function sqrt takes 1 parameters but 0 were passed, sqrt( <-- HERE");
-        assert_parse_error!("sqrt(1,1)", "error while parsing token \"sqrt(1,1)\" in expression \"sqrt(1,1)\": incorrect number of arguments passed, function sqrt takes 1 parameters but 2 were passed, sqrt(1,1 <-- HERE");
-        assert_parse_error!("logab()", "error while parsing token \"logab()\" in expression \"logab()\": incorrect number of arguments passed, function logab takes 2 parameters but 0 were passed, logab( <-- HERE");
-        assert_parse_error!("logab(1)", "error while parsing token \"logab(1)\" in expression \"logab(1)\": incorrect number of arguments passed, function logab takes 2 parameters but 1 was passed, logab(1 <-- HERE");
-        assert_parse_error!("logab(1,1,1)", "error while parsing token \"logab(1,1,1)\" in expression \"logab(1,1,1)\": incorrect number of arguments passed, function logab takes 2 parameters but 3 were passed, logab(1,1,1 <-- HERE");
+    fn function_missing_closing_parenthesis() {
+        assert_parse_error!("sqrt(1", "error while parsing token \"sqrt(1\" in expression \"sqrt(1\": missing closing parenthesis, sqrt(1 <-- HERE");
     }
 }
@@ -0,0 +1,507 @@
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+use std::f64::consts::PI;
+
+/// Roots closer than this fraction of their magnitude are taken as a single multiple root, and
+/// real or imaginary parts smaller than it as zero. Multiple roots of polynomials whose coefficients
+/// aren't integers can only be found up to about the square root of the machine epsilon.
+const ROOT_TOLERANCE: f64 = 1e-7;
+
+/// Largest magnitude of the coefficients of a polynomial factored exactly into square-free
+/// factors, beyond which integers stored as `f64` may have been rounded.
+const MAX_EXACT_COEFFICIENT: f64 = 9007199254740992.;
+
+const MAX_ITERATIONS: usize = 1000;
+
+/// Number of Newton steps refining each root found.
+const POLISH_STEPS: usize = 5;
+
+/// Significant digits roots are rounded to when that doesn't make them worse roots.
+const ROUNDED_DIGITS: i32 = 12;
+
+/// Coefficients of a polynomial with rational coefficients, lowest degree first and without
+/// trailing zeros, so that the zero polynomial is empty.
+pub(crate) type Rational = Vec<BigRational>;
+
+fn trim(mut p: Rational) -> Rational {
+    while p.last().is_some_and(Zero::is_zero) {
+        p.pop();
+    }
+
+    p
+}
+
+fn derivative(p: &[BigRational]) -> Rational {
+    p.iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| c * BigRational::from_integer(i.into()))
+        .collect()
+}
+
+fn sub(a: &[BigRational], b: &[BigRational]) -> Rational {
+    let zero = BigRational::zero();
+
+    trim(
+        (0..a.len().max(b.len()))
+            .map(|i| a.get(i).unwrap_or(&zero) - b.get(i).unwrap_or(&zero))
+            .collect(),
+    )
+}
+
+/// Quotient and remainder of dividing `a` by the non-zero polynomial `b`.
+pub(crate) fn div_rem(a: &[BigRational], b: &[BigRational]) -> (Rational, Rational) {
+    if a.len() < b.len() {
+        return (Vec::new(), a.to_vec());
+    }
+
+    let mut r = a.to_vec();
+    let mut q = vec![BigRational::zero(); a.len() - b.len() + 1];
+    let lead = b.last().unwrap();
+
+    for i in (0..q.len()).rev() {
+        let c = &r[i + b.len() - 1] / lead;
+
+        for (j, bj) in b.iter().enumerate() {
+            r[i + j] -= &c * bj;
+        }
+
+        q[i] = c;
+    }
+
+    r.truncate(b.len() - 1);
+    (q, trim(r))
+}
+
+fn monic(p: Rational) -> Rational {
+    let lead = p.last().unwrap().clone();
+    p.into_iter().map(|c| c / &lead).collect()
+}
+
+pub(crate) fn gcd(a: &[BigRational], b: &[BigRational]) -> Rational {
+    let (mut a, mut b) = (a.to_vec(), b.to_vec());
+
+    while !b.is_empty() {
+        let r = div_rem(&a, &b).1;
+        a = b;
+        b = if r.is_empty() { r } else { monic(r) };
+    }
+
+    monic(a)
+}
+
+/// Splits `p`, of degree at least one, into square-free factors along with the multiplicity of
+/// their roots in `p`, using Yun's algorithm.
+fn square_free(p: &[BigRational]) -> Vec<(Rational, usize)> {
+    let d = derivative(p);
+    let a = gcd(p, &d);
+
+    let mut b = div_rem(p, &a).0;
+    let mut c = sub(&div_rem(&d, &a).0, &derivative(&b));
+    let mut factors = Vec::new();
+
+    for multiplicity in 1.. {
+        if b.len() <= 1 {
+            break;
+        }
+
+        let a = gcd(&b, &c);
+        let next = div_rem(&b, &a).0;
+        c = sub(&div_rem(&c, &a).0, &derivative(&next));
+        b = next;
+
+        if a.len() > 1 {
+            factors.push((a, multiplicity));
+        }
+    }
+
+    factors
+}
+
+/// Rounds `x` to [`ROUNDED_DIGITS`] significant digits.
+fn round(x: f64) -> f64 {
+    if x == 0. || !x.is_finite() {
+        return x;
+    }
+
+    let scale = 10f64.powi(ROUNDED_DIGITS - 1 - x.abs().log10().floor() as i32);
+    (x * scale).round() / scale
+}
+
+/// Value of `p` and of its derivative at `z`, by Horner's method.
+fn eval(p: &[f64], z: Complex64) -> (Complex64, Complex64) {
+    let mut value = Complex64::zero();
+    let mut derivative = Complex64::zero();
+
+    for c in p.iter().rev() {
+        derivative = derivative * z + value;
+        value = value * z + c;
+    }
+
+    (value, derivative)
+}
+
+/// Both roots of the monic polynomial `x^2 + b x + c`.
+fn quadratic(b: f64, c: f64) -> [Complex64; 2] {
+    let discriminant = b * b - 4. * c;
+
+    if discriminant < 0. {
+        let (re, im) = (-0.5 * b, 0.5 * (-discriminant).sqrt());
+        return [Complex64::new(re, -im), Complex64::new(re, im)];
+    } else if b == 0. {
+        let x = (-c).sqrt();
+        return [Complex64::new(-x, 0.), Complex64::new(x, 0.)];
+    }
+
+    // Avoids the cancellation of `-b + sqrt(discriminant)` when `b` is large
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+
+    match q == 0. {
+        true => [Complex64::zero(), Complex64::new(-b, 0.)],
+        false => [Complex64::new(q, 0.), Complex64::new(c / q, 0.)],
+    }
+}
+
+/// Roots of `x^2 + b x + c` for complex coefficients.
+fn complex_quadratic(b: Complex64, c: Complex64) -> [Complex64; 2] {
+    let root = (b * b - c * 4.).sqrt();
+    [(-b - root) * 0.5, (-b + root) * 0.5]
+}
+
+/// Roots of the monic polynomial `x^3 + a x^2 + b x + c`, by Cardano's formula when it has a
+/// single real root and by the trigonometric method when it has three.
+fn cubic(a: f64, b: f64, c: f64) -> Vec<Complex64> {
+    // x = t - a/3 turns it into t^3 + p t + q
+    let shift = a / 3.;
+    let p = b - a * shift;
+    let q = 2. * shift * shift * shift - b * shift + c;
+    let discriminant = (q / 2.).powi(2) + (p / 3.).powi(3);
+
+    let roots = if discriminant > 0. {
+        let root = discriminant.sqrt();
+        let (u, v) = ((-q / 2. + root).cbrt(), (-q / 2. - root).cbrt());
+        let (re, im) = (-(u + v) / 2., (u - v) * 3f64.sqrt() / 2.);
+
+        vec![
+            Complex64::new(u + v, 0.),
+            Complex64::new(re, -im),
+            Complex64::new(re, im),
+        ]
+    } else if p == 0. {
+        vec![Complex64::zero(); 3]
+    } else {
+        let r = 2. * (-p / 3.).sqrt();
+        let phi = ((3. * q / (2. * p)) * (-3. / p).sqrt())
+            .clamp(-1., 1.)
+            .acos()
+            / 3.;
+
+        (0..3)
+            .map(|k| Complex64::new(r * (phi - 2. * PI * k as f64 / 3.).cos(), 0.))
+            .collect()
+    };
+
+    roots.into_iter().map(|t| t - shift).collect()
+}
+
+/// Roots of the monic polynomial `x^4 + a x^3 + b x^2 + c x + d`, by Ferrari's method.
+fn quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<Complex64> {
+    // x = y - a/4 turns it into y^4 + p y^2 + q y + r
+    let shift = a / 4.;
+    let p = b - 6. * shift * shift;
+    let q = c - 2. * b * shift + 8. * shift.powi(3);
+    let r = d - c * shift + b * shift * shift - 3. * shift.powi(4);
+
+    // Unless q = 0, it factors into (y^2 + s y + α)(y^2 - s y + β), where m = s^2 / 2 is a
+    // positive root of the resolvent cubic
+    let m = cubic(p, p * p / 4. - r, -q * q / 8.)
+        .into_iter()
+        .filter(|m| m.im == 0.)
+        .map(|m| m.re)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    let roots = if q == 0. || m <= 0. {
+        // Biquadratic, a quadratic in y^2
+        quadratic(p, r)
+            .iter()
+            .flat_map(|z| [-z.sqrt(), z.sqrt()])
+            .collect::<Vec<Complex64>>()
+    } else {
+        let s = Complex64::new((2. * m).sqrt(), 0.);
+        let alpha = (s * s + p) * 0.5 - s.inv() * (q / 2.);
+        let beta = (s * s + p) * 0.5 + s.inv() * (q / 2.);
+
+        let mut roots = complex_quadratic(s, alpha).to_vec();
+        roots.extend(complex_quadratic(-s, beta));
+        roots
+    };
+
+    roots.into_iter().map(|y| y - shift).collect()
+}
+
+/// Roots of the monic polynomial `p` by the Durand–Kerner method, which refines guesses of every
+/// root at once.
+fn durand_kerner(p: &[f64]) -> Vec<Complex64> {
+    let n = p.len() - 1;
+
+    // Every root lies within the Cauchy bound, spread the initial guesses around a circle on it
+    let radius = 1. + p[..n].iter().fold(0f64, |max, c| max.max(c.abs()));
+    let mut z = (0..n)
+        .map(|k| Complex64::from_polar(radius, 2. * PI * k as f64 / n as f64 + 0.4))
+        .collect::<Vec<Complex64>>();
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut change = 0f64;
+
+        for k in 0..n {
+            let denominator = (0..n)
+                .filter(|j| *j != k)
+                .fold(Complex64::new(1., 0.), |product, j| product * (z[k] - z[j]));
+            let delta = eval(p, z[k]).0 / denominator;
+
+            if !delta.is_finite() {
+                continue;
+            }
+
+            z[k] -= delta;
+            change = change.max(delta.norm() / z[k].norm().max(1.));
+        }
+
+        if change <= f64::EPSILON {
+            break;
+        }
+    }
+
+    z
+}
+
+/// Every root of the square-free polynomial `p`, lowest degree first.
+fn factor_roots(p: &[f64]) -> Vec<Complex64> {
+    let lead = p[p.len() - 1];
+    let p = p.iter().map(|c| c / lead).collect::<Vec<f64>>();
+
+    let roots = match p.as_slice() {
+        [c, _] => vec![Complex64::new(-c, 0.)],
+        [c, b, _] => quadratic(*b, *c).to_vec(),
+        [d, c, b, a, _] => quartic(*a, *b, *c, *d),
+        [c, b, a, _] => cubic(*a, *b, *c),
+        _ => durand_kerner(&p),
+    };
+
+    roots
+        .into_iter()
+        .map(|mut z| {
+            for _ in 0..POLISH_STEPS {
+                let (value, derivative) = eval(&p, z);
+                let next = z - value / derivative;
+
+                if !next.is_finite() || eval(&p, next).0.norm() >= value.norm() {
+                    break;
+                }
+
+                z = next;
+            }
+
+            // Prefers a shorter value that is as good a root, so that exact roots print exactly
+            let rounded = Complex64::new(round(z.re), round(z.im));
+
+            match eval(&p, rounded).0.norm() <= eval(&p, z).0.norm() {
+                true => rounded,
+                false => z,
+            }
+        })
+        .collect()
+}
+
+/// Every root of the polynomial with coefficients `p`, lowest degree first and of degree at least
+/// one, along with its multiplicity. Real roots come first in increasing order, followed by the
+/// complex ones ordered by their real and then imaginary parts.
+pub(crate) fn roots(p: &[f64]) -> Vec<(Complex64, usize)> {
+    let zeros = p.iter().position(|c| *c != 0.).unwrap();
+    let p = &p[zeros..];
+
+    let mut roots = Vec::new();
+
+    if zeros > 0 {
+        roots.push((Complex64::zero(), zeros));
+    }
+
+    if p.len() == 1 {
+        return roots;
+    }
+
+    // The multiplicities of integer polynomials are found exactly, as floating point methods
+    // can only find multiple roots with a fraction of the precision of simple ones
+    let factors = if p
+        .iter()
+        .all(|c| c.fract() == 0. && c.abs() <= MAX_EXACT_COEFFICIENT)
+    {
+        let exact = p
+            .iter()
+            .map(|c| BigRational::from_float(*c).unwrap())
+            .collect::<Vec<BigRational>>();
+
+        square_free(&exact)
+            .into_iter()
+            .map(|(factor, multiplicity)| {
+                let factor = factor.iter().map(|c| c.to_f64().unwrap()).collect();
+                (factor, multiplicity)
+            })
+            .collect()
+    } else {
+        vec![(p.to_vec(), 1)]
+    };
+
+    for (factor, multiplicity) in factors {
+        for z in factor_roots(&factor) {
+            let clean = |x: f64| match x.abs() <= ROOT_TOLERANCE * z.norm() {
+                true => 0.,
+                false => x,
+            };
+            let z = Complex64::new(clean(z.re), clean(z.im));
+
+            roots.push((z, multiplicity));
+        }
+    }
+
+    roots.sort_by(|(a, _), (b, _)| {
+        (a.im != 0.)
+            .cmp(&(b.im != 0.))
+            .then(a.re.total_cmp(&b.re))
+            .then(a.im.total_cmp(&b.im))
+    });
+
+    let mut merged: Vec<(Complex64, usize)> = Vec::new();
+
+    for (z, multiplicity) in roots {
+        match merged.last_mut() {
+            Some((last, n)) if (z - *last).norm() <= ROOT_TOLERANCE * z.norm().max(last.norm()) => {
+                *n += multiplicity
+            }
+            _ => merged.push((z, multiplicity)),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(p: &[f64], expected: &[(f64, f64, usize)]) {
+        let roots = roots(p);
+
+        assert_eq!(roots.len(), expected.len(), "got {:?}", roots);
+
+        for ((z, n), (re, im, multiplicity)) in roots.iter().zip(expected) {
+            assert!(
+                (z.re - re).abs() < 1e-9 && (z.im - im).abs() < 1e-9 && n == multiplicity,
+                "expected {:?}, got {:?}",
+                expected,
+                roots
+            );
+        }
+    }
+
+    #[test]
+    fn polynomial_low_degree() {
+        assert_roots(
+            &[-6., 11., -6., 1.],
+            &[(1., 0., 1), (2., 0., 1), (3., 0., 1)],
+        );
+        assert_roots(&[1., 0., 1.], &[(0., -1., 1), (0., 1., 1)]);
+        assert_roots(&[1., -2., 1.], &[(1., 0., 2)]);
+        assert_roots(&[0., 0., 1.], &[(0., 0., 2)]);
+        assert_roots(
+            &[-1., 0., 0., 1.],
+            &[
+                (1., 0., 1),
+                (-0.5, -3f64.sqrt() / 2., 1),
+                (-0.5, 3f64.sqrt() / 2., 1),
+            ],
+        );
+        assert_roots(
+            &[-1., 0., 0., 0., 1.],
+            &[(-1., 0., 1), (1., 0., 1), (0., -1., 1), (0., 1., 1)],
+        );
+        // (x - 1)(x - 2)(x - 3)(x - 4), going through Ferrari's resolvent cubic
+        assert_roots(
+            &[24., -50., 35., -10., 1.],
+            &[(1., 0., 1), (2., 0., 1), (3., 0., 1), (4., 0., 1)],
+        );
+        // (x^2 + 1)(x^2 + 2x + 5)
+        assert_roots(
+            &[5., 2., 6., 2., 1.],
+            &[(-1., -2., 1), (-1., 2., 1), (0., -1., 1), (0., 1., 1)],
+        );
+    }
+
+    #[test]
+    fn polynomial_high_degree() {
+        // x^5 - x
+        assert_roots(
+            &[0., -1., 0., 0., 0., 1.],
+            &[
+                (-1., 0., 1),
+                (0., 0., 1),
+                (1., 0., 1),
+                (0., -1., 1),
+                (0., 1., 1),
+            ],
+        );
+        // (x - 1)^5
+        assert_roots(&[-1., 5., -10., 10., -5., 1.], &[(1., 0., 5)]);
+        // (x - 1)^2 (x + 2)^3 (x^2 + 1)
+        assert_roots(
+            &expand(&[(1., 2), (-2., 3)], &[1., 0., 1.]),
+            &[(-2., 0., 3), (1., 0., 2), (0., -1., 1), (0., 1., 1)],
+        );
+        // x^6 - 64
+        let s = 3f64.sqrt();
+        assert_roots(
+            &[-64., 0., 0., 0., 0., 0., 1.],
+            &[
+                (-2., 0., 1),
+                (2., 0., 1),
+                (-1., -s, 1),
+                (-1., s, 1),
+                (1., -s, 1),
+                (1., s, 1),
+            ],
+        );
+    }
+
+    /// Coefficients of `q` times `(x - r)^n` for every `(r, n)` in `roots`.
+    fn expand(roots: &[(f64, usize)], q: &[f64]) -> Vec<f64> {
+        roots.iter().fold(q.to_vec(), |p, (r, n)| {
+            (0..*n).fold(p, |p, _| {
+                let mut res = vec![0.; p.len() + 1];
+
+                for (i, c) in p.iter().enumerate() {
+                    res[i + 1] += c;
+                    res[i] -= r * c;
+                }
+
+                res
+            })
+        })
+    }
+
+    #[test]
+    fn polynomial_inexact_coefficients() {
+        // (x - 0.1)^2 can't be factored exactly, the nearly equal roots are merged instead
+        assert_roots(&[0.010000000000000002, -0.2, 1.], &[(0.1, 0., 2)]);
+        assert_roots(&[-2., 0., 0.5], &[(-2., 0., 1), (2., 0., 1)]);
+        assert_roots(
+            &[-std::f64::consts::PI, 1.],
+            &[(std::f64::consts::PI, 0., 1)],
+        );
+        // Small roots are only merged when close relative to their magnitude
+        let small = roots(&[-1e-20, 0., 1.]);
+        assert_eq!(small.len(), 2, "got {:?}", small);
+        assert!((small[0].0.re + 1e-10).abs() < 1e-20 && (small[1].0.re - 1e-10).abs() < 1e-20);
+        assert_eq!(roots(&[1e-20, -2e-10, 1.]).len(), 1);
+    }
+}
@@ -0,0 +1,1048 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::f64::consts::LN_10;
+use std::fmt;
+
+use crate::backend::Backend;
+use crate::eval::{self, CONSTANTS};
+use crate::functions::{Arity, FunctionRegistry};
+use crate::math;
+use crate::parser::INTEGER_DIVISION;
+
+/// Extra significant digits carried by intermediate results, so that rounding errors don't
+/// reach the printed ones.
+const GUARD_DIGITS: usize = 10;
+
+/// Largest integer exponent `^` computes by repeated squaring, larger ones go through `exp(y ln x)`.
+const MAX_SQUARING_EXPONENT: u32 = 10_000;
+
+/// Largest number whose factorial is computed as a product of integers.
+const MAX_INTEGER_FACTORIAL: u32 = 10_000;
+
+/// Largest magnitude (in digits) of the argument of `sin`, `cos` and `tan`, larger ones fall back
+/// to floating point as reducing them would need too many digits of pi.
+const MAX_TRIGONOMETRIC_MAGNITUDE: i64 = 1_000;
+
+fn pow10(n: i64) -> BigInt {
+    num_traits::pow(BigInt::from(10), n as usize)
+}
+
+fn digit_count(x: &BigInt) -> i64 {
+    if x.is_zero() {
+        0
+    } else {
+        x.magnitude().to_string().len() as i64
+    }
+}
+
+/// `a / b`, rounded half away from zero.
+fn div_round(a: &BigInt, b: &BigInt) -> BigInt {
+    let (q, r) = a.div_rem(b);
+
+    if r.abs() * 2 >= b.abs() {
+        if a.is_negative() == b.is_negative() {
+            q + 1
+        } else {
+            q - 1
+        }
+    } else {
+        q
+    }
+}
+
+/// A number of the form `mantissa * 10^exponent`, with no trailing zeros in the mantissa.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Decimal {
+    mantissa: BigInt,
+    exponent: i64,
+}
+
+impl Decimal {
+    fn new(mut mantissa: BigInt, mut exponent: i64) -> Decimal {
+        if mantissa.is_zero() {
+            return Decimal::zero();
+        }
+
+        let ten = BigInt::from(10);
+
+        loop {
+            let (q, r) = mantissa.div_rem(&ten);
+
+            if !r.is_zero() {
+                break;
+            }
+
+            mantissa = q;
+            exponent += 1;
+        }
+
+        Decimal { mantissa, exponent }
+    }
+
+    fn zero() -> Decimal {
+        Decimal {
+            mantissa: BigInt::zero(),
+            exponent: 0,
+        }
+    }
+
+    fn one() -> Decimal {
+        Decimal::from_int(1)
+    }
+
+    fn from_int(x: impl Into<BigInt>) -> Decimal {
+        Decimal::new(x.into(), 0)
+    }
+
+    /// The number `x / 10^scale`.
+    fn from_fixed(x: BigInt, scale: i64) -> Decimal {
+        Decimal::new(x, -scale)
+    }
+
+    fn from_f64(x: f64) -> Decimal {
+        parse_decimal(&format!("{:e}", x)).unwrap()
+    }
+
+    /// Rounds to `digits` significant digits, half away from zero.
+    fn round(&self, digits: usize) -> Decimal {
+        let excess = digit_count(&self.mantissa) - digits as i64;
+
+        if excess <= 0 {
+            return self.clone();
+        }
+
+        Decimal::new(
+            div_round(&self.mantissa, &pow10(excess)),
+            self.exponent + excess,
+        )
+    }
+
+    /// `x * 10^scale`, rounded to an integer.
+    fn to_fixed(&self, scale: i64) -> BigInt {
+        let shift = self.exponent + scale;
+
+        if shift >= 0 {
+            &self.mantissa * pow10(shift)
+        } else {
+            div_round(&self.mantissa, &pow10(-shift))
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        format!("{}e{}", self.mantissa, self.exponent)
+            .parse()
+            .unwrap()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+
+    fn is_integer(&self) -> bool {
+        self.exponent >= 0
+    }
+
+    /// The `n` such that `10^(n-1) <= |x| < 10^n`.
+    fn magnitude(&self) -> i64 {
+        digit_count(&self.mantissa) + self.exponent
+    }
+
+    fn neg(&self) -> Decimal {
+        Decimal {
+            mantissa: -&self.mantissa,
+            exponent: self.exponent,
+        }
+    }
+
+    fn abs(&self) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.abs(),
+            exponent: self.exponent,
+        }
+    }
+
+    /// Both mantissas scaled to the smallest of the two exponents.
+    fn align(&self, other: &Decimal) -> (BigInt, BigInt, i64) {
+        let exponent = self.exponent.min(other.exponent);
+
+        (
+            &self.mantissa * pow10(self.exponent - exponent),
+            &other.mantissa * pow10(other.exponent - exponent),
+            exponent,
+        )
+    }
+
+    fn add(&self, other: &Decimal, digits: usize) -> Decimal {
+        if self.is_zero() {
+            return other.round(digits);
+        } else if other.is_zero() {
+            return self.round(digits);
+        }
+
+        // Don't align numbers whose digits don't overlap, the smaller one can't affect the result
+        let gap = self.magnitude() - other.magnitude();
+
+        if gap > digits as i64 + 2 {
+            return self.round(digits);
+        } else if -gap > digits as i64 + 2 {
+            return other.round(digits);
+        }
+
+        let (a, b, exponent) = self.align(other);
+        Decimal::new(a + b, exponent).round(digits)
+    }
+
+    fn sub(&self, other: &Decimal, digits: usize) -> Decimal {
+        self.add(&other.neg(), digits)
+    }
+
+    fn mul(&self, other: &Decimal, digits: usize) -> Decimal {
+        Decimal::new(
+            &self.mantissa * &other.mantissa,
+            self.exponent + other.exponent,
+        )
+        .round(digits)
+    }
+
+    /// `x / other`, which must not be zero.
+    fn div(&self, other: &Decimal, digits: usize) -> Decimal {
+        let shift =
+            (digits as i64 + digit_count(&other.mantissa) - digit_count(&self.mantissa) + 2).max(0);
+
+        Decimal::new(
+            div_round(&(&self.mantissa * pow10(shift)), &other.mantissa),
+            self.exponent - other.exponent - shift,
+        )
+        .round(digits)
+    }
+
+    /// Remainder of the truncated division, with the sign of `x` like `f64`'s `%`.
+    fn rem(&self, other: &Decimal, digits: usize) -> Decimal {
+        let (a, b, exponent) = self.align(other);
+        Decimal::new(a % b, exponent).round(digits)
+    }
+
+    /// Truncated quotient of `x / other`, which must not be zero.
+    fn quotient(&self, other: &Decimal, digits: usize) -> Decimal {
+        let (a, b, _) = self.align(other);
+        Decimal::new(a / b, 0).round(digits)
+    }
+
+    /// Square root of `x`, which must not be negative.
+    fn sqrt(&self, digits: usize) -> Decimal {
+        let mut shift = (2 * (digits as i64 + 2) - digit_count(&self.mantissa)).max(0);
+
+        if (self.exponent - shift) % 2 != 0 {
+            shift += 1;
+        }
+
+        Decimal::new(
+            (&self.mantissa * pow10(shift)).sqrt(),
+            (self.exponent - shift) / 2,
+        )
+        .round(digits)
+    }
+
+    fn powi(&self, mut n: u32, digits: usize) -> Decimal {
+        let mut base = self.clone();
+        let mut res = Decimal::one();
+
+        while n > 0 {
+            if n % 2 == 1 {
+                res = res.mul(&base, digits);
+            }
+
+            base = base.mul(&base, digits);
+            n /= 2;
+        }
+
+        res
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        let (a, b, _) = self.align(other);
+        a.partial_cmp(&b)
+    }
+}
+
+/// Parses a decimal literal such as `1.25e-3` exactly.
+fn parse_decimal(text: &str) -> Option<Decimal> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+        None => (text, 0),
+    };
+
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int, frac).parse::<BigInt>().ok()?;
+
+    Some(Decimal::new(
+        digits,
+        exponent.checked_sub(frac.len() as i64)?,
+    ))
+}
+
+// Fixed point helpers, `x` standing for `x / 10^scale`.
+
+/// `atan(1/n)`.
+fn atan_inv_fixed(n: u32, scale: i64) -> BigInt {
+    let mut term = pow10(scale) / n;
+    let mut sum = term.clone();
+    let mut k = 1u32;
+
+    while !term.is_zero() {
+        term = -term / (n * n);
+        sum += &term / (2 * k + 1);
+        k += 1;
+    }
+
+    sum
+}
+
+fn pi_fixed(scale: i64) -> BigInt {
+    // Machin's formula, pi = 16 atan(1/5) - 4 atan(1/239)
+    let pi = atan_inv_fixed(5, scale + 5) * 16 - atan_inv_fixed(239, scale + 5) * 4;
+    div_round(&pi, &pow10(5))
+}
+
+/// `exp(x)`, only meant for `|x|` not much larger than 1.
+fn exp_fixed(x: &BigInt, scale: i64) -> BigInt {
+    let one = pow10(scale);
+    let mut term = one.clone();
+    let mut sum = one.clone();
+    let mut n = 1u32;
+
+    while !term.is_zero() {
+        term = term * x / &one / n;
+        sum += &term;
+        n += 1;
+    }
+
+    sum
+}
+
+/// `ln(x)`, only meant for `x` between 0.1 and 10.
+fn ln_fixed(x: &BigInt, scale: i64) -> BigInt {
+    let one = pow10(scale);
+    let threshold = &one / 100;
+    let mut x = x.clone();
+    let mut halvings = 0;
+
+    // ln x = 2^k ln(x^(1/2^k)), bringing x close enough to 1 for the series to converge quickly
+    while (&x - &one).abs() > threshold {
+        x = (&x * &one).sqrt();
+        halvings += 1;
+    }
+
+    // ln x = 2 atanh((x - 1) / (x + 1))
+    let z = (&x - &one) * &one / (&x + &one);
+    let z2 = &z * &z / &one;
+    let mut term = z.clone();
+    let mut sum = z;
+    let mut n = 1u32;
+
+    while !term.is_zero() {
+        term = term * &z2 / &one;
+        sum += &term / (2 * n + 1);
+        n += 1;
+    }
+
+    sum * 2 * num_traits::pow(BigInt::from(2), halvings)
+}
+
+fn ln10_fixed(scale: i64) -> BigInt {
+    ln_fixed(&(pow10(scale) * 10), scale)
+}
+
+/// `atan(x)`, only meant for `|x| <= 1`.
+fn atan_fixed(x: &BigInt, scale: i64) -> BigInt {
+    let one = pow10(scale);
+    let threshold = &one / 10;
+    let mut x = x.clone();
+    let mut halvings = 0;
+
+    // atan x = 2 atan(x / (1 + sqrt(1 + x^2)))
+    while x.abs() > threshold {
+        x = &x * &one / (&one + (&one * &one + &x * &x).sqrt());
+        halvings += 1;
+    }
+
+    let x2 = &x * &x / &one;
+    let mut term = x.clone();
+    let mut sum = x;
+    let mut n = 1u32;
+
+    while !term.is_zero() {
+        term = -term * &x2 / &one;
+        sum += &term / (2 * n + 1);
+        n += 1;
+    }
+
+    sum * num_traits::pow(BigInt::from(2), halvings)
+}
+
+// Functions over decimals, returning `None` outside of their domain (or when the result overflows)
+// so that the caller can fall back to `f64` and get the right infinity or NaN.
+
+fn pi(digits: usize) -> Decimal {
+    let scale = digits as i64 + 2;
+    Decimal::from_fixed(pi_fixed(scale), scale).round(digits)
+}
+
+fn exp(x: &Decimal, digits: usize) -> Option<Decimal> {
+    if x.magnitude() > 18 {
+        return None;
+    }
+
+    // exp x = 10^n exp(x - n ln 10), with |x - n ln 10| <= ln(10) / 2
+    let n = (x.to_f64() / LN_10).round() as i64;
+    let scale = (digits + GUARD_DIGITS) as i64 + digit_count(&BigInt::from(n));
+    let r = x.to_fixed(scale) - ln10_fixed(scale) * n;
+
+    Some(Decimal::new(exp_fixed(&r, scale), n - scale).round(digits))
+}
+
+fn ln(x: &Decimal, digits: usize) -> Option<Decimal> {
+    if x.is_negative() || x.is_zero() {
+        return None;
+    }
+
+    // ln x = ln(x / 10^e) + e ln 10, with 1 <= x / 10^e < 10
+    let e = x.magnitude() - 1;
+
+    // Results close to zero need more digits, as the leading ones cancel out
+    let near_one = x.sub(&Decimal::one(), 2 * digits);
+    let extra = if near_one.is_zero() {
+        return Some(Decimal::zero());
+    } else {
+        (-near_one.magnitude()).max(0)
+    };
+
+    let scale = (digits + GUARD_DIGITS) as i64 + extra + digit_count(&BigInt::from(e));
+    let mut res = ln_fixed(&x.to_fixed(scale - e), scale);
+
+    if e != 0 {
+        res += ln10_fixed(scale) * e;
+    }
+
+    Some(Decimal::from_fixed(res, scale).round(digits))
+}
+
+fn sin_cos(x: &Decimal, digits: usize) -> Option<(Decimal, Decimal)> {
+    let magnitude = x.magnitude();
+
+    if magnitude > MAX_TRIGONOMETRIC_MAGNITUDE {
+        return None;
+    }
+
+    let scale = (digits + GUARD_DIGITS) as i64 + magnitude.abs();
+    let one = pow10(scale);
+
+    // Reduce x to [-pi, pi]
+    let x = x.to_fixed(scale);
+    let two_pi = pi_fixed(scale) * 2;
+    let r = &x - div_round(&x, &two_pi) * &two_pi;
+    let r2 = &r * &r / &one;
+
+    let mut sin_term = r.clone();
+    let mut sin = r;
+    let mut cos_term = one.clone();
+    let mut cos = one.clone();
+    let mut n = 1u32;
+
+    while !sin_term.is_zero() || !cos_term.is_zero() {
+        cos_term = -cos_term * &r2 / &one / ((2 * n - 1) * (2 * n));
+        sin_term = -sin_term * &r2 / &one / ((2 * n) * (2 * n + 1));
+        cos += &cos_term;
+        sin += &sin_term;
+        n += 1;
+    }
+
+    Some((
+        Decimal::from_fixed(sin, scale).round(digits),
+        Decimal::from_fixed(cos, scale).round(digits),
+    ))
+}
+
+fn atan(x: &Decimal, digits: usize) -> Decimal {
+    if x.is_zero() {
+        return Decimal::zero();
+    }
+
+    let magnitude = x.magnitude();
+
+    let (res, scale) = if magnitude > 0 {
+        // atan x = ±pi/2 - atan(1/x) for |x| >= 1
+        let scale = (digits + GUARD_DIGITS) as i64;
+        let half_pi: BigInt = pi_fixed(scale) / 2;
+        let inv = Decimal::one().div(x, digits + GUARD_DIGITS);
+        let atan_inv = atan_fixed(&inv.to_fixed(scale), scale);
+
+        if x.is_negative() {
+            (-half_pi - atan_inv, scale)
+        } else {
+            (half_pi - atan_inv, scale)
+        }
+    } else {
+        let scale = (digits + GUARD_DIGITS) as i64 - magnitude;
+        (atan_fixed(&x.to_fixed(scale), scale), scale)
+    };
+
+    Decimal::from_fixed(res, scale).round(digits)
+}
+
+fn asin(x: &Decimal, digits: usize) -> Option<Decimal> {
+    let one = Decimal::one();
+    let working = digits + GUARD_DIGITS;
+
+    match x.abs().partial_cmp(&one)? {
+        Ordering::Greater => None,
+        Ordering::Equal => {
+            let half_pi = pi(digits).div(&Decimal::from_int(2), digits);
+            Some(if x.is_negative() {
+                half_pi.neg()
+            } else {
+                half_pi
+            })
+        }
+        Ordering::Less => {
+            // asin x = atan(x / sqrt((1 - x) (1 + x)))
+            let cos = one
+                .sub(x, working)
+                .mul(&one.add(x, working), working)
+                .sqrt(working);
+
+            Some(atan(&x.div(&cos, working), digits))
+        }
+    }
+}
+
+fn acos(x: &Decimal, digits: usize) -> Option<Decimal> {
+    let one = Decimal::one();
+    let working = digits + GUARD_DIGITS;
+
+    if x.abs() > one {
+        return None;
+    } else if *x == one.neg() {
+        return Some(pi(digits));
+    }
+
+    // acos x = 2 atan(sqrt((1 - x) / (1 + x)))
+    let tan = one
+        .sub(x, working)
+        .div(&one.add(x, working), working)
+        .sqrt(working);
+
+    Some(atan(&tan, working).mul(&Decimal::from_int(2), digits))
+}
+
+fn pow(x: &Decimal, y: &Decimal, digits: usize) -> Option<Decimal> {
+    if x.is_zero() {
+        return None;
+    }
+
+    let working = digits + GUARD_DIGITS;
+
+    if y.is_integer() {
+        let n = y.to_fixed(0);
+        let odd = n.is_odd();
+
+        match n.abs().to_u32() {
+            Some(n) if n <= MAX_SQUARING_EXPONENT => {
+                let working = working + digit_count(&n.into()) as usize;
+                let res = x.powi(n, working);
+
+                return Some(if y.is_negative() {
+                    Decimal::one().div(&res, digits)
+                } else {
+                    res.round(digits)
+                });
+            }
+            _ => {
+                // |x|^y, negated for odd exponents of negative numbers
+                let res = exp(&y.mul(&ln(&x.abs(), working * 2)?, working * 2), digits)?;
+                return Some(if x.is_negative() && odd {
+                    res.neg()
+                } else {
+                    res
+                });
+            }
+        }
+    }
+
+    if x.is_negative() {
+        return None;
+    }
+
+    // x^y = exp(y ln x), whose argument needs more digits the larger it is
+    let working = working + y.magnitude().max(0) as usize + 3;
+    exp(&y.mul(&ln(x, working)?, working), digits)
+}
+
+/// Extends `numbers`, holding the first Bernoulli numbers `B_0, B_1, ...`, up to `B_n`.
+fn bernoulli(numbers: &mut Vec<BigRational>, n: usize) {
+    if numbers.is_empty() {
+        numbers.push(BigRational::one());
+    }
+
+    // B_m = -1/(m + 1) sum_{k < m} C(m + 1, k) B_k
+    for m in numbers.len()..=n {
+        if m > 1 && m % 2 == 1 {
+            numbers.push(BigRational::zero());
+            continue;
+        }
+
+        let mut binomial = BigInt::one();
+        let mut sum = BigRational::zero();
+
+        for (k, b) in numbers.iter().enumerate() {
+            if !b.is_zero() {
+                sum += b * BigRational::from_integer(binomial.clone());
+            }
+
+            binomial = binomial * (m + 1 - k) / (k + 1);
+        }
+
+        numbers.push(-sum / BigRational::from_integer((m + 1).into()));
+    }
+}
+
+/// `ln(gamma(w))` from Stirling's series, only meant for `w` with about as many integer digits as
+/// `digits`.
+fn ln_gamma_stirling(w: &Decimal, digits: usize) -> Option<Decimal> {
+    let working = digits + GUARD_DIGITS + w.magnitude().max(0) as usize;
+    let half = Decimal::new(5.into(), -1);
+
+    // (w - 1/2) ln w - w + ln(2 pi) / 2
+    let two_pi = pi(working).mul(&Decimal::from_int(2), working);
+    let mut res = w
+        .sub(&half, working)
+        .mul(&ln(w, working)?, working)
+        .sub(w, working)
+        .add(&ln(&two_pi, working)?.mul(&half, working), working);
+
+    // + sum_k B_2k / (2k (2k - 1) w^(2k - 1))
+    let mut numbers = Vec::new();
+    let w2 = w.mul(w, working);
+    let mut power = w.clone();
+    let mut k = 1;
+
+    loop {
+        bernoulli(&mut numbers, 2 * k);
+        let b = &numbers[2 * k];
+        let coefficient = Decimal::from_int(b.numer().clone()).div(
+            &Decimal::from_int(b.denom() * (2 * k) * (2 * k - 1)),
+            working,
+        );
+        let term = coefficient.div(&power, working);
+
+        if term.is_zero() || term.magnitude() < res.magnitude() - working as i64 {
+            break;
+        }
+
+        res = res.add(&term, working);
+        power = power.mul(&w2, working);
+        k += 1;
+    }
+
+    Some(res.round(digits))
+}
+
+/// `x!`, only defined for `x >= 0`.
+fn factorial(x: &Decimal, digits: usize) -> Option<Decimal> {
+    if x.is_negative() {
+        return None;
+    }
+
+    let working = digits + GUARD_DIGITS;
+
+    if x.is_integer() {
+        if let Some(n) = x
+            .to_fixed(0)
+            .to_u32()
+            .filter(|n| *n <= MAX_INTEGER_FACTORIAL)
+        {
+            let res = (2..=n).fold(BigInt::one(), |acc, i| acc * i);
+            return Some(Decimal::from_int(res).round(digits));
+        }
+    }
+
+    // x! = gamma(x + 1) = gamma(x + 1 + n) / ((x + 1) (x + 2) ... (x + n)), with n large enough
+    // for Stirling's series to converge to the requested digits
+    let z = x.add(&Decimal::one(), working);
+    let shift = (working as i64 - z.to_f64() as i64).max(0);
+    let working = working + digit_count(&shift.into()) as usize;
+    let mut product = Decimal::one();
+
+    for i in 0..shift {
+        product = product.mul(&z.add(&Decimal::from_int(i), working), working);
+    }
+
+    let w = z.add(&Decimal::from_int(shift), working);
+    let res = exp(&ln_gamma_stirling(&w, working)?, working)?;
+
+    Some(res.div(&product, digits))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Repr {
+    Finite(Decimal),
+    /// Infinities and NaN, which are handled as `f64`.
+    NonFinite(f64),
+}
+
+/// Result of evaluating an expression with arbitrary precision.
+///
+/// Formatting with a precision, as in `format!("{:.50}", x)`, rounds to that many significant
+/// digits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreciseValue {
+    repr: Repr,
+}
+
+impl PreciseValue {
+    fn finite(x: Decimal) -> PreciseValue {
+        PreciseValue {
+            repr: Repr::Finite(x),
+        }
+    }
+
+    fn from_f64(x: f64) -> PreciseValue {
+        PreciseValue {
+            repr: if x.is_finite() {
+                Repr::Finite(Decimal::from_f64(x))
+            } else {
+                Repr::NonFinite(x)
+            },
+        }
+    }
+
+    fn as_decimal(&self) -> Option<&Decimal> {
+        match &self.repr {
+            Repr::Finite(x) => Some(x),
+            Repr::NonFinite(_) => None,
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.as_decimal().is_some()
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match &self.repr {
+            Repr::Finite(x) => x.to_f64(),
+            Repr::NonFinite(x) => *x,
+        }
+    }
+}
+
+impl fmt::Display for PreciseValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let x = match &self.repr {
+            Repr::Finite(x) => x,
+            Repr::NonFinite(x) => return write!(f, "{}", x),
+        };
+
+        let x = match f.precision() {
+            Some(digits) => x.round(digits.max(1)),
+            None => x.clone(),
+        };
+
+        if x.is_zero() {
+            return write!(f, "0");
+        }
+
+        let sign = if x.is_negative() { "-" } else { "" };
+        let digits = x.mantissa.magnitude().to_string();
+        let len = digits.len() as i64;
+
+        // Position of the decimal point, relative to the first digit
+        let point = len + x.exponent;
+        let max_point = (f.precision().unwrap_or(0) as i64).max(21);
+
+        if point > max_point || point < -5 {
+            let (first, rest) = digits.split_at(1);
+            let rest = if rest.is_empty() {
+                String::new()
+            } else {
+                format!(".{}", rest)
+            };
+
+            write!(f, "{}{}{}e{}", sign, first, rest, point - 1)
+        } else if point <= 0 {
+            write!(f, "{}0.{}{}", sign, "0".repeat(-point as usize), digits)
+        } else if point >= len {
+            write!(
+                f,
+                "{}{}{}",
+                sign,
+                digits,
+                "0".repeat((point - len) as usize)
+            )
+        } else {
+            let (int, frac) = digits.split_at(point as usize);
+            write!(f, "{}{}.{}", sign, int, frac)
+        }
+    }
+}
+
+/// Evaluates expressions with a given number of significant digits. Values keep a few more digits
+/// than requested, which are dropped when formatting them with that precision.
+#[derive(Debug, Clone)]
+pub struct PreciseBackend {
+    digits: usize,
+    functions: FunctionRegistry,
+}
+
+impl PreciseBackend {
+    pub fn new(digits: usize) -> PreciseBackend {
+        PreciseBackend {
+            digits,
+            functions: FunctionRegistry::builtin(),
+        }
+    }
+
+    /// Number of significant digits results are computed to.
+    pub fn digits(&self) -> usize {
+        self.digits
+    }
+
+    fn working(&self) -> usize {
+        self.digits + GUARD_DIGITS
+    }
+
+    fn exact_call(&self, name: &str, args: &[&Decimal]) -> Option<Decimal> {
+        let working = self.working();
+
+        match (name, args) {
+            ("cos", [x]) => sin_cos(x, working).map(|(_, cos)| cos),
+            ("sin", [x]) => sin_cos(x, working).map(|(sin, _)| sin),
+            ("tan", [x]) => sin_cos(x, working)
+                .filter(|(_, cos)| !cos.is_zero())
+                .map(|(sin, cos)| sin.div(&cos, working)),
+            ("acos", [x]) => acos(x, working),
+            ("asin", [x]) => asin(x, working),
+            ("atan", [x]) => Some(atan(x, working)),
+            ("ln", [x]) => ln(x, working),
+            ("sqrt", [x]) if !x.is_negative() => Some(x.sqrt(working)),
+            ("logab", [a, b]) => {
+                let ln_a = ln(a, working + GUARD_DIGITS).filter(|x| !x.is_zero())?;
+                Some(ln(b, working + GUARD_DIGITS)?.div(&ln_a, working))
+            }
+            ("nroot", [n, x]) if !n.is_zero() => {
+                pow(x, &Decimal::one().div(n, working + GUARD_DIGITS), working)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Backend for PreciseBackend {
+    type Value = PreciseValue;
+
+    fn literal(&self, text: &str) -> Result<PreciseValue, String> {
+        parse_decimal(text)
+            .map(|x| PreciseValue::finite(x.round(self.working())))
+            .ok_or_else(|| "unkown token".to_owned())
+    }
+
+    fn constant(&self, name: &str) -> Option<PreciseValue> {
+        match name {
+            "pi" => Some(PreciseValue::finite(pi(self.working()))),
+            "e" => exp(&Decimal::one(), self.working()).map(PreciseValue::finite),
+            _ => CONSTANTS.get(name).map(|x| PreciseValue::from_f64(*x)),
+        }
+    }
+
+    fn unary(&self, op: char, operand: PreciseValue) -> Result<PreciseValue, String> {
+        let res = match (op, operand.as_decimal()) {
+            ('+', _) => return Ok(operand),
+            ('-', Some(x)) => Some(x.neg()),
+            ('!', Some(x)) => factorial(x, self.working()),
+            _ => None,
+        };
+
+        Ok(match res {
+            Some(x) => PreciseValue::finite(x),
+            None if op == '!' => PreciseValue::from_f64(math::fact(operand.to_f64())),
+            None => PreciseValue::from_f64(-operand.to_f64()),
+        })
+    }
+
+    fn binary(
+        &self,
+        op: char,
+        lhs: PreciseValue,
+        rhs: PreciseValue,
+    ) -> Result<PreciseValue, String> {
+        let working = self.working();
+
+        let res = match (lhs.as_decimal(), rhs.as_decimal()) {
+            (Some(a), Some(b)) => match op {
+                '+' => Some(a.add(b, working)),
+                '-' => Some(a.sub(b, working)),
+                '*' => Some(a.mul(b, working)),
+                '/' if !b.is_zero() => Some(a.div(b, working)),
+                '%' if !b.is_zero() => Some(a.rem(b, working)),
+                INTEGER_DIVISION if !b.is_zero() => Some(a.quotient(b, working)),
+                '^' => pow(a, b, working),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        Ok(match res {
+            Some(x) => PreciseValue::finite(x),
+            None => PreciseValue::from_f64(eval::binary(op, lhs.to_f64(), rhs.to_f64())),
+        })
+    }
+
+    fn arity(&self, name: &str) -> Option<Arity> {
+        self.functions
+            .get(name)
+            .filter(|f| f.native().is_some())
+            .map(|f| f.arity())
+    }
+
+    fn call(&self, name: &str, args: &[PreciseValue]) -> Result<PreciseValue, String> {
+        let decimals = args
+            .iter()
+            .map(PreciseValue::as_decimal)
+            .collect::<Option<Vec<&Decimal>>>();
+
+        if let Some(x) = decimals.and_then(|args| self.exact_call(name, &args)) {
+            return Ok(PreciseValue::finite(x));
+        }
+
+        let args = args.iter().map(PreciseValue::to_f64).collect::<Vec<f64>>();
+        let native = self.functions.get(name).and_then(|f| f.native()).unwrap();
+
+        native(&args).map(PreciseValue::from_f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Session;
+
+    fn precise(src: &str, digits: usize) -> String {
+        let res = Session::new(PreciseBackend::new(digits))
+            .evaluate(src)
+            .unwrap();
+
+        format!("{:.*}", digits, res)
+    }
+
+    #[test]
+    fn precise_constants() {
+        assert_eq!(
+            precise("pi", 50),
+            "3.1415926535897932384626433832795028841971693993751"
+        );
+        assert_eq!(
+            precise("e", 50),
+            "2.7182818284590452353602874713526624977572470937"
+        );
+        assert_eq!(precise("inf", 50), "inf");
+        assert_eq!(precise("nan", 50), "NaN");
+    }
+
+    #[test]
+    fn precise_operators() {
+        assert_eq!(precise("0.1 + 0.2", 50), "0.3");
+        assert_eq!(precise("1/3", 30), "0.333333333333333333333333333333");
+        assert_eq!(precise("2/3", 10), "0.6666666667");
+        assert_eq!(precise("-2^2", 50), "-4");
+        assert_eq!(precise("2^-2", 50), "0.25");
+        assert_eq!(precise("(7.5) % 2", 50), "1.5");
+        assert_eq!(precise("-7 % 3", 50), "-1");
+        assert_eq!(precise("(7.5) // 2", 50), "3");
+        assert_eq!(precise("25!", 50), "15511210043330985984000000");
+        assert_eq!(precise("1/0", 50), "inf");
+        assert_eq!(precise("1/inf", 50), "0");
+    }
+
+    #[test]
+    fn precise_powers() {
+        assert_eq!(
+            precise("2^0.5", 50),
+            "1.4142135623730950488016887242096980785696718753769"
+        );
+        assert_eq!(precise("2^200", 10), "1.606938044e60");
+        assert_eq!(precise("0.5^20", 50), "9.5367431640625e-7");
+        assert_eq!(precise("10^-30", 5), "1e-30");
+        assert_eq!(precise("(-2)^3", 50), "-8");
+        assert_eq!(precise("(-2)^0.5", 50), "NaN");
+    }
+
+    #[test]
+    fn precise_functions() {
+        assert_eq!(
+            precise("sqrt(2)", 50),
+            "1.4142135623730950488016887242096980785696718753769"
+        );
+        assert_eq!(
+            precise("ln(2)", 50),
+            "0.69314718055994530941723212145817656807550013436026"
+        );
+        assert_eq!(
+            precise("sin(1)", 50),
+            "0.84147098480789650665250232163029899962256306079837"
+        );
+        assert_eq!(
+            precise("cos(1)", 50),
+            "0.54030230586813971740093660744297660373231042061792"
+        );
+        assert_eq!(
+            precise("tan(1)", 50),
+            "1.5574077246549022305069748074583601730872507723815"
+        );
+        assert_eq!(
+            precise("atan(1) * 4", 50),
+            "3.1415926535897932384626433832795028841971693993751"
+        );
+        assert_eq!(
+            precise("asin(0.5)", 50),
+            "0.52359877559829887307710723054658381403286156656252"
+        );
+        assert_eq!(
+            precise("acos(0.5)", 50),
+            "1.047197551196597746154214461093167628065723133125"
+        );
+        assert_eq!(precise("logab(2, 1024)", 50), "10");
+        assert_eq!(precise("nroot(3, 27)", 50), "3");
+    }
+
+    #[test]
+    fn precise_small_arguments() {
+        assert_eq!(precise("sin(1e-30)", 30), "1e-30");
+        assert_eq!(precise("ln(1 + 3e-15)", 20), "2.9999999999999955e-15");
+    }
+
+    #[test]
+    fn precise_factorial() {
+        assert_eq!(
+            precise("0.5!", 50),
+            "0.88622692545275801364908374167057259139877472806119"
+        );
+        assert_eq!(precise("3.5!", 30), "11.6317283965674489291442241094");
+        assert_eq!(precise("(-1)!", 50), "NaN");
+    }
+
+    #[test]
+    fn precise_domain() {
+        assert_eq!(precise("sqrt(-1)", 50), "NaN");
+        assert_eq!(precise("ln(0)", 50), "-inf");
+        assert_eq!(precise("asin(2)", 50), "NaN");
+        assert_eq!(precise("acos(-1)", 20), "3.1415926535897932385");
+    }
+}
@@ -0,0 +1,337 @@
+use crate::context::Context;
+use crate::errors::*;
+use crate::eval::Evaluator;
+use crate::lexer::Span;
+use crate::parser::parse_expression;
+
+/// Relative and absolute error below which an integral is considered accurate.
+const RELATIVE_TOLERANCE: f64 = 1e-10;
+const ABSOLUTE_TOLERANCE: f64 = 1e-12;
+
+/// Number of pieces the interval may be split into before giving up on reaching the tolerance.
+const MAX_SEGMENTS: usize = 500;
+
+/// Nodes of the 15-point Kronrod rule on [-1, 1], the odd ones being the nodes of the 7-point
+/// Gauss rule it extends. Only the non-negative half is listed, the rules being symmetric.
+const KRONROD_NODES: [f64; 8] = [
+    0.9914553711208126,
+    0.9491079123427585,
+    0.8648644233597691,
+    0.7415311855993945,
+    0.5860872354676911,
+    0.4058451513773972,
+    0.20778495500789848,
+    0.,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022935322010529224,
+    0.06309209262997856,
+    0.10479001032225019,
+    0.14065325971552592,
+    0.1690047266392679,
+    0.19035057806478542,
+    0.20443294007529889,
+    0.20948214108472782,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.1294849661688697,
+    0.27970539148927664,
+    0.3818300505051189,
+    0.4179591836734694,
+];
+
+/// Value of a definite integral, along with an estimate of its absolute error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Integral {
+    pub value: f64,
+    pub error: f64,
+}
+
+impl Integral {
+    /// Whether the estimated error is within the tolerance the integration aims for.
+    pub fn is_accurate(&self) -> bool {
+        self.error <= ABSOLUTE_TOLERANCE.max(RELATIVE_TOLERANCE * self.value.abs())
+    }
+}
+
+/// A piece of the interval of integration, along with the estimate of the integral over it.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    a: f64,
+    b: f64,
+    integral: Integral,
+}
+
+/// Integrates `f`, a function of the variable of integration, with adaptive Gauss-Kronrod
+/// quadrature.
+pub(crate) struct Quadrature<'a, F> {
+    src: &'a str,
+    span: Span,
+    f: F,
+}
+
+impl<'a, F: FnMut(f64) -> Result<f64, SyntaxError>> Quadrature<'a, F> {
+    /// `span` is the part of `src` being integrated, and is only used to report errors.
+    pub fn new(src: &'a str, span: Span, f: F) -> Quadrature<'a, F> {
+        Quadrature { src, span, f }
+    }
+
+    fn error(&self, msg: String) -> SyntaxError {
+        SyntaxError::at(self.src, self.span, msg)
+    }
+
+    /// Integrates from `a` to `b`, either of which may be infinite. Infinite bounds are mapped to
+    /// finite ones by a change of variable, so that the same rule applies.
+    pub fn integrate(&mut self, a: f64, b: f64) -> Result<Integral, SyntaxError> {
+        if a.is_nan() || b.is_nan() {
+            return Err(self.error("the bounds of an integral must be numbers".to_owned()));
+        }
+
+        if a == b {
+            return Ok(Integral {
+                value: 0.,
+                error: 0.,
+            });
+        }
+
+        if a > b {
+            let integral = self.integrate(b, a)?;
+            return Ok(Integral {
+                value: -integral.value,
+                ..integral
+            });
+        }
+
+        match (a.is_finite(), b.is_finite()) {
+            (true, true) => self.adaptive(a, b, |x| (x, 1.)),
+            // x = a + t / (1 - t)
+            (true, false) => self.adaptive(0., 1., |t| (a + t / (1. - t), 1. / (1. - t).powi(2))),
+            // x = b - (1 - t) / t
+            (false, true) => self.adaptive(0., 1., |t| (b - (1. - t) / t, 1. / t.powi(2))),
+            // x = t / (1 - t^2)
+            (false, false) => self.adaptive(-1., 1., |t| {
+                let s = 1. - t * t;
+                (t / s, (1. + t * t) / (s * s))
+            }),
+        }
+    }
+
+    /// Integrates `f(x(t)) x'(t)` from `a` to `b`, where `transform` maps `t` to `x(t)` and
+    /// `x'(t)`. The piece with the largest estimated error is split in halves until the total
+    /// error is within the tolerance, or the pieces can't be split any further.
+    fn adaptive(
+        &mut self,
+        a: f64,
+        b: f64,
+        transform: impl Fn(f64) -> (f64, f64),
+    ) -> Result<Integral, SyntaxError> {
+        let ends = (a, b);
+        let mut segments = vec![self.segment(a, b, ends, &transform)?];
+
+        loop {
+            let total = Integral {
+                value: segments.iter().map(|s| s.integral.value).sum(),
+                error: segments.iter().map(|s| s.integral.error).sum(),
+            };
+
+            if total.is_accurate() || segments.len() >= MAX_SEGMENTS {
+                return Ok(total);
+            }
+
+            let (worst, segment) = segments
+                .iter()
+                .enumerate()
+                .max_by(|(_, x), (_, y)| x.integral.error.total_cmp(&y.integral.error))
+                .map(|(i, segment)| (i, *segment))
+                .unwrap();
+            let middle = (segment.a + segment.b) / 2.;
+
+            if middle <= segment.a || middle >= segment.b {
+                return Ok(total);
+            }
+
+            segments[worst] = self.segment(segment.a, middle, ends, &transform)?;
+            segments.push(self.segment(middle, segment.b, ends, &transform)?);
+        }
+    }
+
+    /// Applies the 15-point Kronrod rule from `a` to `b`, estimating its error from the
+    /// difference with the 7-point Gauss rule as QUADPACK does.
+    fn segment(
+        &mut self,
+        a: f64,
+        b: f64,
+        ends: (f64, f64),
+        transform: &impl Fn(f64) -> (f64, f64),
+    ) -> Result<Segment, SyntaxError> {
+        let center = (a + b) / 2.;
+        let radius = (b - a) / 2.;
+
+        let mut values = [(0., 0.); 8];
+        for (i, node) in KRONROD_NODES.iter().enumerate() {
+            let left = self.transformed(center - radius * node, ends, transform)?;
+            let right = if *node == 0. {
+                left
+            } else {
+                self.transformed(center + radius * node, ends, transform)?
+            };
+            values[i] = (left, right);
+        }
+
+        let mut kronrod = 0.;
+        let mut gauss = 0.;
+        let mut absolute = 0.;
+
+        for (i, (left, right)) in values.iter().enumerate() {
+            let (sum, abs) = if i == 7 {
+                (*left, left.abs())
+            } else {
+                (left + right, left.abs() + right.abs())
+            };
+
+            kronrod += KRONROD_WEIGHTS[i] * sum;
+            absolute += KRONROD_WEIGHTS[i] * abs;
+            if i % 2 == 1 {
+                gauss += GAUSS_WEIGHTS[i / 2] * sum;
+            }
+        }
+
+        let mean = kronrod / 2.;
+        let mut deviation = KRONROD_WEIGHTS[7] * (values[7].0 - mean).abs();
+        for (i, (left, right)) in values.iter().enumerate().take(7) {
+            deviation += KRONROD_WEIGHTS[i] * ((left - mean).abs() + (right - mean).abs());
+        }
+
+        let (kronrod, gauss) = (kronrod * radius, gauss * radius);
+        let (absolute, deviation) = (absolute * radius.abs(), deviation * radius.abs());
+
+        let mut error = (kronrod - gauss).abs();
+        if deviation != 0. && error != 0. {
+            error = deviation * (200. * error / deviation).powf(1.5).min(1.);
+        }
+        error = error.max(50. * f64::EPSILON * absolute);
+
+        Ok(Segment {
+            a,
+            b,
+            integral: Integral {
+                value: kronrod,
+                error,
+            },
+        })
+    }
+
+    /// Value of `f(x(t)) x'(t)`. Once the pieces are small enough for `t` to round to `ends`,
+    /// the ends of the whole interval, singularities there are skipped, as they only weigh on the
+    /// integral if it diverges.
+    fn transformed(
+        &mut self,
+        t: f64,
+        ends: (f64, f64),
+        transform: &impl Fn(f64) -> (f64, f64),
+    ) -> Result<f64, SyntaxError> {
+        let (x, dx) = transform(t);
+        let y = (self.f)(x)?;
+
+        if !y.is_finite() && (t <= ends.0 || t >= ends.1) {
+            return Ok(0.);
+        }
+
+        if !y.is_finite() {
+            return Err(self.error(format!("the integrand is not finite at {}", x)));
+        }
+
+        // Past the range of floats, the integrand must vanish for the integral to converge
+        Ok(if y == 0. { 0. } else { y * dx })
+    }
+}
+
+/// Integrates the expression `src` with respect to `variable`, from `a` to `b`, either of which
+/// may be infinite. The result holds an estimate of its error, which may exceed the usual
+/// tolerance for integrals that diverge or oscillate too much, see [`Integral::is_accurate`].
+pub fn integrate(
+    ctx: &Context,
+    src: &str,
+    variable: &str,
+    a: f64,
+    b: f64,
+) -> Result<Integral, SyntaxError> {
+    let expr = parse_expression(src)?;
+    let mut evaluator = Evaluator::new(src, ctx);
+
+    Quadrature::new(src, Span::new(0, src.len()), |x| {
+        evaluator.bind(variable, x);
+        evaluator.eval(&expr)
+    })
+    .integrate(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn integral(src: &str, a: f64, b: f64) -> Integral {
+        integrate(&Context::new(), src, "x", a, b).unwrap()
+    }
+
+    fn assert_integral(src: &str, a: f64, b: f64, expected: f64) {
+        let integral = integral(src, a, b);
+
+        assert!(integral.is_accurate(), "{} = {:?}", src, integral);
+        assert!(
+            (integral.value - expected).abs() <= 1e-9 * expected.abs().max(1.),
+            "{} = {:?}, expected {}",
+            src,
+            integral,
+            expected
+        );
+    }
+
+    #[test]
+    fn integrate_finite() {
+        assert_integral("x^2", 0., 1., 1. / 3.);
+        assert_integral("x^2", 1., 0., -1. / 3.);
+        assert_integral("x^2", 1., 1., 0.);
+        assert_integral("sin(x)", 0., PI, 2.);
+        assert_integral("sin(x)", 0., 2. * PI, 0.);
+        assert_integral("e^x", -1., 2., 2f64.exp() - (-1f64).exp());
+        assert_integral("1/x", 1., 1e6, 1e6f64.ln());
+        assert_integral("sin(50x)^2", 0., PI, PI / 2.);
+        assert_integral("x % 1", 0., 2., 1.);
+    }
+
+    #[test]
+    fn integrate_endpoint_singularities() {
+        assert_integral("1/sqrt(x)", 0., 1., 2.);
+        assert_integral("ln(x)", 0., 1., -1.);
+        assert_integral("x^-0.9", 0., 1., 10.);
+    }
+
+    #[test]
+    fn integrate_infinite() {
+        assert_integral("e^-x", 0., f64::INFINITY, 1.);
+        assert_integral("1/x^2", 1., f64::INFINITY, 1.);
+        assert_integral("e^x", f64::NEG_INFINITY, 0., 1.);
+        assert_integral("e^-(x^2)", f64::NEG_INFINITY, f64::INFINITY, PI.sqrt());
+        assert_integral("1/(1 + x^2)", f64::INFINITY, f64::NEG_INFINITY, -PI);
+    }
+
+    #[test]
+    fn integrate_error_estimate() {
+        assert!(integral("x^3", 0., 2.).error < 1e-12);
+
+        // Diverges, or oscillates forever
+        assert!(!integral("1/x", 0., 1.).is_accurate());
+        assert!(!integral("sin(x)", 0., f64::INFINITY).is_accurate());
+    }
+
+    #[test]
+    fn integrate_errors() {
+        let ctx = Context::new();
+
+        assert!(integrate(&ctx, "1/x", "x", -1., 1.).is_err());
+        assert!(integrate(&ctx, "x", "x", f64::NAN, 1.).is_err());
+        assert!(integrate(&ctx, "y", "x", 0., 1.).is_err());
+    }
+}
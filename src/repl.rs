@@ -0,0 +1,177 @@
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::fs;
+use std::path::PathBuf;
+
+use solve::Context;
+
+const HELP: &str = "Enter an expression to evaluate it, the previous result is available as `ans`.
+Assign variables with `name = expr` and define functions with `name(x, y) = expr`, several
+statements can be separated with `;`.
+End a line with \\ (or leave a parenthesis or bracket open) to continue the expression on the next line.
+
+Commands:
+  :help    show this message
+  :funcs   list the available functions
+  :consts  list the available constants
+  :vars    list the variables assigned so far
+  :quit    exit (as does Ctrl-D)";
+
+fn history_path() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join("solve").join("history"))
+}
+
+/// Whether `input` needs more lines before it can be evaluated.
+fn is_incomplete(input: &str) -> bool {
+    let (par_level, bracket_level) =
+        input
+            .chars()
+            .fold((0isize, 0isize), |(par, bracket), c| match c {
+                '(' => (par + 1, bracket),
+                ')' => (par - 1, bracket),
+                '[' => (par, bracket + 1),
+                ']' => (par, bracket - 1),
+                _ => (par, bracket),
+            });
+
+    par_level > 0 || bracket_level > 0
+}
+
+/// Runs a command, returning false if the session should end.
+fn command(cmd: &str, ctx: &Context) -> bool {
+    match cmd {
+        ":help" | ":h" => println!("{}", HELP),
+        ":funcs" => {
+            for (_, function) in ctx.functions().iter() {
+                println!("  {:<16} {}", function.signature(), function.description());
+            }
+
+            for (signature, body) in ctx.definitions() {
+                println!("  {:<16} = {}", signature, body);
+            }
+        }
+        ":consts" => {
+            for (name, value) in ctx.constants() {
+                println!("  {:<8} {}", name, value);
+            }
+        }
+        ":vars" => {
+            for (name, value) in ctx.variables() {
+                println!("  {:<8} {}", name, value);
+            }
+        }
+        ":quit" | ":q" => return false,
+        _ => println!("Unknown command \"{}\", use :help to list them", cmd),
+    }
+
+    true
+}
+
+pub fn run() {
+    let mut rl = match DefaultEditor::new() {
+        Ok(rl) => rl,
+        Err(err) => {
+            println!("Could not start interactive mode: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let history = history_path();
+
+    if let Some(path) = &history {
+        // A missing history file just means this is the first session
+        let _ = rl.load_history(path);
+    }
+
+    let mut ctx = Context::new();
+    let mut input = String::new();
+
+    loop {
+        let prompt = if input.is_empty() { "> " } else { ". " };
+
+        let line = match rl.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                println!("{}", err);
+                break;
+            }
+        };
+
+        if !input.is_empty() {
+            input.push(' ');
+        }
+
+        if let Some(line) = line.strip_suffix('\\') {
+            input.push_str(line);
+            continue;
+        }
+
+        input.push_str(&line);
+
+        if is_incomplete(&input) {
+            continue;
+        }
+
+        let expr = std::mem::take(&mut input);
+        let expr = expr.trim();
+
+        if expr.is_empty() {
+            continue;
+        }
+
+        let _ = rl.add_history_entry(expr);
+
+        if expr.starts_with(':') {
+            if command(expr, &ctx) {
+                continue;
+            } else {
+                break;
+            }
+        }
+
+        match ctx.execute(expr) {
+            Ok(res) => {
+                for warning in ctx.take_warnings() {
+                    println!("warning: {}", warning);
+                }
+
+                if let Some(res) = res {
+                    println!("{}", res);
+                    ctx.set_variable("ans", res);
+                }
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    if let Some(path) = &history {
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        if let Err(err) = rl.save_history(path) {
+            println!("Could not save history to {}: {}", path.display(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_incomplete;
+
+    #[test]
+    fn incomplete_input() {
+        assert!(is_incomplete("sqrt(1+"));
+        assert!(is_incomplete("((1)"));
+        assert!(!is_incomplete("sqrt(1)"));
+        assert!(!is_incomplete("1+1)"));
+        assert!(is_incomplete("[[1, 2],"));
+        assert!(is_incomplete("det([[1, 2], [3, 4]]"));
+        assert!(!is_incomplete("[[1, 2], [3, 4]]"));
+    }
+}
@@ -0,0 +1,90 @@
+/// Number of columns of the extrapolation table, as higher ones amplify rounding errors more than
+/// they speed up convergence.
+const MAX_ORDER: usize = 8;
+
+/// Value of an extrapolation table, along with its rounding error and its estimated error.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Entry {
+    pub value: f64,
+    pub rounding: f64,
+    pub error: f64,
+}
+
+/// Richardson's extrapolation to `h = 0` of values sampled at steps `h`, `h / 2`, `h / 4`...,
+/// whose error is a series in powers of `h^power`. Shared by limits, derivatives and infinite
+/// series.
+pub(crate) struct Table {
+    power: f64,
+    row: Vec<Entry>,
+}
+
+impl Table {
+    pub fn new(power: f64) -> Table {
+        Table {
+            power,
+            row: Vec::new(),
+        }
+    }
+
+    /// Adds the value sampled at the next step, along with its rounding error, and returns the new
+    /// row of the table: the value followed by ever higher orders of extrapolation. The error of
+    /// an extrapolated value is the larger of its distances to the values it was extrapolated
+    /// from, but never less than its rounding error, so that values that cancelled out to 0 aren't
+    /// taken as exact. The sampled value itself has an infinite error.
+    pub fn push(&mut self, value: f64, rounding: f64) -> &[Entry] {
+        let mut row = vec![Entry {
+            value,
+            rounding,
+            error: f64::INFINITY,
+        }];
+
+        for order in 1..=self.row.len().min(MAX_ORDER) {
+            let factor = 2f64.powf(self.power * order as f64) - 1.;
+            let (current, earlier) = (row[order - 1], self.row[order - 1]);
+            let value = current.value + (current.value - earlier.value) / factor;
+            let rounding = current.rounding + (current.rounding + earlier.rounding) / factor;
+            let error = (value - current.value)
+                .abs()
+                .max((value - earlier.value).abs())
+                .max(rounding);
+
+            row.push(Entry {
+                value,
+                rounding,
+                error,
+            });
+        }
+
+        self.row = row;
+        &self.row
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn richardson_table() {
+        // Errors in powers of h are removed one column at a time
+        let mut table = Table::new(1.);
+        let mut h = 1.;
+        let mut last = Vec::new();
+
+        for _ in 0..4 {
+            last = table.push(1. + h + h * h + h * h * h, 0.).to_vec();
+            h /= 2.;
+        }
+
+        assert_eq!(last.len(), 4);
+        assert_eq!(last[3].value, 1.);
+        assert!(last[0].error.is_infinite());
+
+        // Differences below the rounding error don't make a value exact
+        let mut table = Table::new(2.);
+        table.push(0., 1e-10);
+        let row = table.push(0., 1e-10);
+        assert_eq!(row[1].value, 0.);
+        assert!(row[1].error >= 1e-10);
+    }
+}
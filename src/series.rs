@@ -0,0 +1,253 @@
+use crate::errors::*;
+use crate::lexer::Span;
+use crate::richardson;
+
+/// Most terms a sum or product may have, finite or not.
+const MAX_TERMS: f64 = 1e6;
+
+/// Relative difference below which successive estimates of an infinite sum or product are taken
+/// as its limit.
+const TOLERANCE: f64 = 1e-12;
+
+/// Number of terms that must leave the partial result unchanged for the remaining ones to be
+/// taken as negligible.
+const NEGLIGIBLE_TERMS: usize = 6;
+
+/// Number of partial results, taken after 1, 2, 4, 8... terms, to compute before an extrapolated
+/// limit may be accepted.
+const MIN_LEVELS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Sum,
+    Product,
+}
+
+impl Operation {
+    fn identity(self) -> f64 {
+        match self {
+            Operation::Sum => 0.,
+            Operation::Product => 1.,
+        }
+    }
+
+    fn apply(self, acc: f64, term: f64) -> f64 {
+        match self {
+            Operation::Sum => acc + term,
+            Operation::Product => acc * term,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Operation::Sum => "sum",
+            Operation::Product => "product",
+        }
+    }
+}
+
+/// Sums or multiplies the values of `f`, a function of the index, over a range of integers.
+pub(crate) struct Series<'a, F> {
+    src: &'a str,
+    span: Span,
+    f: F,
+}
+
+impl<'a, F: FnMut(f64) -> Result<f64, SyntaxError>> Series<'a, F> {
+    /// `span` is the call being evaluated, and is only used to report errors.
+    pub fn new(src: &'a str, span: Span, f: F) -> Series<'a, F> {
+        Series { src, span, f }
+    }
+
+    fn error(&self, msg: String) -> SyntaxError {
+        SyntaxError::at(self.src, self.span, msg)
+    }
+
+    /// Sum of the terms from `from` to `to` included, which is 0 if `to` is lower than `from`.
+    /// `to` may be infinite, see [`Series::limit`].
+    pub fn sum(&mut self, from: f64, to: f64) -> Result<f64, SyntaxError> {
+        self.evaluate(Operation::Sum, from, to)
+    }
+
+    /// Product of the terms from `from` to `to` included, which is 1 if `to` is lower than
+    /// `from`. `to` may be infinite, see [`Series::limit`].
+    pub fn product(&mut self, from: f64, to: f64) -> Result<f64, SyntaxError> {
+        self.evaluate(Operation::Product, from, to)
+    }
+
+    /// Term of index `k`, which must be a number.
+    fn term(&mut self, operation: Operation, k: f64) -> Result<f64, SyntaxError> {
+        let term = (self.f)(k)?;
+
+        match term.is_nan() {
+            true => Err(self.error(format!(
+                "the {} is undefined, its term of index {} is not a number",
+                operation.name(),
+                k
+            ))),
+            false => Ok(term),
+        }
+    }
+
+    fn evaluate(&mut self, operation: Operation, from: f64, to: f64) -> Result<f64, SyntaxError> {
+        let is_integer = |x: f64| x == x.trunc();
+
+        if !from.is_finite() || !is_integer(from) || !(is_integer(to) || to == f64::INFINITY) {
+            return Err(self.error(format!(
+                "the bounds of a {} must be integers, the upper one may be inf",
+                operation.name()
+            )));
+        }
+
+        if to == f64::INFINITY {
+            return self.limit(operation, from);
+        }
+
+        if to - from + 1. > MAX_TERMS {
+            return Err(self.error(format!(
+                "a {} may not have more than {} terms",
+                operation.name(),
+                MAX_TERMS
+            )));
+        }
+
+        let mut acc = operation.identity();
+        let mut k = from;
+
+        while k <= to {
+            acc = operation.apply(acc, self.term(operation, k)?);
+            k += 1.;
+        }
+
+        Ok(acc)
+    }
+
+    /// Limit of the partial sums or products from `from`. They are computed after 1, 2, 4, 8...
+    /// terms, and extrapolated with Richardson's method assuming their error is a series in
+    /// powers of 1/n, which holds for the usual series such as `1/k^2`. Terms falling below the
+    /// precision of the partial result end the computation early. As periodic partial results
+    /// such as those of `(-1)^k` may look constant after 1, 2, 4, 8... terms, the extrapolation is
+    /// only trusted once the range of the partial results between two of them keeps shrinking.
+    fn limit(&mut self, operation: Operation, from: f64) -> Result<f64, SyntaxError> {
+        let mut acc = operation.identity();
+        let mut k = from;
+        let mut n = 0.;
+        let mut table = richardson::Table::new(1.);
+        let mut negligible = 0;
+        // Lowest and highest partial results since the last row, and the range before it
+        let (mut low, mut high) = (acc, acc);
+        let mut spread = f64::INFINITY;
+
+        while n < MAX_TERMS {
+            let next = operation.apply(acc, self.term(operation, k)?);
+            k += 1.;
+            n += 1.;
+
+            if !next.is_finite() {
+                return Err(self.error(format!("the {} diverges", operation.name())));
+            }
+
+            // Past a few negligible terms, the remaining ones are assumed to be negligible too
+            negligible = if next == acc { negligible + 1 } else { 0 };
+            acc = next;
+            if negligible == NEGLIGIBLE_TERMS {
+                return Ok(acc);
+            }
+
+            (low, high) = (low.min(acc), high.max(acc));
+            if n != (n as u64).next_power_of_two() as f64 {
+                continue;
+            }
+
+            let is_settling = high - low < spread;
+            spread = high - low;
+            (low, high) = (acc, acc);
+
+            // Partial results after n terms, whose error is a series in powers of h = 1/n
+            let row = table.push(acc, acc.abs() * f64::EPSILON);
+            let last = row[row.len() - 1];
+
+            if row.len() > MIN_LEVELS && is_settling && last.error <= TOLERANCE * last.value.abs() {
+                return Ok(last.value);
+            }
+        }
+
+        Err(self.error(format!(
+            "the {} did not converge after {} terms",
+            operation.name(),
+            MAX_TERMS
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn sum(f: fn(f64) -> f64, from: f64, to: f64) -> Result<f64, SyntaxError> {
+        Series::new("", Span::new(0, 0), |k| Ok(f(k))).sum(from, to)
+    }
+
+    fn product(f: fn(f64) -> f64, from: f64, to: f64) -> Result<f64, SyntaxError> {
+        Series::new("", Span::new(0, 0), |k| Ok(f(k))).product(from, to)
+    }
+
+    #[test]
+    fn series_finite() {
+        assert_eq!(sum(|k| k * k, 1., 100.).unwrap(), 338350.);
+        assert_eq!(sum(|k| k, -3., 3.).unwrap(), 0.);
+        assert_eq!(sum(|k| k, 5., 1.).unwrap(), 0.);
+        assert_eq!(product(|k| k, 1., 10.).unwrap(), 3628800.);
+        assert_eq!(product(|k| k, 2., 1.).unwrap(), 1.);
+    }
+
+    #[test]
+    fn series_infinite() {
+        assert_close!(
+            sum(|k| 1. / (k * k), 1., f64::INFINITY).unwrap(),
+            PI * PI / 6.,
+            1e-10
+        );
+        assert_close!(
+            sum(|k| 0.5f64.powf(k), 0., f64::INFINITY).unwrap(),
+            2.,
+            1e-10
+        );
+        assert_close!(
+            sum(|k| (-1f64).powf(k + 1.) / k, 1., f64::INFINITY).unwrap(),
+            2f64.ln(),
+            1e-10
+        );
+        assert_close!(
+            sum(|k| 1. / (k * k * k), 1., f64::INFINITY).unwrap(),
+            1.2020569031595942,
+            1e-10
+        );
+        assert_close!(
+            product(|k| 1. + 1. / (k * k), 1., f64::INFINITY).unwrap(),
+            PI.sinh() / PI,
+            1e-10
+        );
+        assert_close!(
+            product(|k| 1. - 1. / (4. * k * k), 1., f64::INFINITY).unwrap(),
+            2. / PI,
+            1e-10
+        );
+    }
+
+    #[test]
+    fn series_errors() {
+        assert!(sum(|k| k, 1.5, 3.).is_err());
+        assert!(sum(|k| k, 1., f64::NAN).is_err());
+        assert!(sum(|k| k, f64::NEG_INFINITY, 1.).is_err());
+        assert!(sum(|k| k, 1., 1e7).is_err());
+        assert!(sum(|k| 2f64.powf(k), 1., f64::INFINITY).is_err());
+        assert!(sum(|k| 1. / k, 1., f64::INFINITY).is_err());
+        assert!(sum(|k| (-1f64).powf(k), 0., f64::INFINITY).is_err());
+        assert!(sum(|k| (k * PI / 2.).cos(), 0., f64::INFINITY).is_err());
+        assert!(product(|k| (-1f64).powf(k), 0., f64::INFINITY).is_err());
+        assert!(sum(|k| (k - 2.).sqrt(), 1., 3.).is_err());
+        assert!(product(|_| f64::NAN, 1., f64::INFINITY).is_err());
+    }
+}
@@ -0,0 +1,728 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::ast::*;
+use crate::context::Context;
+use crate::errors::*;
+use crate::eval::{check_arity, spread_lists, Binding};
+use crate::exact::{self, parse_decimal, ExactValue};
+use crate::lexer::Span;
+use crate::parser::{parse_expression, INTEGER_DIVISION};
+use crate::polynomial::{self, Rational};
+use crate::symbolic::{self, format, number};
+
+/// Most terms a sum may have once its products and powers are expanded. Expansions growing past
+/// it are kept factored instead, so that `(x + y + z)^20` stays as it is.
+const MAX_TERMS: usize = 64;
+
+/// Highest power of a sum that is expanded, and highest degree of the polynomials whose common
+/// factors are cancelled out of quotients.
+const MAX_EXPANDED_DEGREE: usize = 32;
+
+/// Largest factorial computed while folding constants.
+const MAX_FOLDED_FACTORIAL: u64 = 20;
+
+/// Fractional digits a coefficient may have to be printed as a decimal, as in `0.25x`, instead
+/// of as a fraction, as in `x / 3`.
+const MAX_DECIMAL_DIGITS: usize = 12;
+
+/// Product of factors raised to rational powers, keyed by the printed factor.
+type Monomial = BTreeMap<String, BigRational>;
+
+/// Sum of monomials with their coefficients, none of which is zero.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Sum(BTreeMap<Monomial, BigRational>);
+
+fn monomial_product(a: &Monomial, b: &Monomial) -> Monomial {
+    let mut res = a.clone();
+
+    for (key, exponent) in b {
+        let sum = res.get(key).cloned().unwrap_or_else(BigRational::zero) + exponent;
+
+        if sum.is_zero() {
+            res.remove(key);
+        } else {
+            res.insert(key.clone(), sum);
+        }
+    }
+
+    res
+}
+
+/// Whether the numerator and denominator of `x` are exact as `f64`, which numbers are printed from.
+fn is_representable(x: &BigRational) -> bool {
+    let max = BigInt::from(1u64 << 53);
+    x.numer().abs() <= max && x.denom() <= &max
+}
+
+impl Sum {
+    fn constant(c: BigRational) -> Sum {
+        Sum::term(Monomial::new(), c)
+    }
+
+    fn term(monomial: Monomial, c: BigRational) -> Sum {
+        let mut res = Sum::default();
+
+        if !c.is_zero() {
+            res.0.insert(monomial, c);
+        }
+
+        res
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn as_constant(&self) -> Option<BigRational> {
+        match self.0.iter().next() {
+            None => Some(BigRational::zero()),
+            Some((monomial, c)) if self.len() == 1 && monomial.is_empty() => Some(c.clone()),
+            _ => None,
+        }
+    }
+
+    fn as_term(&self) -> Option<(&Monomial, &BigRational)> {
+        match self.len() {
+            1 => self.0.iter().next(),
+            _ => None,
+        }
+    }
+
+    fn add(mut self, other: &Sum) -> Sum {
+        for (monomial, c) in &other.0 {
+            let sum = self
+                .0
+                .get(monomial)
+                .cloned()
+                .unwrap_or_else(BigRational::zero)
+                + c;
+
+            if sum.is_zero() {
+                self.0.remove(monomial);
+            } else {
+                self.0.insert(monomial.clone(), sum);
+            }
+        }
+
+        self
+    }
+
+    fn scale(&self, k: &BigRational) -> Sum {
+        if k.is_zero() {
+            return Sum::default();
+        }
+
+        Sum(self.0.iter().map(|(m, c)| (m.clone(), c * k)).collect())
+    }
+
+    fn neg(&self) -> Sum {
+        self.scale(&-BigRational::one())
+    }
+
+    fn mul(&self, other: &Sum) -> Sum {
+        let mut res = Sum::default();
+
+        for (a, x) in &self.0 {
+            for (b, y) in &other.0 {
+                res = res.add(&Sum::term(monomial_product(a, b), x * y));
+            }
+        }
+
+        res
+    }
+
+    fn is_representable(&self) -> bool {
+        self.0.values().all(is_representable)
+    }
+
+    /// Coefficients of the sum as a polynomial in its only factor, lowest degree first, if every
+    /// term is a constant or a non-negative integer power of that factor.
+    fn univariate(&self) -> Option<(Option<&String>, Rational)> {
+        let mut variable = None;
+        let mut coefficients = Vec::new();
+
+        for (monomial, c) in &self.0 {
+            let degree = match monomial.iter().collect::<Vec<_>>()[..] {
+                [] => 0,
+                [(key, exponent)] if variable.is_none_or(|v| v == key) => {
+                    variable = Some(key);
+                    exponent
+                        .to_integer()
+                        .to_usize()
+                        .filter(|n| exponent.is_integer() && *n <= MAX_EXPANDED_DEGREE)?
+                }
+                _ => return None,
+            };
+
+            if coefficients.len() <= degree {
+                coefficients.resize(degree + 1, BigRational::zero());
+            }
+
+            coefficients[degree] = c.clone();
+        }
+
+        Some((variable, coefficients))
+    }
+
+    fn from_univariate(variable: Option<&String>, coefficients: &[BigRational]) -> Sum {
+        let mut res = Sum::default();
+
+        for (degree, c) in coefficients.iter().enumerate() {
+            let mut monomial = Monomial::new();
+
+            if let (Some(variable), 1..) = (variable, degree) {
+                monomial.insert(variable.clone(), BigRational::from_integer(degree.into()));
+            }
+
+            res = res.add(&Sum::term(monomial, c.clone()));
+        }
+
+        res
+    }
+}
+
+/// Converts a number to the rational its shortest decimal representation denotes, so that `0.1`
+/// turns into `1/10` instead of the nearest binary fraction.
+fn to_rational(value: f64) -> Option<BigRational> {
+    match value.is_finite() {
+        true => parse_decimal(&format!("{:e}", value)),
+        false => None,
+    }
+}
+
+struct Simplifier<'a> {
+    src: &'a str,
+    ctx: &'a Context,
+    /// Every factor that can't be broken down any further, keyed by its printed form.
+    factors: HashMap<String, Expr>,
+    /// Span given to the expressions built while simplifying.
+    span: Span,
+}
+
+impl<'a> Simplifier<'a> {
+    /// A sum made of `expr` alone, taken as a factor that can't be broken down any further.
+    fn factor(&mut self, expr: Expr, exponent: BigRational) -> Sum {
+        let key = format(&expr);
+        self.factors.insert(key.clone(), expr);
+
+        Sum::term(Monomial::from([(key, exponent)]), BigRational::one())
+    }
+
+    /// Like [`Simplifier::factor`], keeping single terms broken down.
+    fn grouped(&mut self, sum: Sum) -> Sum {
+        match sum.len() {
+            0 | 1 => sum,
+            _ => {
+                let expr = self.to_expr(&sum);
+                self.factor(expr, BigRational::one())
+            }
+        }
+    }
+
+    fn binary(&self, op: char, lhs: Expr, rhs: Expr) -> Expr {
+        let mut expr = symbolic::binary(op, lhs, rhs);
+        expr.span = self.span;
+        expr
+    }
+
+    fn simplify(&mut self, expr: &Expr) -> Result<Sum, SyntaxError> {
+        match &expr.kind {
+            ExprKind::Number(value) => Ok(match to_rational(*value) {
+                Some(value) => Sum::constant(value),
+                None => self.factor(number(*value, self.span), BigRational::one()),
+            }),
+            ExprKind::Identifier(_) => {
+                let mut expr = expr.clone();
+                expr.span = self.span;
+                Ok(self.factor(expr, BigRational::one()))
+            }
+            ExprKind::Prefix { op, operand } => {
+                let operand = self.simplify(operand)?;
+
+                match op {
+                    '-' => Ok(operand.neg()),
+                    _ => Ok(operand),
+                }
+            }
+            ExprKind::Postfix { op, operand } => {
+                let operand = self.simplify(operand)?;
+
+                match operand.as_constant() {
+                    Some(n)
+                        if n.is_integer()
+                            && !n.is_negative()
+                            && n <= BigRational::from_integer(MAX_FOLDED_FACTORIAL.into()) =>
+                    {
+                        let n = n.to_integer().to_u64().unwrap();
+                        Ok(Sum::constant(BigRational::from_integer(exact::factorial(
+                            n,
+                        ))))
+                    }
+                    _ => {
+                        let operand = self.to_expr(&operand);
+                        let expr = Expr::new(
+                            ExprKind::Postfix {
+                                op: *op,
+                                operand: Box::new(operand),
+                            },
+                            self.span,
+                        );
+
+                        Ok(self.factor(expr, BigRational::one()))
+                    }
+                }
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (a, b) = (self.simplify(lhs)?, self.simplify(rhs)?);
+
+                Ok(match op {
+                    '+' => a.add(&b),
+                    '-' => a.add(&b.neg()),
+                    '*' => self.product(a, b),
+                    '/' => self.quotient(a, b),
+                    '^' => self.power(a, b),
+                    '%' => self.remainder(a, b),
+                    &INTEGER_DIVISION => self.integer_quotient(a, b),
+                    _ => unreachable!(),
+                })
+            }
+            ExprKind::Call { name, args } => self.call(name, args, expr),
+            ExprKind::List(_) => Err(SyntaxError::at(
+                self.src,
+                expr.span,
+                "vectors and matrices can't be simplified".to_owned(),
+            )),
+            ExprKind::Convert { .. } => Err(SyntaxError::at(
+                self.src,
+                expr.span,
+                "unit conversions can't be simplified".to_owned(),
+            )),
+        }
+    }
+
+    fn product(&mut self, a: Sum, b: Sum) -> Sum {
+        if a.len() * b.len() <= MAX_TERMS || a.len() <= 1 || b.len() <= 1 {
+            let res = a.mul(&b);
+
+            if res.is_representable() {
+                return res;
+            }
+        }
+
+        let (a, b) = (self.grouped(a), self.grouped(b));
+        a.mul(&b)
+    }
+
+    /// `1 / a` for a single term `a`.
+    fn inverse(monomial: &Monomial, c: &BigRational) -> Sum {
+        let monomial = monomial.iter().map(|(k, e)| (k.clone(), -e)).collect();
+        Sum::term(monomial, c.recip())
+    }
+
+    fn quotient(&mut self, a: Sum, b: Sum) -> Sum {
+        if let Some(c) = b.as_constant() {
+            if c.is_zero() {
+                let expr = self.binary('/', self.to_expr(&a), self.to_expr(&b));
+                return self.factor(expr, BigRational::one());
+            }
+
+            return a.scale(&c.recip());
+        } else if let Some((monomial, c)) = b.as_term() {
+            let inverse = Simplifier::inverse(monomial, c);
+            return self.product(a, inverse);
+        }
+
+        // Cancels the common factors of polynomials in the same variable, as in (x^2 - 1) / (x - 1)
+        let (a, b) = match (a.univariate(), b.univariate()) {
+            (Some((x, p)), Some((y, q))) if x.is_none() || x == y => {
+                let gcd = polynomial::gcd(&p, &q);
+                let (p, q) = (
+                    polynomial::div_rem(&p, &gcd).0,
+                    polynomial::div_rem(&q, &gcd).0,
+                );
+
+                (Sum::from_univariate(y, &p), Sum::from_univariate(y, &q))
+            }
+            _ => (a, b),
+        };
+
+        if let Some(c) = b.as_constant() {
+            return a.scale(&c.recip());
+        } else if let Some((monomial, c)) = b.as_term() {
+            let inverse = Simplifier::inverse(monomial, c);
+            return self.product(a, inverse);
+        }
+
+        let a = self.grouped(a);
+        let expr = self.to_expr(&b);
+        let inverse = self.factor(expr, -BigRational::one());
+
+        a.mul(&inverse)
+    }
+
+    fn power(&mut self, base: Sum, exponent: Sum) -> Sum {
+        let r = match exponent.as_constant() {
+            Some(r) => r,
+            None => {
+                let expr = self.binary('^', self.to_expr(&base), self.to_expr(&exponent));
+                return self.factor(expr, BigRational::one());
+            }
+        };
+
+        if let Some(c) = base.as_constant() {
+            if let Ok(Some(res)) = exact::pow(&c, &r) {
+                return Sum::constant(res);
+            }
+        } else if r.is_zero() {
+            return Sum::constant(BigRational::one());
+        } else if let Some((monomial, c)) = base.as_term() {
+            // (x^a)^r = x^(a r) only holds for any x when r is an integer
+            let distributes =
+                r.is_integer() || (c.is_positive() && monomial.values().all(|e| e.is_one()));
+
+            if let (true, Ok(Some(c))) = (distributes, exact::pow(c, &r)) {
+                let monomial = monomial.iter().map(|(k, e)| (k.clone(), e * &r)).collect();
+                return Sum::term(monomial, c);
+            }
+        } else if let Some(n) = r.to_integer().to_usize().filter(|_| r.is_integer()) {
+            if n <= MAX_EXPANDED_DEGREE {
+                let mut res = Sum::constant(BigRational::one());
+
+                for _ in 0..n {
+                    res = res.mul(&base);
+
+                    if res.len() > MAX_TERMS || !res.is_representable() {
+                        break;
+                    }
+                }
+
+                if res.len() <= MAX_TERMS && res.is_representable() {
+                    return res;
+                }
+            }
+        }
+
+        if base.len() > 1 {
+            let expr = self.to_expr(&base);
+            return self.factor(expr, r);
+        }
+
+        let expr = self.binary('^', self.to_expr(&base), self.rational(&r));
+        self.factor(expr, BigRational::one())
+    }
+
+    fn remainder(&mut self, a: Sum, b: Sum) -> Sum {
+        if let (Some(a), Some(b)) = (a.as_constant(), b.as_constant()) {
+            if !b.is_zero() {
+                return Sum::constant(&a - &b * (&a / &b).trunc());
+            }
+        }
+
+        let expr = self.binary('%', self.to_expr(&a), self.to_expr(&b));
+        self.factor(expr, BigRational::one())
+    }
+
+    fn integer_quotient(&mut self, a: Sum, b: Sum) -> Sum {
+        if let (Some(a), Some(b)) = (a.as_constant(), b.as_constant()) {
+            if !b.is_zero() {
+                return Sum::constant((&a / &b).trunc());
+            }
+        }
+
+        let expr = self.binary(INTEGER_DIVISION, self.to_expr(&a), self.to_expr(&b));
+        self.factor(expr, BigRational::one())
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], expr: &Expr) -> Result<Sum, SyntaxError> {
+        let function = match self.ctx.definition(name) {
+            Some(_) => None,
+            None => self.ctx.functions().get(name),
+        };
+
+        let args = match function {
+            Some(function) => spread_lists(name, function.arity(), args),
+            None => Cow::Borrowed(args),
+        };
+        let args = &args[..];
+        let is_series = Binding::of(name, args, self.ctx).is_some();
+
+        if let Some(function) = function {
+            check_arity(self.src, name, function.arity(), args, expr)?;
+        }
+
+        if let (Some(function), [condition, then, otherwise]) = (function, args) {
+            if function.native().is_none() {
+                // Only a constant condition picks a branch
+                if let Some(c) = self.simplify(condition)?.as_constant() {
+                    return self.simplify(if c.is_zero() { otherwise } else { then });
+                }
+            }
+        }
+
+        let args = args
+            .iter()
+            .map(|arg| self.simplify(arg))
+            .collect::<Result<Vec<Sum>, SyntaxError>>()?;
+
+        let native = function
+            .and_then(|function| function.native())
+            .filter(|_| !is_series);
+        let constants = args
+            .iter()
+            .map(|arg| arg.as_constant().and_then(|c| c.to_f64()))
+            .collect::<Option<Vec<f64>>>();
+
+        if let (Some(native), Some(values)) = (native, constants) {
+            let folded = native(&values)
+                .ok()
+                .filter(|value| symbolic::is_short(*value))
+                .and_then(to_rational);
+
+            if let Some(value) = folded {
+                return Ok(Sum::constant(value));
+            }
+        }
+
+        let args = args.iter().map(|arg| self.to_expr(arg)).collect();
+        let expr = symbolic::call(name, args, self.span);
+
+        Ok(self.factor(expr, BigRational::one()))
+    }
+
+    /// Number `x`, or the quotient of its numerator and denominator if it is neither an integer
+    /// nor has a short decimal representation.
+    fn rational(&self, x: &BigRational) -> Expr {
+        match decimal(x) {
+            Some(value) => number(value, self.span),
+            None if x.is_integer() => number(x.numer().to_f64().unwrap(), self.span),
+            None => self.binary(
+                '/',
+                number(x.numer().to_f64().unwrap(), self.span),
+                number(x.denom().to_f64().unwrap(), self.span),
+            ),
+        }
+    }
+
+    /// Sort key of a factor. Names come first, followed by everything else in alphabetical order.
+    fn factor_order<'k>(&self, key: &'k str) -> (bool, &'k str) {
+        let is_name = matches!(self.factors[key].kind, ExprKind::Identifier(_));
+        (!is_name, key)
+    }
+
+    /// Orders terms by decreasing degree, breaking ties by the exponent of each factor, as in
+    /// `x^2 + 2x y + y^2 + x + 1`.
+    fn compare_terms(&self, a: &Monomial, b: &Monomial) -> Ordering {
+        let degree = |m: &Monomial| m.values().fold(BigRational::zero(), |sum, e| sum + e);
+        let mut keys = a
+            .keys()
+            .chain(b.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        keys.sort_by_key(|key| self.factor_order(key));
+
+        let zero = BigRational::zero();
+        let exponents = |m: &Monomial| {
+            keys.iter()
+                .map(|key| m.get(*key).unwrap_or(&zero).clone())
+                .collect::<Vec<_>>()
+        };
+
+        degree(b)
+            .cmp(&degree(a))
+            .then_with(|| exponents(b).cmp(&exponents(a)))
+    }
+
+    /// Product of `factors` raised to their exponents, starting with `coefficient` unless it is 1.
+    fn product_expr(&self, coefficient: Option<Expr>, factors: &[(&String, BigRational)]) -> Expr {
+        let mut res = coefficient;
+
+        for (key, exponent) in factors {
+            let base = self.factors[*key].clone();
+            let factor = match exponent.is_one() {
+                true => base,
+                false => symbolic::pow(base, self.rational(exponent)),
+            };
+
+            res = Some(match res {
+                Some(res) => symbolic::mul(res, factor),
+                None => factor,
+            });
+        }
+
+        res.unwrap_or_else(|| number(1., self.span))
+    }
+
+    /// The term `c m`, without its sign.
+    fn term_expr(&self, monomial: &Monomial, c: &BigRational) -> Expr {
+        let mut factors = monomial.iter().collect::<Vec<_>>();
+        factors.sort_by_key(|(key, _)| self.factor_order(key));
+
+        let numerator = factors
+            .iter()
+            .filter(|(_, e)| e.is_positive())
+            .map(|(key, e)| (*key, (*e).clone()))
+            .collect::<Vec<_>>();
+        let denominator = factors
+            .iter()
+            .filter(|(_, e)| e.is_negative())
+            .map(|(key, e)| (*key, -(*e).clone()))
+            .collect::<Vec<_>>();
+
+        let c = c.abs();
+        let coefficient = |x: BigRational| match x.is_one() {
+            true => None,
+            false => Some(self.rational(&x)),
+        };
+
+        let (top, bottom) = match decimal(&c).is_some() || c.is_integer() {
+            true => (coefficient(c), None),
+            false => (
+                coefficient(BigRational::from_integer(c.numer().clone())),
+                coefficient(BigRational::from_integer(c.denom().clone())),
+            ),
+        };
+
+        let numerator = self.product_expr(top, &numerator);
+
+        match (bottom.is_some(), denominator.is_empty()) {
+            (false, true) => numerator,
+            _ => symbolic::div(numerator, self.product_expr(bottom, &denominator)),
+        }
+    }
+
+    fn to_expr(&self, sum: &Sum) -> Expr {
+        let mut terms = sum.0.iter().collect::<Vec<_>>();
+        terms.sort_by(|(a, _), (b, _)| self.compare_terms(a, b));
+
+        let mut res: Option<Expr> = None;
+
+        for (monomial, c) in terms {
+            let term = self.term_expr(monomial, c);
+
+            res = Some(match (res, c.is_negative()) {
+                (None, false) => term,
+                (None, true) => symbolic::neg(term),
+                (Some(res), false) => symbolic::add(res, term),
+                (Some(res), true) => symbolic::sub(res, term),
+            });
+        }
+
+        res.unwrap_or_else(|| number(0., self.span))
+    }
+}
+
+/// Value of `x` if it has a short decimal representation.
+fn decimal(x: &BigRational) -> Option<f64> {
+    let repr = ExactValue::Rational(x.clone()).decimal(MAX_DECIMAL_DIGITS);
+
+    match repr.contains('(') || repr.ends_with("...") || !is_representable(x) {
+        true => None,
+        false => repr.parse().ok(),
+    }
+}
+
+/// Simplifies the expression `src`: constants are folded, products and small powers of sums are
+/// expanded, like terms are collected and common factors of polynomial quotients are cancelled.
+/// Returns the result with terms in a canonical order and only the parentheses the precedences of
+/// the operators require, as in `2x + 1` for `(x+1)^2 - x^2`. Other names are kept as they are,
+/// even if `ctx` defines them.
+pub fn simplify(ctx: &Context, src: &str) -> Result<String, SyntaxError> {
+    let expr = parse_expression(src)?;
+    let mut simplifier = Simplifier {
+        src,
+        ctx,
+        factors: HashMap::new(),
+        span: Span::new(0, src.len()),
+    };
+
+    let sum = simplifier.simplify(&expr)?;
+    Ok(format(&simplifier.to_expr(&sum)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simplified(src: &str) -> String {
+        let res = simplify(&Context::new(), src).unwrap();
+        assert_eq!(
+            simplify(&Context::new(), &res).unwrap(),
+            res,
+            "{} isn't stable",
+            res
+        );
+        res
+    }
+
+    #[test]
+    fn simplify_polynomials() {
+        assert_eq!(simplified("(x+1)^2 - x^2"), "2x + 1");
+        assert_eq!(simplified("(x + 1)(x - 1)"), "x^2 - 1");
+        assert_eq!(simplified("(x - 2)^3"), "x^3 - 6x^2 + 12x - 8");
+        assert_eq!(simplified("x + x + x"), "3x");
+        assert_eq!(simplified("2x - 2x"), "0");
+        assert_eq!(simplified("(x + y)^2"), "x^2 + 2x * y + y^2");
+        assert_eq!(simplified("y + x"), "x + y");
+        assert_eq!(simplified("3 - x + x^2"), "x^2 - x + 3");
+        assert_eq!(simplified("-(a - b)"), "-a + b");
+    }
+
+    #[test]
+    fn simplify_constants() {
+        assert_eq!(simplified("1 + 2 * 3"), "7");
+        assert_eq!(simplified("0.1 + 0.2"), "0.3");
+        assert_eq!(simplified("1/3 + 1/6"), "0.5");
+        assert_eq!(simplified("1/3"), "1 / 3");
+        assert_eq!(simplified("x/3 + x/3"), "2x / 3");
+        assert_eq!(simplified("0.5x * 4"), "2x");
+        assert_eq!(simplified("2^10 + 5!"), "1144");
+        assert_eq!(simplified("sqrt(16) + sin(0)"), "4");
+        assert_eq!(simplified("sqrt(2) * sqrt(2)"), "sqrt(2)^2");
+        assert_eq!(simplified("7 % 3"), "1");
+        assert_eq!(simplified("-7 // 2 + x // 2"), "x // 2 - 3");
+        assert_eq!(simplified("if(1, x, y)"), "x");
+        assert_eq!(simplified("2^0.5"), "2^0.5");
+        assert_eq!(simplified("1e20 x"), "100000000000000000000x");
+        assert_eq!(simplified("x^1e20"), "x^100000000000000000000");
+    }
+
+    #[test]
+    fn simplify_products_and_quotients() {
+        assert_eq!(simplified("x * x^2 / x^4"), "1 / x");
+        assert_eq!(simplified("x / x"), "1");
+        assert_eq!(simplified("sin(x) * sin(x)"), "sin(x)^2");
+        assert_eq!(simplified("2 / (4x)"), "0.5 / x");
+        assert_eq!(simplified("(x^2 - 1) / (x - 1)"), "x + 1");
+        assert_eq!(simplified("(x^2 + 2x + 1) / (2x + 2)"), "0.5x + 0.5");
+        assert_eq!(simplified("(x + 2) / (x + 1)"), "(x + 2) / (x + 1)");
+        assert_eq!(simplified("x^0.5 * x^0.5"), "x");
+        assert_eq!(simplified("(2x)^3"), "8x^3");
+        assert_eq!(simplified("(x^2)^0.5"), "x^2^0.5");
+        assert_eq!(simplified("x^y * 2"), "2x^y");
+        assert_eq!(simplified("x^(1/3) * x"), "x^(4 / 3)");
+    }
+
+    #[test]
+    fn simplify_limits() {
+        // Too many terms to expand, so the power is kept as it is
+        assert_eq!(simplified("(x + y + z)^20 - 1"), "(x + y + z)^20 - 1");
+    }
+
+    #[test]
+    fn simplify_errors() {
+        let ctx = Context::new();
+
+        assert!(simplify(&ctx, "x = 1").is_err());
+        assert!(simplify(&ctx, "3 m in km").is_err());
+        assert_eq!(
+            format!("{}", simplify(&ctx, "sin(x, y)").expect_err("")),
+            "error while parsing token \"sin(x, y)\" in expression \"sin(x, y)\": incorrect number of arguments passed, function sin takes 1 parameters but 2 were passed, sin(x, y <-- HERE"
+        );
+    }
+}
@@ -0,0 +1,527 @@
+use crate::ast::*;
+use crate::complex::ComplexValue;
+use crate::context::Context;
+use crate::errors::*;
+use crate::eval::{Binding, Evaluator, CONSTANTS};
+use crate::lexer::Span;
+use crate::numeric::RootFinder;
+use crate::parser::{parse_equation, parse_equations};
+use crate::polynomial;
+
+/// Highest degree a polynomial may reach while expanding an equation, so that `(x+1)^1000` is
+/// solved numerically instead of being expanded and solved for each of its roots.
+const MAX_DEGREE: usize = 64;
+
+/// Coefficients smaller than this fraction of the terms of the same degree they were computed from
+/// are taken as zero, so that rounding errors such as the ones in `0.1x + 0.2x = 0.3x` don't
+/// change the degree of the equation.
+const NEGLIGIBLE_COEFFICIENT: f64 = 1e-12;
+
+/// Solutions of an equation in a single unknown.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Solutions {
+    /// Every root of a polynomial equation along with its multiplicity. Real roots come first in
+    /// increasing order, followed by the complex ones.
+    Roots(Vec<(ComplexValue, usize)>),
+    /// A single solution of any other equation, found numerically.
+    Numeric(f64),
+    None,
+    Infinite,
+}
+
+impl Solutions {
+    /// Describes the solutions for the unknown `variable`, as in `x = -2 or x = 2`.
+    pub fn describe(&self, variable: &str) -> String {
+        match self {
+            Solutions::Roots(roots) => roots
+                .iter()
+                .map(|(root, multiplicity)| match multiplicity {
+                    1 => format!("{} = {}", variable, root),
+                    _ => format!("{} = {} (multiplicity {})", variable, root, multiplicity),
+                })
+                .collect::<Vec<String>>()
+                .join(" or "),
+            Solutions::Numeric(value) => format!("{} = {}", variable, value),
+            Solutions::None => "no solution".to_owned(),
+            Solutions::Infinite => "infinitely many solutions".to_owned(),
+        }
+    }
+}
+
+/// Coefficients of a polynomial in the unknown, lowest degree first.
+type Polynomial = Vec<f64>;
+
+fn add(a: &[f64], b: &[f64], sign: f64) -> Polynomial {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).copied().unwrap_or(0.) + sign * b.get(i).copied().unwrap_or(0.))
+        .collect()
+}
+
+fn mul(a: &[f64], b: &[f64]) -> Polynomial {
+    let mut res = vec![0.; a.len() + b.len() - 1];
+
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            res[i + j] += x * y;
+        }
+    }
+
+    res
+}
+
+fn degree(p: &[f64]) -> usize {
+    p.iter().rposition(|c| *c != 0.).unwrap_or(0)
+}
+
+/// Whether the unknown `variable` appears anywhere in `expr`, where names are looked up in `ctx`.
+pub(crate) fn contains(expr: &Expr, variable: &str, ctx: &Context) -> bool {
+    match &expr.kind {
+        ExprKind::Number(_) => false,
+        ExprKind::Identifier(name) => name == variable,
+        ExprKind::Prefix { operand, .. } | ExprKind::Postfix { operand, .. } => {
+            contains(operand, variable, ctx)
+        }
+        ExprKind::Binary { lhs, rhs, .. } => {
+            contains(lhs, variable, ctx) || contains(rhs, variable, ctx)
+        }
+        ExprKind::Call { name, args } => args.iter().enumerate().any(|(i, arg)| {
+            Binding::of(name, args, ctx).is_none_or(|binding| binding.is_free(args, i, variable))
+                && contains(arg, variable, ctx)
+        }),
+        ExprKind::List(items) => items.iter().any(|item| contains(item, variable, ctx)),
+        ExprKind::Convert { value, target } => {
+            contains(value, variable, ctx) || contains(target, variable, ctx)
+        }
+    }
+}
+
+struct Expander<'a> {
+    src: &'a str,
+    ctx: &'a Context,
+    variable: &'a str,
+}
+
+impl<'a> Expander<'a> {
+    /// Expands `expr` into a polynomial in the unknown, evaluating the parts not depending on it.
+    /// Returns `None` if `expr` isn't a polynomial, or if its degree exceeds [`MAX_DEGREE`].
+    fn expand(&self, expr: &Expr) -> Result<Option<Polynomial>, SyntaxError> {
+        if !contains(expr, self.variable, self.ctx) {
+            return Ok(Some(vec![Evaluator::new(self.src, self.ctx).eval(expr)?]));
+        }
+
+        let res = match &expr.kind {
+            ExprKind::Identifier(_) => vec![0., 1.],
+            ExprKind::Prefix { op, operand } => match (op, self.expand(operand)?) {
+                (_, None) => return Ok(None),
+                ('-', Some(p)) => p.iter().map(|c| -c).collect(),
+                (_, Some(p)) => p,
+            },
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (a, b) = match (self.expand(lhs)?, self.expand(rhs)?) {
+                    (Some(a), Some(b)) => (a, b),
+                    _ => return Ok(None),
+                };
+
+                match op {
+                    '+' => add(&a, &b, 1.),
+                    '-' => add(&a, &b, -1.),
+                    '*' => mul(&a, &b),
+                    '/' if degree(&b) == 0 => a.iter().map(|c| c / b[0]).collect(),
+                    '^' if degree(&b) == 0 => {
+                        let n = b[0];
+
+                        if n < 0. || n.fract() != 0. || degree(&a) as f64 * n > MAX_DEGREE as f64 {
+                            return Ok(None);
+                        }
+
+                        (0..n as usize).fold(vec![1.], |res, _| mul(&res, &a))
+                    }
+                    _ => return Ok(None),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(match degree(&res) > MAX_DEGREE {
+            true => None,
+            false => Some(res),
+        })
+    }
+}
+
+/// Solves the polynomial equation `p = 0`, dropping first the coefficients negligible next to the
+/// magnitude `scales` of the terms they cancelled out from, degree by degree.
+fn polynomial_solutions(mut p: Polynomial, scales: &[f64]) -> Solutions {
+    for (c, scale) in p.iter_mut().zip(scales) {
+        if c.abs() <= scale * NEGLIGIBLE_COEFFICIENT {
+            *c = 0.;
+        }
+    }
+
+    p.truncate(degree(&p) + 1);
+
+    match p.as_slice() {
+        [c] if *c == 0. => Solutions::Infinite,
+        [_] => Solutions::None,
+        _ => Solutions::Roots(
+            polynomial::roots(&p)
+                .into_iter()
+                .map(|(z, multiplicity)| (ComplexValue::new(z.re, z.im), multiplicity))
+                .collect(),
+        ),
+    }
+}
+
+/// Settings of the numeric root finder, used for equations that can't be solved exactly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolveOptions {
+    /// Interval to look for a root in, at which both sides of the equation must cross.
+    pub interval: Option<(f64, f64)>,
+    /// Starting point of the search for a root, 0 by default.
+    pub guess: Option<f64>,
+    /// Width of the interval the root is narrowed down to, relative to its magnitude when it is
+    /// greater than 1.
+    pub tolerance: f64,
+    pub max_iterations: usize,
+}
+
+impl Default for SolveOptions {
+    fn default() -> SolveOptions {
+        SolveOptions {
+            interval: None,
+            guess: None,
+            tolerance: 1e-12,
+            max_iterations: 100,
+        }
+    }
+}
+
+/// Solves the equation `src`, of the form `lhs = rhs`, for the unknown `variable`. Other names
+/// are looked up in `ctx`. Equations which are polynomials in `variable` are solved for every
+/// real and complex root, see [`Solutions::Roots`]. Any other equation is solved numerically
+/// with the default [`SolveOptions`].
+pub fn solve(ctx: &Context, src: &str, variable: &str) -> Result<Solutions, SyntaxError> {
+    solve_with(ctx, src, variable, &SolveOptions::default())
+}
+
+/// Like [`solve`], but with the given settings for the numeric root finder, which finds a single
+/// solution. Giving an interval or an initial guess always solves the equation numerically.
+pub fn solve_with(
+    ctx: &Context,
+    src: &str,
+    variable: &str,
+    options: &SolveOptions,
+) -> Result<Solutions, SyntaxError> {
+    let (lhs, rhs) = parse_equation(src)?;
+
+    if options.interval.is_none() && options.guess.is_none() {
+        let expander = Expander { src, ctx, variable };
+
+        if let (Some(lhs), Some(rhs)) = (expander.expand(&lhs)?, expander.expand(&rhs)?) {
+            let p = add(&lhs, &rhs, -1.);
+            let scales = (0..p.len())
+                .map(|i| {
+                    let magnitude = |p: &[f64]| p.get(i).map_or(0., |c| c.abs());
+                    magnitude(&lhs).max(magnitude(&rhs))
+                })
+                .collect::<Vec<f64>>();
+
+            if p.iter().any(|c| !c.is_finite()) {
+                return Err(SyntaxError::at(
+                    src,
+                    Span::new(0, src.len()),
+                    "the equation has non-finite coefficients".to_owned(),
+                ));
+            }
+
+            return Ok(polynomial_solutions(p, &scales));
+        }
+    }
+
+    let mut ctx = ctx.clone();
+
+    let root = RootFinder::new(src, options, |x| {
+        ctx.set_variable(variable, x);
+        let evaluator = Evaluator::new(src, &ctx);
+
+        Ok(evaluator.eval(&lhs)? - evaluator.eval(&rhs)?)
+    })
+    .find()?;
+
+    Ok(Solutions::Numeric(root))
+}
+
+/// Names in the equations `src`, separated by `;` or newlines, that are neither variables nor
+/// constants of `ctx`, in order of appearance. Used to find the unknowns when they aren't given.
+pub fn unknowns(ctx: &Context, src: &str) -> Result<Vec<String>, SyntaxError> {
+    /// `bound` holds the variables bound by the functions `expr` is an argument of.
+    fn visit(expr: &Expr, ctx: &Context, bound: &[&str], names: &mut Vec<String>) {
+        match &expr.kind {
+            ExprKind::Number(_) => {}
+            ExprKind::Identifier(name) => {
+                let known = ctx.variable(name).is_some()
+                    || ctx.constant(name).is_some()
+                    || CONSTANTS.contains_key(name.as_str())
+                    || bound.contains(&name.as_str());
+
+                if !known && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            ExprKind::Prefix { operand, .. } | ExprKind::Postfix { operand, .. } => {
+                visit(operand, ctx, bound, names)
+            }
+            ExprKind::Binary { lhs, rhs, .. } => {
+                visit(lhs, ctx, bound, names);
+                visit(rhs, ctx, bound, names);
+            }
+            ExprKind::Call { name, args } => {
+                let binding = Binding::of(name, args, ctx);
+
+                for (i, arg) in args.iter().enumerate() {
+                    match &binding {
+                        Some(binding) if i == binding.variable => {}
+                        Some(binding) if binding.scope.contains(&i) => {
+                            let mut bound = bound.to_vec();
+                            if let ExprKind::Identifier(variable) = &args[binding.variable].kind {
+                                bound.push(variable);
+                            }
+                            visit(arg, ctx, &bound, names);
+                        }
+                        _ => visit(arg, ctx, bound, names),
+                    }
+                }
+            }
+            ExprKind::List(items) => items.iter().for_each(|item| visit(item, ctx, bound, names)),
+            ExprKind::Convert { value, target } => {
+                visit(value, ctx, bound, names);
+                visit(target, ctx, bound, names);
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+
+    for (lhs, rhs) in parse_equations(src)? {
+        visit(&lhs, ctx, &[], &mut names);
+        visit(&rhs, ctx, &[], &mut names);
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve_for(src: &str, variable: &str) -> String {
+        solve(&Context::new(), src, variable)
+            .unwrap()
+            .describe(variable)
+    }
+
+    #[test]
+    fn solve_linear() {
+        assert_eq!(solve_for("2x + 3 = 11", "x"), "x = 4");
+        assert_eq!(solve_for("3 = 11 - 2x", "x"), "x = 4");
+        assert_eq!(solve_for("(y - 1) / 4 = 2", "y"), "y = 9");
+        assert_eq!(solve_for("2(x + 1) = x", "x"), "x = -2");
+        assert_eq!(solve_for("0.5x + 0.25x = 3", "x"), "x = 4");
+    }
+
+    #[test]
+    fn solve_special_cases() {
+        assert_eq!(solve_for("x + 1 = x + 2", "x"), "no solution");
+        assert_eq!(
+            solve_for("2(x + 1) = 2x + 2", "x"),
+            "infinitely many solutions"
+        );
+        assert_eq!(
+            solve_for("0.1x + 0.2x = 0.3x", "x"),
+            "infinitely many solutions"
+        );
+        assert_eq!(solve_for("x^2 + 1 = x^2", "x"), "no solution");
+    }
+
+    #[test]
+    fn solve_scales() {
+        assert_eq!(solve_for("x = 1e13", "x"), "x = 10000000000000");
+        assert_eq!(solve_for("1e13 x = 1", "x"), "x = 0.0000000000001");
+        assert_eq!(solve_for("x = 1e-13", "x"), "x = 0.0000000000001");
+        assert_eq!(
+            solve_for("1e-20 x + 1 = 2", "x"),
+            "x = 100000000000000000000"
+        );
+        assert_eq!(solve_for("1e20 x + 1 = 1e20 x", "x"), "no solution");
+        assert_eq!(
+            solve_for("x^2 = 1e-20", "x"),
+            "x = -0.0000000001 or x = 0.0000000001"
+        );
+    }
+
+    #[test]
+    fn solve_polynomial() {
+        assert_eq!(solve_for("x^2 = 4", "x"), "x = -2 or x = 2");
+        assert_eq!(solve_for("x^2 - 2x + 1 = 0", "x"), "x = 1 (multiplicity 2)");
+        assert_eq!(solve_for("(x - 1)(x - 3) = 0", "x"), "x = 1 or x = 3");
+        assert_eq!(solve_for("x^3 = 4x", "x"), "x = -2 or x = 0 or x = 2");
+        assert_eq!(solve_for("x^2 = 0", "x"), "x = 0 (multiplicity 2)");
+        assert_eq!(
+            solve_for("x^2 = 2", "x"),
+            format!("x = -{0} or x = {0}", 2f64.sqrt())
+        );
+        assert_eq!(
+            solve_for("x^3 - 6x^2 + 11x - 6 = 0", "x"),
+            "x = 1 or x = 2 or x = 3"
+        );
+        assert_eq!(
+            solve_for("(x - 2)^3 (x + 1)^2 = 0", "x"),
+            "x = -1 (multiplicity 2) or x = 2 (multiplicity 3)"
+        );
+    }
+
+    #[test]
+    fn solve_polynomial_complex() {
+        assert_eq!(solve_for("x^2 = -1", "x"), "x = -i or x = i");
+        assert_eq!(
+            solve_for("x^2 + 2x + 5 = 0", "x"),
+            "x = -1 - 2i or x = -1 + 2i"
+        );
+        assert_eq!(
+            solve_for("x^4 = 1", "x"),
+            "x = -1 or x = 1 or x = -i or x = i"
+        );
+        assert_eq!(
+            solve_for("x^5 + x^3 = 0", "x"),
+            "x = 0 (multiplicity 3) or x = -i or x = i"
+        );
+
+        match solve(&Context::new(), "x^3 = 1", "x").unwrap() {
+            Solutions::Roots(roots) => {
+                let roots = roots
+                    .iter()
+                    .map(|(z, multiplicity)| (z.re(), z.im(), *multiplicity))
+                    .collect::<Vec<(f64, f64, usize)>>();
+                let im = 3f64.sqrt() / 2.;
+
+                assert_eq!(roots[0], (1., 0., 1));
+                assert!((roots[1].0 + 0.5).abs() < 1e-15 && (roots[1].1 + im).abs() < 1e-15);
+                assert!((roots[2].0 + 0.5).abs() < 1e-15 && (roots[2].1 - im).abs() < 1e-15);
+            }
+            solutions => panic!("expected roots, got {:?}", solutions),
+        }
+    }
+
+    #[test]
+    fn solve_with_context() {
+        let mut ctx = Context::new();
+        ctx.set_variable("a", 3.);
+        ctx.set_variable("x", 100.);
+
+        assert_eq!(
+            solve(&ctx, "a x = sqrt(36)", "x").unwrap(),
+            Solutions::Roots(vec![(ComplexValue::new(2., 0.), 1)])
+        );
+        assert_eq!(unknowns(&ctx, "a x + b = pi").unwrap(), vec!["b"]);
+        assert_eq!(
+            unknowns(&Context::new(), "x + y = 2x").unwrap(),
+            vec!["x", "y"]
+        );
+        assert_eq!(
+            unknowns(&Context::new(), "integrate(t a, t, 0, b) = t").unwrap(),
+            vec!["a", "b", "t"]
+        );
+    }
+
+    #[test]
+    fn solve_numeric() {
+        let close = |src: &str, expected: f64| match solve(&Context::new(), src, "x").unwrap() {
+            Solutions::Numeric(value) => {
+                assert!((value - expected).abs() < 1e-12, "{} gave {}", src, value);
+            }
+            solutions => panic!("{} gave {:?}", src, solutions),
+        };
+
+        close("cos(x) = x", 0.7390851332151607);
+        close("x = e^-x", 0.5671432904097838);
+        close("1/x = 2", 0.5);
+        close("e^x = 10", 10f64.ln());
+        close("x^0.5 = 3", 9.);
+        close("ln(x) = 1", std::f64::consts::E);
+        close("sqrt(x) + x = 6", 4.);
+        close("sin(x)^2 = 0", 0.);
+    }
+
+    #[test]
+    fn solve_numeric_options() {
+        let ctx = Context::new();
+        let solve = |src, options: SolveOptions| match solve_with(&ctx, src, "x", &options) {
+            Ok(Solutions::Numeric(value)) => Ok(value),
+            Ok(solutions) => panic!("{} gave {:?}", src, solutions),
+            Err(err) => Err(err.to_string()),
+        };
+
+        let interval = |a, b| SolveOptions {
+            interval: Some((a, b)),
+            ..SolveOptions::default()
+        };
+        let guess = |x| SolveOptions {
+            guess: Some(x),
+            ..SolveOptions::default()
+        };
+
+        let root = solve("sin(x) = 0", interval(3., 4.)).unwrap();
+        assert!((root - std::f64::consts::PI).abs() < 1e-12);
+
+        let root = solve("x^2 = 2", guess(-1.)).unwrap();
+        assert!((root + 2f64.sqrt()).abs() < 1e-12);
+
+        let root = solve("x^2 = 2", interval(0., 10.)).unwrap();
+        assert!((root - 2f64.sqrt()).abs() < 1e-12);
+
+        let loose = SolveOptions {
+            interval: Some((0., 2.)),
+            tolerance: 1e-3,
+            ..SolveOptions::default()
+        };
+        let root = solve("x^3 + x = 1", loose).unwrap();
+        assert!((root - 0.6823278038280193).abs() < 1e-3);
+
+        assert_eq!(
+            solve("cos(x) = x", interval(2., 3.)).unwrap_err(),
+            "error while parsing token \"cos(x) = x\" in expression \"cos(x) = x\": no sign change between 2 and 3, cos(x) = x <-- HERE"
+        );
+        assert_eq!(
+            solve("1/x = 0", interval(-1., 1.)).unwrap_err(),
+            "error while parsing token \"1/x = 0\" in expression \"1/x = 0\": the equation only changes sign at discontinuities between -1 and 1, 1/x = 0 <-- HERE"
+        );
+
+        let strict = SolveOptions {
+            interval: Some((0., 1e9)),
+            max_iterations: 3,
+            ..SolveOptions::default()
+        };
+        assert_eq!(
+            solve("e^x = 10", strict).unwrap_err(),
+            "error while parsing token \"e^x = 10\" in expression \"e^x = 10\": no convergence after 3 iterations, e^x = 10 <-- HERE"
+        );
+    }
+
+    #[test]
+    fn solve_errors() {
+        let ctx = Context::new();
+
+        assert_eq!(
+            format!("{}", solve(&ctx, "sin(x) = 2", "x").expect_err("")),
+            "error while parsing token \"sin(x) = 2\" in expression \"sin(x) = 2\": no solution found near 0, try another initial guess or an interval, sin(x) = 2 <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", solve(&ctx, "e^x = -1", "x").expect_err("")),
+            "error while parsing token \"e^x = -1\" in expression \"e^x = -1\": no solution found near 0, try another initial guess or an interval, e^x = -1 <-- HERE"
+        );
+        assert!(solve(&ctx, "e^x = 0", "x").is_err());
+        assert!(solve(&ctx, "e^(-x^2) = 0", "x").is_err());
+        assert!(solve(&ctx, "x + y = 1", "x").is_err());
+        assert!(solve(&ctx, "sin(x) = y", "x").is_err());
+    }
+}
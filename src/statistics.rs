@@ -0,0 +1,152 @@
+/// The values sorted in increasing order, or `None` if one of them is NaN, as order statistics
+/// are then undefined.
+fn sorted(values: &[f64]) -> Option<Vec<f64>> {
+    if values.iter().any(|x| x.is_nan()) {
+        return None;
+    }
+
+    let mut values = values.to_vec();
+    values.sort_by(f64::total_cmp);
+    Some(values)
+}
+
+pub fn min(values: &[f64]) -> Result<f64, String> {
+    Ok(sorted(values).map_or(f64::NAN, |values| values[0]))
+}
+
+pub fn max(values: &[f64]) -> Result<f64, String> {
+    Ok(sorted(values).map_or(f64::NAN, |values| values[values.len() - 1]))
+}
+
+pub fn sum(values: &[f64]) -> Result<f64, String> {
+    Ok(values.iter().sum())
+}
+
+pub fn mean(values: &[f64]) -> Result<f64, String> {
+    Ok(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+pub fn median(values: &[f64]) -> Result<f64, String> {
+    percentile_of(50., values)
+}
+
+/// The most frequent value, the smallest one in case of a tie.
+pub fn mode(values: &[f64]) -> Result<f64, String> {
+    let values = match sorted(values) {
+        Some(values) => values,
+        None => return Ok(f64::NAN),
+    };
+
+    let mut best = (values[0], 0);
+    for run in values.chunk_by(|x, y| x == y) {
+        if run.len() > best.1 {
+            best = (run[0], run.len());
+        }
+    }
+
+    Ok(best.0)
+}
+
+/// Sum of the squared deviations from the mean.
+fn squared_deviations(values: &[f64]) -> f64 {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|x| (x - mean).powi(2)).sum()
+}
+
+/// Variance of a sample, dividing by one less than the number of values.
+pub fn var(values: &[f64]) -> Result<f64, String> {
+    Ok(squared_deviations(values) / (values.len() - 1) as f64)
+}
+
+/// Variance of a whole population.
+pub fn varp(values: &[f64]) -> Result<f64, String> {
+    Ok(squared_deviations(values) / values.len() as f64)
+}
+
+pub fn stdev(values: &[f64]) -> Result<f64, String> {
+    var(values).map(f64::sqrt)
+}
+
+pub fn stdevp(values: &[f64]) -> Result<f64, String> {
+    varp(values).map(f64::sqrt)
+}
+
+/// `args[0]`-th percentile of the other arguments.
+pub fn percentile(args: &[f64]) -> Result<f64, String> {
+    percentile_of(args[0], &args[1..])
+}
+
+/// Interpolates linearly between the closest ranks, the 0th percentile being the smallest value
+/// and the 100th the largest one, as spreadsheets do.
+fn percentile_of(p: f64, values: &[f64]) -> Result<f64, String> {
+    if !(0. ..=100.).contains(&p) {
+        return Err("the percentile must be between 0 and 100".to_owned());
+    }
+
+    let values = match sorted(values) {
+        Some(values) => values,
+        None => return Ok(f64::NAN),
+    };
+
+    let rank = p / 100. * (values.len() - 1) as f64;
+    let (below, fraction) = (rank.floor() as usize, rank.fract());
+
+    if fraction == 0. {
+        return Ok(values[below]);
+    }
+
+    Ok(values[below] + fraction * (values[below + 1] - values[below]))
+}
+
+pub fn geomean(values: &[f64]) -> Result<f64, String> {
+    if values.iter().any(|x| *x < 0.) {
+        return Err("the geometric mean is only defined for non-negative values".to_owned());
+    }
+
+    Ok((values.iter().map(|x| x.ln()).sum::<f64>() / values.len() as f64).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: [f64; 8] = [2., 4., 4., 4., 5., 5., 7., 9.];
+
+    #[test]
+    fn statistics_central() {
+        assert_eq!(min(&DATA), Ok(2.));
+        assert_eq!(max(&DATA), Ok(9.));
+        assert_eq!(sum(&DATA), Ok(40.));
+        assert_eq!(mean(&DATA), Ok(5.));
+        assert_eq!(median(&DATA), Ok(4.5));
+        assert_eq!(median(&[3., 1., 2.]), Ok(2.));
+        assert_eq!(mode(&DATA), Ok(4.));
+        assert_eq!(mode(&[3., 1., 3., 1.]), Ok(1.));
+        assert_eq!(mode(&[7.]), Ok(7.));
+        assert!((geomean(&[1., 3., 9.]).unwrap() - 3.).abs() < 1e-15);
+        assert_eq!(geomean(&[0., 4.]), Ok(0.));
+        assert!(geomean(&[-1., 4.]).is_err());
+        assert!(min(&[1., f64::NAN]).unwrap().is_nan());
+    }
+
+    #[test]
+    fn statistics_spread() {
+        assert_eq!(varp(&DATA), Ok(4.));
+        assert_eq!(stdevp(&DATA), Ok(2.));
+        assert_eq!(var(&DATA), Ok(32. / 7.));
+        assert_eq!(stdev(&DATA), Ok((32f64 / 7.).sqrt()));
+        assert_eq!(var(&[1., 1.]), Ok(0.));
+    }
+
+    #[test]
+    fn statistics_percentile() {
+        let values = [15., 20., 35., 40., 50.];
+        assert_eq!(percentile(&[0., 15., 20., 35., 40., 50.]), Ok(15.));
+        assert_eq!(percentile(&[100., 15., 20., 35., 40., 50.]), Ok(50.));
+        assert_eq!(percentile_of(40., &values), Ok(29.));
+        assert_eq!(percentile_of(50., &values), Ok(35.));
+        assert_eq!(percentile_of(90., &values), Ok(46.));
+        assert!(percentile(&[101., 1.]).is_err());
+        assert!(percentile(&[f64::NAN, 1.]).is_err());
+    }
+}
@@ -0,0 +1,403 @@
+use crate::ast::*;
+use crate::eval;
+use crate::lexer::Span;
+use crate::parser::{operator_symbol, OPERATORS};
+
+/// Most significant digits a number folded from two constants may have, so that `2 * 3` turns
+/// into `6` while `1 / 3` is kept as it is instead of becoming `0.3333333333333333`.
+const MAX_FOLDED_DIGITS: usize = 12;
+
+/// Binding power of atoms, such as numbers, names and calls, which never need parentheses.
+const ATOM_PRECEDENCE: usize = usize::MAX;
+
+/// Whether `value` can replace the expression it was computed from without cluttering it.
+pub(crate) fn is_short(value: f64) -> bool {
+    let repr = format!("{:e}", value);
+    let mantissa = repr.split('e').next().unwrap();
+
+    value.is_finite() && mantissa.chars().filter(char::is_ascii_digit).count() <= MAX_FOLDED_DIGITS
+}
+
+/// Value of `expr` if it is a number, possibly negated.
+pub(crate) fn as_number(expr: &Expr) -> Option<f64> {
+    match &expr.kind {
+        ExprKind::Number(value) => Some(*value),
+        ExprKind::Prefix { op: '-', operand } => as_number(operand).map(|value| -value),
+        ExprKind::Prefix { op: '+', operand } => as_number(operand),
+        _ => None,
+    }
+}
+
+pub(crate) fn number(value: f64, span: Span) -> Expr {
+    // Adding 0 turns -0 into 0
+    Expr::new(ExprKind::Number(value + 0.), span)
+}
+
+pub(crate) fn binary(op: char, lhs: Expr, rhs: Expr) -> Expr {
+    let span = lhs.span.to(rhs.span);
+
+    Expr::new(
+        ExprKind::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+        span,
+    )
+}
+
+/// Applies `op` to `lhs` and `rhs`, computing the result right away if both are numbers and it
+/// is short enough.
+fn fold(op: char, lhs: Expr, rhs: Expr) -> Expr {
+    if let (Some(a), Some(b)) = (as_number(&lhs), as_number(&rhs)) {
+        let value = eval::binary(op, a, b);
+
+        if is_short(value) {
+            return number(value, lhs.span.to(rhs.span));
+        }
+    }
+
+    binary(op, lhs, rhs)
+}
+
+/// Whether `a` and `b` are the same expression, regardless of where they come from.
+fn same(a: &Expr, b: &Expr) -> bool {
+    format(a) == format(b)
+}
+
+pub(crate) fn neg(expr: Expr) -> Expr {
+    if let Some(value) = as_number(&expr) {
+        return number(-value, expr.span);
+    }
+
+    match expr.kind {
+        ExprKind::Prefix { op: '-', operand } => *operand,
+        // The sign goes into the coefficient, as in `-2x`
+        ExprKind::Binary {
+            op: op @ ('*' | '/'),
+            lhs,
+            rhs,
+        } if as_number(&lhs).is_some() => binary(op, neg(*lhs), *rhs),
+        kind => {
+            let span = expr.span;
+
+            Expr::new(
+                ExprKind::Prefix {
+                    op: '-',
+                    operand: Box::new(Expr::new(kind, span)),
+                },
+                span,
+            )
+        }
+    }
+}
+
+/// Operand of a negation, if `expr` is one. Negative numbers, and products and quotients with
+/// a negative coefficient, count as negated positive ones.
+fn negated(expr: &Expr) -> Option<Expr> {
+    match &expr.kind {
+        ExprKind::Prefix { op: '-', operand } => Some(*operand.clone()),
+        ExprKind::Number(value) if *value < 0. => Some(number(-value, expr.span)),
+        ExprKind::Binary {
+            op: op @ ('*' | '/'),
+            lhs,
+            rhs,
+        } if as_number(lhs).is_some_and(|value| value < 0.) => {
+            Some(binary(*op, neg(*lhs.clone()), *rhs.clone()))
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn add(lhs: Expr, rhs: Expr) -> Expr {
+    if as_number(&lhs) == Some(0.) {
+        rhs
+    } else if as_number(&rhs) == Some(0.) {
+        lhs
+    } else if let Some(rhs) = negated(&rhs) {
+        sub(lhs, rhs)
+    } else {
+        fold('+', lhs, rhs)
+    }
+}
+
+pub(crate) fn sub(lhs: Expr, rhs: Expr) -> Expr {
+    if as_number(&rhs) == Some(0.) {
+        lhs
+    } else if same(&lhs, &rhs) {
+        number(0., lhs.span.to(rhs.span))
+    } else if as_number(&lhs) == Some(0.) {
+        neg(rhs)
+    } else if let Some(rhs) = negated(&rhs) {
+        add(lhs, rhs)
+    } else {
+        fold('-', lhs, rhs)
+    }
+}
+
+pub(crate) fn mul(lhs: Expr, rhs: Expr) -> Expr {
+    match (as_number(&lhs), as_number(&rhs)) {
+        (Some(0.), _) => return lhs,
+        (_, Some(0.)) => return rhs,
+        (Some(1.), _) => return rhs,
+        (_, Some(1.)) => return lhs,
+        (Some(-1.), _) => return neg(rhs),
+        (_, Some(-1.)) => return neg(lhs),
+        (Some(_), Some(_)) => return fold('*', lhs, rhs),
+        // Coefficients go first, as in `2x`
+        (None, Some(_)) => return mul(rhs, lhs),
+        _ => {}
+    }
+
+    if let Some(lhs) = negated(&lhs) {
+        return neg(mul(lhs, rhs));
+    } else if let Some(rhs) = negated(&rhs) {
+        return neg(mul(lhs, rhs));
+    }
+
+    // Merges the coefficients of `a * (b * x)`
+    if let ExprKind::Binary {
+        op: '*',
+        lhs: b,
+        rhs: x,
+    } = &rhs.kind
+    {
+        if as_number(&lhs).is_some() && as_number(b).is_some() {
+            let coefficient = fold('*', lhs, *b.clone());
+
+            if as_number(&coefficient).is_some() {
+                return mul(coefficient, *x.clone());
+            }
+
+            return binary('*', coefficient, *x.clone());
+        }
+
+        // Moves the coefficient of `a * (2x)` to the front
+        if let Some(coefficient) = as_number(b) {
+            return mul(number(coefficient, b.span), mul(lhs, *x.clone()));
+        }
+    }
+
+    binary('*', lhs, rhs)
+}
+
+pub(crate) fn div(lhs: Expr, rhs: Expr) -> Expr {
+    if as_number(&rhs) == Some(1.) {
+        return lhs;
+    } else if as_number(&rhs) == Some(-1.) {
+        return neg(lhs);
+    } else if as_number(&lhs) == Some(0.) && as_number(&rhs) != Some(0.) {
+        return lhs;
+    } else if same(&lhs, &rhs) && as_number(&rhs) != Some(0.) {
+        return number(1., lhs.span.to(rhs.span));
+    }
+
+    if let Some(lhs) = negated(&lhs) {
+        return neg(div(lhs, rhs));
+    } else if let Some(rhs) = negated(&rhs) {
+        return neg(div(lhs, rhs));
+    }
+
+    fold('/', lhs, rhs)
+}
+
+pub(crate) fn pow(lhs: Expr, rhs: Expr) -> Expr {
+    match (as_number(&lhs), as_number(&rhs)) {
+        (_, Some(1.)) => lhs,
+        (_, Some(0.)) => number(1., lhs.span.to(rhs.span)),
+        (Some(1.), _) => lhs,
+        _ => fold('^', lhs, rhs),
+    }
+}
+
+pub(crate) fn call(name: &str, args: Vec<Expr>, span: Span) -> Expr {
+    Expr::new(
+        ExprKind::Call {
+            name: name.to_owned(),
+            args,
+        },
+        span,
+    )
+}
+
+/// How tightly `expr` binds its operands when printed, from [`OPERATORS`].
+fn precedence(expr: &Expr) -> usize {
+    match &expr.kind {
+        ExprKind::Binary { op, .. } | ExprKind::Postfix { op, .. } => OPERATORS[op],
+        ExprKind::Convert { .. } => 0,
+        _ => ATOM_PRECEDENCE,
+    }
+}
+
+/// Whether `expr` is printed with a leading sign. A sign grabs everything up to the next `+`,
+/// `-`, `*` or `/`, so it can only be left unparenthesized on the left of those operators.
+fn is_signed(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Prefix { .. } => true,
+        ExprKind::Number(value) => value.is_sign_negative(),
+        ExprKind::Binary { op, lhs, .. } => matches!(op, '+' | '-' | '*' | '/') && is_signed(lhs),
+        _ => false,
+    }
+}
+
+/// Whether a product is printed without the `*`, as in `2x` or `3sin(x)`. A negative coefficient
+/// reads back as the negation of the whole product, which has the same value.
+fn is_implicit(lhs: &Expr, rhs: &Expr) -> bool {
+    let starts_with_name = |expr: &Expr| match &expr.kind {
+        // Names starting with `e` could be mistaken for an exponent, as in `2e5`
+        ExprKind::Identifier(name) | ExprKind::Call { name, .. } => !name.starts_with(['e', 'E']),
+        _ => false,
+    };
+
+    matches!(lhs.kind, ExprKind::Number(_))
+        && match &rhs.kind {
+            ExprKind::Binary { op: '^', lhs, .. } => starts_with_name(lhs),
+            _ => starts_with_name(rhs),
+        }
+}
+
+fn is_implicit_product(expr: &Expr) -> bool {
+    matches!(&expr.kind, ExprKind::Binary { op: '*', lhs, rhs } if is_implicit(lhs, rhs))
+}
+
+/// Formats `expr` so that parsing it back gives the same expression, only adding the
+/// parentheses the precedences of [`OPERATORS`] require.
+pub(crate) fn format(expr: &Expr) -> String {
+    let wrap = |expr: &Expr, parenthesize: bool| match parenthesize {
+        true => format!("({})", format(expr)),
+        false => format(expr),
+    };
+
+    match &expr.kind {
+        ExprKind::Number(value) if value.is_nan() => "nan".to_owned(),
+        ExprKind::Number(value) => value.to_string(),
+        ExprKind::Identifier(name) => name.clone(),
+        ExprKind::Prefix { op, operand } => {
+            // The operand of a sign extends over powers, remainders and implicit products
+            let parenthesize = (precedence(operand) < OPERATORS[&'^']
+                && !is_implicit_product(operand))
+                || is_signed(operand);
+            format!("{}{}", op, wrap(operand, parenthesize))
+        }
+        ExprKind::Postfix { op, operand } => {
+            let parenthesize = precedence(operand) < OPERATORS[op] || is_signed(operand);
+            format!("{}{}", wrap(operand, parenthesize), op)
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            let p = OPERATORS[op];
+            let left = wrap(
+                lhs,
+                precedence(lhs) < p || (is_signed(lhs) && !matches!(op, '+' | '-' | '*' | '/')),
+            );
+
+            // Operators are left associative, so a right operand binding as tightly needs
+            // parentheses unless regrouping it doesn't change the result
+            let associative = matches!(
+                (op, &rhs.kind),
+                ('+', ExprKind::Binary { op: '+', .. }) | ('*', ExprKind::Binary { op: '*', .. })
+            );
+            let right = wrap(
+                rhs,
+                precedence(rhs) < p || (precedence(rhs) == p && !associative) || is_signed(rhs),
+            );
+
+            match op {
+                '*' if is_implicit(lhs, rhs) => format!("{}{}", left, right),
+                '^' => format!("{}^{}", left, right),
+                _ => format!("{} {} {}", left, operator_symbol(*op), right),
+            }
+        }
+        ExprKind::Call { name, args } => format!(
+            "{}({})",
+            name,
+            args.iter().map(format).collect::<Vec<String>>().join(", ")
+        ),
+        ExprKind::List(items) => format!(
+            "[{}]",
+            items.iter().map(format).collect::<Vec<String>>().join(", ")
+        ),
+        ExprKind::Convert { value, target } => {
+            format!("{} in {}", wrap(value, false), wrap(target, false))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expression;
+
+    fn parse(src: &str) -> Expr {
+        parse_expression(src).unwrap()
+    }
+
+    /// Formats `src` after parsing it, checking that the result parses back to the same tree.
+    fn reformat(src: &str) -> String {
+        let res = format(&parse(src));
+        assert_eq!(format(&parse(&res)), res, "{} doesn't round trip", res);
+        res
+    }
+
+    #[test]
+    fn format_minimal_parentheses() {
+        assert_eq!(reformat("((1 + 2)) * 3"), "(1 + 2) * 3");
+        assert_eq!(reformat("1 + (2 * 3)"), "1 + 2 * 3");
+        assert_eq!(reformat("(1 - 2) - 3"), "1 - 2 - 3");
+        assert_eq!(reformat("1 - (2 - 3)"), "1 - (2 - 3)");
+        assert_eq!(reformat("1 + (2 + 3)"), "1 + 2 + 3");
+        assert_eq!(reformat("a / (b * c)"), "a / (b * c)");
+        assert_eq!(reformat("(a^b)^c"), "a^b^c");
+        assert_eq!(reformat("a^(b^c)"), "a^(b^c)");
+        assert_eq!(reformat("(x + 1)!"), "(x + 1)!");
+        assert_eq!(reformat("(x^2)!"), "(x^2)!");
+        assert_eq!(reformat("x % (y % 2)"), "x % (y % 2)");
+        assert_eq!(reformat("(a//b) * c // (d / 2)"), "a // b * c // (d / 2)");
+        assert_eq!(reformat("2 * x^(1/3)"), "2x^(1 / 3)");
+        assert_eq!(reformat("3 * sin(x + 1)"), "3sin(x + 1)");
+        assert_eq!(reformat("2 * e^x"), "2 * e^x");
+        assert_eq!(reformat("(2 * x)^2"), "(2x)^2");
+        assert_eq!(reformat("logab(2, x * y)"), "logab(2, x * y)");
+    }
+
+    #[test]
+    fn format_signs() {
+        assert_eq!(reformat("-x^2"), "-x^2");
+        assert_eq!(reformat("(-x)^2"), "(-x)^2");
+        assert_eq!(reformat("-(x + 1)"), "-(x + 1)");
+        assert_eq!(reformat("-(2x)"), "-2x");
+        assert_eq!(reformat("x!!"), "x!!");
+        assert_eq!(reformat("-x * y"), "-x * y");
+        assert_eq!(reformat("x * (-y)"), "x * (-y)");
+        assert_eq!(reformat("x - (-y)"), "x - (-y)");
+        assert_eq!(reformat("(-x)!"), "(-x)!");
+        assert_eq!(reformat("2^(-x) % 3"), "2^(-x) % 3");
+        assert_eq!(
+            format(&pow(parse("x"), number(-2., Span::new(0, 0)))),
+            "x^(-2)"
+        );
+    }
+
+    #[test]
+    fn builders_simplify() {
+        let (x, span) = (parse("x"), Span::new(0, 1));
+
+        assert_eq!(format(&add(number(0., span), x.clone())), "x");
+        assert_eq!(format(&add(x.clone(), parse("-y"))), "x - y");
+        assert_eq!(format(&sub(number(0., span), x.clone())), "-x");
+        assert_eq!(format(&mul(x.clone(), number(2., span))), "2x");
+        assert_eq!(format(&mul(number(3., span), parse("2x"))), "6x");
+        assert_eq!(format(&mul(number(1., span), x.clone())), "x");
+        assert_eq!(format(&mul(number(0., span), x.clone())), "0");
+        assert_eq!(format(&mul(parse("-a"), parse("-b"))), "a * b");
+        assert_eq!(format(&div(number(1., span), number(4., span))), "0.25");
+        assert_eq!(format(&div(number(1., span), number(3., span))), "1 / 3");
+        assert_eq!(format(&pow(x.clone(), number(1., span))), "x");
+        assert_eq!(format(&neg(neg(x.clone()))), "x");
+        assert_eq!(format(&neg(parse("2x"))), "-2x");
+        assert_eq!(format(&sub(parse("y"), neg(parse("2x")))), "y + 2x");
+        assert_eq!(format(&div(number(-1., span), parse("x^2"))), "-1 / x^2");
+        assert_eq!(format(&div(x.clone(), x.clone())), "1");
+        assert_eq!(format(&sub(parse("sin(x)"), parse("sin(x)"))), "0");
+        assert_eq!(format(&mul(parse("y"), neg(parse("2x")))), "-2 * y * x");
+    }
+}
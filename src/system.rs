@@ -0,0 +1,342 @@
+use std::fmt;
+
+use crate::ast::*;
+use crate::context::Context;
+use crate::errors::*;
+use crate::eval::Evaluator;
+use crate::lexer::Span;
+use crate::parser::parse_equations;
+use crate::solver::contains;
+
+/// Pivots smaller than this fraction of the largest coefficient of their unknown are taken as zero,
+/// so that rounding errors don't hide a dependency between equations. The same goes for right hand
+/// sides left after elimination, compared with the largest one.
+const NEGLIGIBLE_PIVOT: f64 = 1e-12;
+
+/// Solution of a system of linear equations.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SystemSolution {
+    /// Value of every unknown, in the order they were given in.
+    Unique(Vec<(String, f64)>),
+    /// Some of the equations contradict the others, so there is no solution.
+    Inconsistent,
+    /// There are at least as many equations as unknowns, but some of them follow from the
+    /// others, so there are infinitely many solutions.
+    Singular,
+    /// There are fewer equations than unknowns, so there are infinitely many solutions.
+    Underdetermined,
+}
+
+impl fmt::Display for SystemSolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SystemSolution::Unique(values) => {
+                let values = values
+                    .iter()
+                    .map(|(name, value)| format!("{} = {}", name, value))
+                    .collect::<Vec<String>>();
+
+                write!(f, "{}", values.join(", "))
+            }
+            SystemSolution::Inconsistent => write!(f, "no solution, the system is inconsistent"),
+            SystemSolution::Singular => {
+                write!(f, "infinitely many solutions, the system is singular")
+            }
+            SystemSolution::Underdetermined => {
+                write!(
+                    f,
+                    "infinitely many solutions, the system is underdetermined"
+                )
+            }
+        }
+    }
+}
+
+/// Coefficients of a linear combination of the unknowns, the constant term first.
+type Linear = Vec<f64>;
+
+fn is_constant(v: &[f64]) -> bool {
+    v[1..].iter().all(|c| *c == 0.)
+}
+
+struct Linearizer<'a> {
+    src: &'a str,
+    ctx: &'a Context,
+    variables: &'a [&'a str],
+}
+
+impl<'a> Linearizer<'a> {
+    fn not_linear(&self, span: Span) -> SyntaxError {
+        SyntaxError::at(
+            self.src,
+            span,
+            format!(
+                "the equation is not linear in {}",
+                self.variables.join(", ")
+            ),
+        )
+    }
+
+    /// Expands `expr` into a linear combination of the unknowns, evaluating the parts not
+    /// depending on them.
+    fn linear(&self, expr: &Expr) -> Result<Linear, SyntaxError> {
+        let n = self.variables.len() + 1;
+
+        if !self.variables.iter().any(|v| contains(expr, v, self.ctx)) {
+            let mut res = vec![0.; n];
+            res[0] = Evaluator::new(self.src, self.ctx).eval(expr)?;
+            return Ok(res);
+        }
+
+        match &expr.kind {
+            ExprKind::Identifier(name) => {
+                let mut res = vec![0.; n];
+                let i = self.variables.iter().position(|v| v == name).unwrap();
+                res[i + 1] = 1.;
+                Ok(res)
+            }
+            ExprKind::Prefix { op, operand } => {
+                let v = self.linear(operand)?;
+
+                match op {
+                    '-' => Ok(v.iter().map(|c| -c).collect()),
+                    _ => Ok(v),
+                }
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (a, b) = (self.linear(lhs)?, self.linear(rhs)?);
+                let scale = |v: &[f64], k: f64| v.iter().map(|c| c * k).collect();
+
+                match op {
+                    '+' => Ok(a.iter().zip(&b).map(|(x, y)| x + y).collect()),
+                    '-' => Ok(a.iter().zip(&b).map(|(x, y)| x - y).collect()),
+                    '*' if is_constant(&a) => Ok(scale(&b, a[0])),
+                    '*' if is_constant(&b) => Ok(scale(&a, b[0])),
+                    '/' if is_constant(&b) => Ok(scale(&a, 1. / b[0])),
+                    '^' if is_constant(&b) && b[0] == 1. => Ok(a),
+                    _ => Err(self.not_linear(expr.span)),
+                }
+            }
+            _ => Err(self.not_linear(expr.span)),
+        }
+    }
+}
+
+/// Solves the system of linear equations `src`, separated by `;` or newlines, for the unknowns
+/// `variables`. Other names are looked up in `ctx`. Uses Gaussian elimination with partial
+/// pivoting.
+pub fn solve_system(
+    ctx: &Context,
+    src: &str,
+    variables: &[&str],
+) -> Result<SystemSolution, SyntaxError> {
+    let equations = parse_equations(src)?;
+
+    if variables.is_empty() {
+        return Err(SyntaxError::at(
+            src,
+            Span::new(0, src.len()),
+            "the system has no unknowns".to_owned(),
+        ));
+    }
+
+    let linearizer = Linearizer {
+        src,
+        ctx,
+        variables,
+    };
+
+    // Each row holds the coefficients of the unknowns followed by the right hand side
+    let mut rows = Vec::new();
+
+    for (lhs, rhs) in &equations {
+        let span = lhs.span.to(rhs.span);
+        let (lhs, rhs) = (linearizer.linear(lhs)?, linearizer.linear(rhs)?);
+        let mut row = lhs[1..]
+            .iter()
+            .zip(&rhs[1..])
+            .map(|(a, b)| a - b)
+            .collect::<Vec<f64>>();
+        row.push(rhs[0] - lhs[0]);
+
+        if row.iter().any(|c| !c.is_finite()) {
+            return Err(SyntaxError::at(
+                src,
+                span,
+                "the equation has non-finite coefficients".to_owned(),
+            ));
+        }
+
+        rows.push(row);
+    }
+
+    let (n, m) = (rows.len(), variables.len());
+    // Each column is scaled on its own, so that unknowns of different magnitudes don't hide each
+    // other, nor the right hand side the coefficients
+    let tolerances = (0..=m)
+        .map(|col| rows.iter().fold(0f64, |max, row| max.max(row[col].abs())) * NEGLIGIBLE_PIVOT)
+        .collect::<Vec<f64>>();
+
+    // Forward elimination, leaving the rows in echelon form
+    let mut rank = 0;
+
+    for col in 0..m {
+        if rank == n {
+            break;
+        }
+
+        let pivot = (rank..n)
+            .max_by(|i, j| rows[*i][col].abs().total_cmp(&rows[*j][col].abs()))
+            .unwrap();
+
+        if rows[pivot][col].abs() <= tolerances[col] {
+            continue;
+        }
+
+        rows.swap(rank, pivot);
+
+        let (top, bottom) = rows.split_at_mut(rank + 1);
+        let pivot_row = &top[rank];
+
+        for row in bottom {
+            let factor = row[col] / pivot_row[col];
+
+            for (x, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * p;
+            }
+        }
+
+        rank += 1;
+    }
+
+    if rows[rank..].iter().any(|row| row[m].abs() > tolerances[m]) {
+        return Ok(SystemSolution::Inconsistent);
+    } else if rank < m {
+        return Ok(match n < m {
+            true => SystemSolution::Underdetermined,
+            false => SystemSolution::Singular,
+        });
+    }
+
+    // With full rank, the pivot of the i-th row is in the i-th column
+    let mut values = vec![0.; m];
+
+    for i in (0..m).rev() {
+        let sum = (i + 1..m).map(|j| rows[i][j] * values[j]).sum::<f64>();
+        values[i] = (rows[i][m] - sum) / rows[i][i] + 0.;
+    }
+
+    Ok(SystemSolution::Unique(
+        variables
+            .iter()
+            .map(|name| name.to_string())
+            .zip(values)
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(src: &str, variables: &[&str]) -> String {
+        solve_system(&Context::new(), src, variables)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn system_unique() {
+        assert_eq!(system("x + y = 3; x - y = 1", &["x", "y"]), "x = 2, y = 1");
+
+        let src = "2a + b - c = 8\n-3a - b + 2c = -11\n-2a + b + 2c = -3";
+        match solve_system(&Context::new(), src, &["a", "b", "c"]).unwrap() {
+            SystemSolution::Unique(values) => {
+                for ((_, value), expected) in values.iter().zip([2., 3., -1.]) {
+                    assert!((value - expected).abs() < 1e-12);
+                }
+            }
+            solution => panic!("expected a unique solution, got {:?}", solution),
+        }
+
+        assert_eq!(system("y = 2x; x = 3", &["x", "y"]), "x = 3, y = 6");
+        assert_eq!(
+            system("(x + y) / 2 = 1; 2(x - y) = 0", &["x", "y"]),
+            "x = 1, y = 1"
+        );
+        // Zero in the first pivot position needs a row swap
+        assert_eq!(system("y = 1; x + y = 3", &["x", "y"]), "x = 2, y = 1");
+        // Consistent overdetermined system
+        assert_eq!(
+            system("x + y = 3; x - y = 1; 2x = 4", &["x", "y"]),
+            "x = 2, y = 1"
+        );
+    }
+
+    #[test]
+    fn system_scales() {
+        assert_eq!(
+            system("x + y = 1e13; x - y = 0", &["x", "y"]),
+            "x = 5000000000000, y = 5000000000000"
+        );
+        assert_eq!(
+            system("1e-13 x + y = 1; y = 0", &["x", "y"]),
+            "x = 10000000000000, y = 0"
+        );
+        assert_eq!(
+            system("1e12 x + y = 2e12; x - 1e-12 y = 0", &["x", "y"]),
+            "x = 1, y = 1000000000000"
+        );
+        assert_eq!(
+            system("x + y = 1e13; 2x + 2y = 2e13", &["x", "y"]),
+            "infinitely many solutions, the system is singular"
+        );
+    }
+
+    #[test]
+    fn system_degenerate() {
+        assert_eq!(
+            system("x + y = 3; x + y = 4", &["x", "y"]),
+            "no solution, the system is inconsistent"
+        );
+        assert_eq!(
+            system("x + y = 3; 2x + 2y = 6", &["x", "y"]),
+            "infinitely many solutions, the system is singular"
+        );
+        assert_eq!(
+            system("x + y + z = 1; x - y = 0", &["x", "y", "z"]),
+            "infinitely many solutions, the system is underdetermined"
+        );
+        assert_eq!(
+            system("0.1x + 0.2y = 0.3; x + 2y = 3", &["x", "y"]),
+            "infinitely many solutions, the system is singular"
+        );
+    }
+
+    #[test]
+    fn system_with_context() {
+        let mut ctx = Context::new();
+        ctx.set_variable("k", 2.);
+
+        assert_eq!(
+            solve_system(&ctx, "k x + y = 5; x - y = sqrt(1)", &["x", "y"]).unwrap(),
+            SystemSolution::Unique(vec![("x".to_owned(), 2.), ("y".to_owned(), 1.)])
+        );
+    }
+
+    #[test]
+    fn system_errors() {
+        let ctx = Context::new();
+
+        assert_eq!(
+            format!("{}", solve_system(&ctx, "x + y = 3; x y = 2", &["x", "y"]).expect_err("")),
+            "error while parsing token \"x y\" in expression \"x + y = 3; x y = 2\": the equation is not linear in x, y, x + y = 3; x y <-- HERE"
+        );
+        assert_eq!(
+            format!("{}", solve_system(&ctx, "x^2 = 1", &["x"]).expect_err("")),
+            "error while parsing token \"x^2\" in expression \"x^2 = 1\": the equation is not linear in x, x^2 <-- HERE"
+        );
+        assert!(solve_system(&ctx, "x + y = 3; x - z = 1", &["x", "y"]).is_err());
+        assert!(solve_system(&ctx, "1 = 1", &[]).is_err());
+    }
+}
@@ -0,0 +1,486 @@
+use phf::phf_map;
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::ast::*;
+use crate::backend::Backend;
+use crate::eval::CONSTANTS;
+use crate::functions::{Arity, FunctionRegistry};
+use crate::math;
+use crate::parser::{parse_expression, INTEGER_DIVISION};
+
+/// Symbols of the SI base units, in the order their exponents are stored in a [`Dimension`].
+const BASE_UNITS: [&str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+/// Exponents of each of the SI base units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Dimension([i32; 7]);
+
+const fn dim(m: i32, kg: i32, s: i32, a: i32, k: i32, mol: i32, cd: i32) -> Dimension {
+    Dimension([m, kg, s, a, k, mol, cd])
+}
+
+const NONE: Dimension = dim(0, 0, 0, 0, 0, 0, 0);
+
+/// Units that can be written on their own, with their value in SI base units and whether they
+/// accept a prefix (as in `km`).
+static UNITS: phf::Map<&str, (f64, Dimension, bool)> = phf_map! {
+    "m" => (1., dim(1, 0, 0, 0, 0, 0, 0), true),
+    "g" => (1e-3, dim(0, 1, 0, 0, 0, 0, 0), true),
+    "s" => (1., dim(0, 0, 1, 0, 0, 0, 0), true),
+    "A" => (1., dim(0, 0, 0, 1, 0, 0, 0), true),
+    "K" => (1., dim(0, 0, 0, 0, 1, 0, 0), true),
+    "mol" => (1., dim(0, 0, 0, 0, 0, 1, 0), true),
+    "cd" => (1., dim(0, 0, 0, 0, 0, 0, 1), true),
+
+    "rad" => (1., NONE, false),
+    "deg" => (PI / 180., NONE, false),
+
+    "min" => (60., dim(0, 0, 1, 0, 0, 0, 0), false),
+    "h" => (3600., dim(0, 0, 1, 0, 0, 0, 0), false),
+    "d" => (86400., dim(0, 0, 1, 0, 0, 0, 0), false),
+    "wk" => (604800., dim(0, 0, 1, 0, 0, 0, 0), false),
+    "yr" => (31557600., dim(0, 0, 1, 0, 0, 0, 0), false),
+
+    "inch" => (0.0254, dim(1, 0, 0, 0, 0, 0, 0), false),
+    "ft" => (0.3048, dim(1, 0, 0, 0, 0, 0, 0), false),
+    "yd" => (0.9144, dim(1, 0, 0, 0, 0, 0, 0), false),
+    "mi" => (1609.344, dim(1, 0, 0, 0, 0, 0, 0), false),
+    "nmi" => (1852., dim(1, 0, 0, 0, 0, 0, 0), false),
+    "au" => (149597870700., dim(1, 0, 0, 0, 0, 0, 0), false),
+    "ly" => (9460730472580800., dim(1, 0, 0, 0, 0, 0, 0), false),
+
+    "ha" => (1e4, dim(2, 0, 0, 0, 0, 0, 0), false),
+    "acre" => (4046.8564224, dim(2, 0, 0, 0, 0, 0, 0), false),
+    "L" => (1e-3, dim(3, 0, 0, 0, 0, 0, 0), true),
+    "l" => (1e-3, dim(3, 0, 0, 0, 0, 0, 0), true),
+    "gal" => (3.785411784e-3, dim(3, 0, 0, 0, 0, 0, 0), false),
+
+    "t" => (1e3, dim(0, 1, 0, 0, 0, 0, 0), true),
+    "lb" => (0.45359237, dim(0, 1, 0, 0, 0, 0, 0), false),
+    "oz" => (0.028349523125, dim(0, 1, 0, 0, 0, 0, 0), false),
+
+    "mph" => (0.44704, dim(1, 0, -1, 0, 0, 0, 0), false),
+    "kn" => (1852. / 3600., dim(1, 0, -1, 0, 0, 0, 0), false),
+
+    "Hz" => (1., dim(0, 0, -1, 0, 0, 0, 0), true),
+    "N" => (1., dim(1, 1, -2, 0, 0, 0, 0), true),
+    "Pa" => (1., dim(-1, 1, -2, 0, 0, 0, 0), true),
+    "bar" => (1e5, dim(-1, 1, -2, 0, 0, 0, 0), true),
+    "atm" => (101325., dim(-1, 1, -2, 0, 0, 0, 0), false),
+    "psi" => (6894.757293168361, dim(-1, 1, -2, 0, 0, 0, 0), false),
+    "J" => (1., dim(2, 1, -2, 0, 0, 0, 0), true),
+    "Wh" => (3600., dim(2, 1, -2, 0, 0, 0, 0), true),
+    "eV" => (1.602176634e-19, dim(2, 1, -2, 0, 0, 0, 0), true),
+    "cal" => (4.184, dim(2, 1, -2, 0, 0, 0, 0), true),
+    "W" => (1., dim(2, 1, -3, 0, 0, 0, 0), true),
+    "C" => (1., dim(0, 0, 1, 1, 0, 0, 0), true),
+    "V" => (1., dim(2, 1, -3, -1, 0, 0, 0), true),
+    "ohm" => (1., dim(2, 1, -3, -2, 0, 0, 0), true),
+    "Ω" => (1., dim(2, 1, -3, -2, 0, 0, 0), true),
+    "F" => (1., dim(-2, -1, 4, 2, 0, 0, 0), true),
+    "S" => (1., dim(-2, -1, 3, 2, 0, 0, 0), true),
+    "Wb" => (1., dim(2, 1, -2, -1, 0, 0, 0), true),
+    "T" => (1., dim(0, 1, -2, -1, 0, 0, 0), true),
+    "H" => (1., dim(2, 1, -2, -2, 0, 0, 0), true),
+};
+
+static PREFIXES: phf::Map<&str, f64> = phf_map! {
+    "Y" => 1e24,
+    "Z" => 1e21,
+    "E" => 1e18,
+    "P" => 1e15,
+    "T" => 1e12,
+    "G" => 1e9,
+    "M" => 1e6,
+    "k" => 1e3,
+    "h" => 1e2,
+    "da" => 1e1,
+    "d" => 1e-1,
+    "c" => 1e-2,
+    "m" => 1e-3,
+    "u" => 1e-6,
+    "µ" => 1e-6,
+    "n" => 1e-9,
+    "p" => 1e-12,
+    "f" => 1e-15,
+    "a" => 1e-18,
+    "z" => 1e-21,
+    "y" => 1e-24,
+};
+
+/// Value in SI base units and dimension of the unit `name`, which may have a prefix.
+fn unit(name: &str) -> Option<(f64, Dimension)> {
+    if let Some((factor, dimension, _)) = UNITS.get(name) {
+        return Some((*factor, *dimension));
+    }
+
+    PREFIXES.entries().find_map(
+        |(prefix, scale)| match UNITS.get(name.strip_prefix(prefix)?)? {
+            (factor, dimension, true) => Some((scale * factor, *dimension)),
+            _ => None,
+        },
+    )
+}
+
+impl Dimension {
+    fn is_none(&self) -> bool {
+        *self == NONE
+    }
+
+    fn zip(self, other: Dimension, f: impl Fn(i32, i32) -> i32) -> Dimension {
+        let mut res = self;
+
+        for (a, b) in res.0.iter_mut().zip(other.0) {
+            *a = f(*a, b);
+        }
+
+        res
+    }
+
+    /// The dimension raised to `exponent`, if every resulting exponent is a whole number.
+    fn pow(self, exponent: f64) -> Option<Dimension> {
+        let mut res = self;
+
+        for d in res.0.iter_mut() {
+            let x = *d as f64 * exponent;
+
+            if (x - x.round()).abs() > 1e-9 {
+                return None;
+            }
+
+            *d = x.round() as i32;
+        }
+
+        Some(res)
+    }
+
+    /// Describes the dimension in error messages.
+    fn describe(&self) -> String {
+        if self.is_none() {
+            "a dimensionless number".to_owned()
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts = BASE_UNITS
+            .iter()
+            .zip(self.0)
+            .filter(|(_, exponent)| *exponent != 0)
+            .map(|(unit, exponent)| match exponent {
+                1 => unit.to_string(),
+                _ => format!("{}^{}", unit, exponent),
+            })
+            .collect::<Vec<String>>();
+
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// Whether `expr` only combines names with products, quotients and constant powers, as the target
+/// of a conversion must. A leading `1 /` is allowed for reciprocal units such as `1/s`.
+fn is_unit(expr: &Expr) -> bool {
+    let is_exponent = |expr: &Expr| match &expr.kind {
+        ExprKind::Number(_) => true,
+        ExprKind::Prefix { operand, .. } => matches!(operand.kind, ExprKind::Number(_)),
+        _ => false,
+    };
+
+    match &expr.kind {
+        ExprKind::Identifier(_) => true,
+        ExprKind::Binary { op: '*', lhs, rhs } => is_unit(lhs) && is_unit(rhs),
+        ExprKind::Binary { op: '/', lhs, rhs } => {
+            (is_unit(lhs) || matches!(lhs.kind, ExprKind::Number(n) if n == 1.)) && is_unit(rhs)
+        }
+        ExprKind::Binary { op: '^', lhs, rhs } => is_unit(lhs) && is_exponent(rhs),
+        _ => false,
+    }
+}
+
+/// A number with a unit, as evaluated in units mode.
+///
+/// Quantities are stored in SI base units and displayed as such (`2.5 m s^-1`), except for the
+/// result of a conversion, which is displayed in the unit it was converted to (`2.5 m/s`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    value: f64,
+    dimension: Dimension,
+    /// Name and value of the unit the quantity was converted to, if any.
+    unit: Option<(String, f64)>,
+}
+
+impl Quantity {
+    fn new(value: f64, dimension: Dimension) -> Quantity {
+        Quantity {
+            value,
+            dimension,
+            unit: None,
+        }
+    }
+
+    fn number(value: f64) -> Quantity {
+        Quantity::new(value, NONE)
+    }
+
+    /// The value in SI base units.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimension.is_none()
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.unit {
+            Some((name, factor)) => write!(f, "{} {}", self.value / factor, name),
+            None if self.is_dimensionless() => write!(f, "{}", self.value),
+            None => write!(f, "{} {}", self.value, self.dimension),
+        }
+    }
+}
+
+/// Evaluates expressions over quantities with units, checking that their dimensions are
+/// compatible.
+#[derive(Debug, Clone)]
+pub struct UnitsBackend {
+    functions: FunctionRegistry,
+}
+
+impl Default for UnitsBackend {
+    fn default() -> UnitsBackend {
+        UnitsBackend {
+            functions: FunctionRegistry::builtin(),
+        }
+    }
+}
+
+impl UnitsBackend {
+    pub fn new() -> UnitsBackend {
+        UnitsBackend::default()
+    }
+}
+
+impl Backend for UnitsBackend {
+    type Value = Quantity;
+
+    fn literal(&self, text: &str) -> Result<Quantity, String> {
+        text.parse::<f64>()
+            .map(Quantity::number)
+            .map_err(|_| "unkown token".to_owned())
+    }
+
+    fn constant(&self, name: &str) -> Option<Quantity> {
+        match CONSTANTS.get(name) {
+            Some(x) => Some(Quantity::number(*x)),
+            None => unit(name).map(|(factor, dimension)| Quantity::new(factor, dimension)),
+        }
+    }
+
+    fn unary(&self, op: char, operand: Quantity) -> Result<Quantity, String> {
+        match op {
+            '+' => Ok(operand),
+            '-' => Ok(Quantity::new(-operand.value, operand.dimension)),
+            '!' if operand.is_dimensionless() => Ok(Quantity::number(math::fact(operand.value))),
+            '!' => Err(format!(
+                "the factorial expects a dimensionless number, got {}",
+                operand.dimension
+            )),
+            _ => unreachable!(),
+        }
+    }
+
+    fn binary(&self, op: char, lhs: Quantity, rhs: Quantity) -> Result<Quantity, String> {
+        let (a, b) = (lhs.dimension, rhs.dimension);
+
+        if matches!(op, '+' | '-' | '%') && a != b {
+            return Err(match op {
+                '+' => format!("cannot add {} and {}", a.describe(), b.describe()),
+                '-' => format!("cannot subtract {} from {}", b.describe(), a.describe()),
+                _ => format!(
+                    "cannot take the remainder of {} by {}",
+                    a.describe(),
+                    b.describe()
+                ),
+            });
+        }
+
+        let (x, y) = (lhs.value, rhs.value);
+
+        Ok(match op {
+            '+' => Quantity::new(x + y, a),
+            '-' => Quantity::new(x - y, a),
+            '*' => Quantity::new(x * y, a.zip(b, |a, b| a + b)),
+            '/' => Quantity::new(x / y, a.zip(b, |a, b| a - b)),
+            INTEGER_DIVISION => Quantity::new((x / y).trunc(), a.zip(b, |a, b| a - b)),
+            '%' => Quantity::new(x % y, a),
+            '^' => {
+                if !b.is_none() {
+                    return Err(format!("the exponent must be dimensionless, got {}", b));
+                }
+
+                match a.pow(y) {
+                    Some(dimension) => Quantity::new(x.powf(y), dimension),
+                    None => return Err(format!("({})^{} is not a whole unit", a, y)),
+                }
+            }
+            _ => unreachable!(),
+        })
+    }
+
+    fn arity(&self, name: &str) -> Option<Arity> {
+        self.functions
+            .get(name)
+            .filter(|f| f.native().is_some())
+            .map(|f| f.arity())
+    }
+
+    fn call(&self, name: &str, args: &[Quantity]) -> Result<Quantity, String> {
+        let values = args.iter().map(Quantity::value).collect::<Vec<f64>>();
+        let native = self.functions.get(name).and_then(|f| f.native()).unwrap();
+
+        // Roots are the only functions accepting quantities with a dimension
+        let dimension = match (name, args) {
+            ("sqrt", [x]) => x
+                .dimension
+                .pow(0.5)
+                .ok_or_else(|| format!("the square root of {} is not a whole unit", x.dimension))?,
+            ("nroot", [n, x]) if n.is_dimensionless() => {
+                x.dimension.pow(1. / n.value).ok_or_else(|| {
+                    format!(
+                        "the {}-th root of {} is not a whole unit",
+                        n.value, x.dimension
+                    )
+                })?
+            }
+            _ => match args.iter().find(|arg| !arg.is_dimensionless()) {
+                Some(arg) => {
+                    return Err(format!(
+                        "{} expects dimensionless arguments, got {}",
+                        name, arg.dimension
+                    ))
+                }
+                None => NONE,
+            },
+        };
+
+        native(&values).map(|value| Quantity::new(value, dimension))
+    }
+
+    fn convert(&self, value: Quantity, target: Quantity, unit: &str) -> Result<Quantity, String> {
+        if !parse_expression(unit).is_ok_and(|expr| is_unit(&expr)) {
+            return Err(format!(
+                "cannot convert to {}, which is not a unit such as m/s or km^2",
+                unit
+            ));
+        } else if value.dimension != target.dimension {
+            return Err(format!(
+                "cannot convert {} to {}",
+                value.dimension.describe(),
+                unit
+            ));
+        } else if target.value == 0. {
+            return Err(format!("cannot convert to {}, which is zero", unit));
+        }
+
+        Ok(Quantity {
+            unit: Some((unit.to_owned(), target.value)),
+            ..value
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::Session;
+
+    fn units(src: &str) -> String {
+        Session::new(UnitsBackend::new())
+            .evaluate(src)
+            .unwrap()
+            .to_string()
+    }
+
+    fn units_error(src: &str) -> String {
+        format!(
+            "{}",
+            Session::new(UnitsBackend::new())
+                .evaluate(src)
+                .expect_err("")
+        )
+    }
+
+    #[test]
+    fn units_lookup() {
+        assert_eq!(unit("m"), Some((1., dim(1, 0, 0, 0, 0, 0, 0))));
+        assert_eq!(unit("km"), Some((1e3, dim(1, 0, 0, 0, 0, 0, 0))));
+        assert_eq!(unit("dam"), Some((1e1, dim(1, 0, 0, 0, 0, 0, 0))));
+        assert_eq!(unit("µs"), Some((1e-6, dim(0, 0, 1, 0, 0, 0, 0))));
+        assert_eq!(unit("min"), Some((60., dim(0, 0, 1, 0, 0, 0, 0))));
+        assert_eq!(unit("Pa"), Some((1., dim(-1, 1, -2, 0, 0, 0, 0))));
+        assert_eq!(unit("kmin"), None);
+        assert_eq!(unit("x"), None);
+    }
+
+    #[test]
+    fn units_conversion() {
+        assert_eq!(units("3 km / 20 min in m/s"), "2.5 m/s");
+        assert_eq!(units("3 km / 20 min to km/h"), "9 km/h");
+        assert_eq!(units("1 mi in km"), "1.609344 km");
+        assert_eq!(units("2 h + 30 min in min"), "150 min");
+        assert_eq!(units("1 kWh in MJ"), "3.6 MJ");
+        assert_eq!(units("180 deg in rad"), format!("{} rad", PI));
+        assert_eq!(units("2 kHz in 1/s"), "2000 1/s");
+        assert_eq!(units("1 km^2 in m^2"), "1000000 m^2");
+        assert_eq!(units("sin(90 deg)"), "1");
+    }
+
+    #[test]
+    fn units_base() {
+        assert_eq!(units("3 km / 20 min"), "2.5 m s^-1");
+        assert_eq!(units("2 kg * 3 m / s^2"), "6 m kg s^-2");
+        assert_eq!(units("sqrt(16 m^2)"), "4 m");
+        assert_eq!(units("(1 m)^3 / 1 L"), "1000");
+        assert_eq!(units("x = 5 m; 2x"), "10 m");
+    }
+
+    #[test]
+    fn units_errors() {
+        assert_eq!(
+            units_error("1 + 1 m + 2 s"),
+            "error while parsing token \"1 + 1 m\" in expression \"1 + 1 m + 2 s\": cannot add a dimensionless number and m, 1 + 1 m <-- HERE"
+        );
+        assert_eq!(
+            units_error("1 m - 2 s"),
+            "error while parsing token \"1 m - 2 s\" in expression \"1 m - 2 s\": cannot subtract s from m, 1 m - 2 s <-- HERE"
+        );
+        assert_eq!(
+            units_error("3 m in s"),
+            "error while parsing token \"3 m in s\" in expression \"3 m in s\": cannot convert m to s, 3 m in s <-- HERE"
+        );
+        assert_eq!(
+            units_error("3 m in 2 m"),
+            "error while parsing token \"3 m in 2 m\" in expression \"3 m in 2 m\": cannot convert to 2 m, which is not a unit such as m/s or km^2, 3 m in 2 m <-- HERE"
+        );
+        assert!(units_error("3 m in m in cm").contains("conversions can't be chained"));
+        assert_eq!(
+            units_error("sin(2 m)"),
+            "error while parsing token \"sin(2 m)\" in expression \"sin(2 m)\": sin expects dimensionless arguments, got m, sin(2 m) <-- HERE"
+        );
+        assert_eq!(
+            units_error("sqrt(2 m)"),
+            "error while parsing token \"sqrt(2 m)\" in expression \"sqrt(2 m)\": the square root of m is not a whole unit, sqrt(2 m) <-- HERE"
+        );
+        assert_eq!(
+            units_error("2^(1 s)"),
+            "error while parsing token \"2^(1 s)\" in expression \"2^(1 s)\": the exponent must be dimensionless, got s, 2^(1 s) <-- HERE"
+        );
+    }
+}